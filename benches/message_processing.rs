@@ -1,6 +1,8 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId, Throughput};
 use vehicle_nn_core::*;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
 fn create_test_message(service: &str, size: usize) -> VehicleMessage {
     let mut message = VehicleMessage::new(
@@ -63,6 +65,26 @@ fn bench_message_serialization(c: &mut Criterion) {
                 })
             },
         );
+        
+        // 只读帧的解析（serde_json）与接入通道自有帧的原地解析（serde_json或simd-json，取决于feature）
+        group.bench_with_input(
+            BenchmarkId::new("parse_frame", size),
+            &json_str,
+            |b, json_str| {
+                b.iter(|| black_box(json::parse_frame(json_str.as_bytes()).unwrap()))
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new(format!("parse_in_place_{}", json::BACKEND), size),
+            &json_str,
+            |b, json_str| {
+                b.iter_batched(
+                    || json_str.as_bytes().to_vec(),
+                    |mut buffer| black_box(json::parse_in_place(&mut buffer).unwrap()),
+                    criterion::BatchSize::SmallInput,
+                )
+            },
+        );
     }
     
     group.finish();
@@ -129,12 +151,87 @@ fn bench_priority_determination(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_frame_buffers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame_buffers");
+    
+    let frame = serde_json::to_vec(&create_test_message("tracking", 10)).unwrap();
+    let pool = BufferPool::new(64, 8192);
+    
+    group.bench_function("allocate", |b| {
+        b.iter(|| {
+            let buffer = frame.clone();
+            black_box(&buffer);
+        })
+    });
+    
+    group.bench_function("pooled", |b| {
+        b.iter(|| {
+            let buffer = pool.acquire_copy(&frame);
+            black_box(&buffer);
+            pool.release(buffer);
+        })
+    });
+    
+    group.finish();
+}
+
+/// 接入路径基准中轮换使用的VIN数量
+const BENCH_VIN_COUNT: u64 = 64;
+
+fn bench_submit_message(c: &mut Criterion) {
+    let mut group = c.benchmark_group("submit_message");
+    group.throughput(Throughput::Elements(1));
+    
+    // 运行中的处理器配合空回调，覆盖解析、校验、去重、采样和入队的完整接入路径
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let processor = Arc::new(MessageProcessor::new());
+    processor.set_callback(Arc::new(|_| Ok(())));
+    let runner = processor.clone();
+    let handle = runtime.spawn(async move { runner.start().await });
+    runtime.block_on(async { tokio::time::sleep(Duration::from_millis(20)).await });
+    
+    for size in [1, 10, 100, 1000].iter() {
+        let data: HashMap<String, String> = (0..*size)
+            .map(|i| (format!("key_{}", i), format!("value_{}", i)))
+            .collect();
+        let data = serde_json::to_string(&data).unwrap();
+        let mut sequence = 0u64;
+        
+        group.bench_with_input(
+            BenchmarkId::new("end_to_end", size),
+            &data,
+            |b, data| {
+                b.iter_batched(
+                    || {
+                        // 在固定的VIN集合中轮换，与真实车队一样复用VIN相关状态；时间戳递增，避免被去重窗口拦截
+                        sequence += 1;
+                        format!(
+                            r#"{{"service": "tracking", "params": {{"vin": "BENCH_VIN_{}", "timestamp": {:.3}, "data": {}}}}}"#,
+                            sequence % BENCH_VIN_COUNT,
+                            1234567890.0 + sequence as f64 / 1000.0,
+                            data
+                        )
+                    },
+                    |frame| runtime.block_on(processor.submit_message(frame.as_bytes())).unwrap(),
+                    criterion::BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+    
+    group.finish();
+    processor.stop();
+    let _ = runtime.block_on(handle);
+}
+
 criterion_group!(
     benches,
     bench_message_creation,
     bench_message_serialization,
     bench_message_hash,
     bench_sampling_decision,
-    bench_priority_determination
+    bench_priority_determination,
+    bench_frame_buffers,
+    bench_submit_message
 );
 criterion_main!(benches);
\ No newline at end of file
@@ -1,7 +1,5 @@
 use vehicle_nn_core::*;
-use std::collections::HashMap;
 use tracing::{info, Level};
-use tracing_subscriber;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -65,16 +63,17 @@ async fn main() -> Result<()> {
     info!("  Drop rate: {:.2}%", stats.get_drop_rate() * 100.0);
     info!("  Avg processing time: {}μs", stats.avg_processing_time_us);
     info!("  Health status: {}", monitor.get_health_status().as_str());
+    info!("  Latency histogram:\n{}", monitor.render_latency_histogram());
     
     Ok(())
 }
 
 async fn process_message(message: &VehicleMessage) -> Result<()> {
     // 模拟不同类型消息的处理时间
-    let processing_time = match message.service.as_str() {
-        "tracking" => std::time::Duration::from_micros(500),  // 快速处理
-        "traj" => std::time::Duration::from_micros(100),      // 很快处理
-        _ => std::time::Duration::from_micros(300),           // 中等处理
+    let processing_time = match message.service_kind() {
+        Service::Tracking => std::time::Duration::from_micros(500),  // 快速处理
+        Service::Traj => std::time::Duration::from_micros(100),      // 很快处理
+        _ => std::time::Duration::from_micros(300),                  // 中等处理
     };
     
     tokio::time::sleep(processing_time).await;
@@ -2,7 +2,6 @@ use vehicle_nn_core::*;
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{info, warn, Level};
-use tracing_subscriber;
 
 /// 完整的车辆消息处理系统示例
 #[tokio::main]
@@ -17,8 +16,12 @@ async fn main() -> Result<()> {
     info!("🚗 Starting Vehicle NN Core Complete System Example");
     info!("📋 {}", get_library_info());
 
-    // 1. 创建消息处理器
-    let mut message_processor = MessageProcessor::new();
+    // 1. 创建消息处理器（超速告警由库内置检测，处理10k条消息后自动停止）
+    let message_processor = MessageProcessor::with_config(ProcessorConfig {
+        speed_alert_threshold: Some(50.0),
+        stop_after: Some(10_000),
+        ..Default::default()
+    });
     
     // 设置消息处理回调
     message_processor.set_callback(Arc::new(|message| {
@@ -27,24 +30,35 @@ async fn main() -> Result<()> {
     
     let processor_arc = Arc::new(message_processor);
     
+    // 订阅超速告警
+    let mut alerts = processor_arc.subscribe_alerts();
+    tokio::spawn(async move {
+        while let Ok(alert) = alerts.recv().await {
+            if let AlertKind::SpeedExceeded { speed, .. } = alert.kind {
+                let (x, y) = alert.position.unwrap_or_default();
+                info!("🚨 High speed detected: {:.1} km/h at ({:.2}, {:.2})", speed, x, y);
+            }
+        }
+    });
+    
     // 2. 创建Nanomsg客户端配置
-    let nanomsg_config = NanomsgConfig {
-        listen_url: "ipc:///tmp/vehicle_test.ipc".to_string(),
-        receive_timeout: Duration::from_millis(100),
-        reconnect_interval: Duration::from_secs(2),
-        max_reconnect_attempts: 5,
-        buffer_size: 4096,
-        batch_size: 50,
-        batch_timeout: Duration::from_millis(5),
-    };
+    let nanomsg_config = NanomsgConfig::builder()
+        .listen_url("ipc:///tmp/vehicle_test.ipc")
+        .receive_timeout(Duration::from_millis(100))
+        .reconnect_interval(Duration::from_secs(2))
+        .max_reconnect_attempts(5)
+        .buffer_size(4096)
+        .batch_size(50)
+        .batch_timeout(Duration::from_millis(5))
+        .build()?;
     
     // 3. 创建Nanomsg客户端
-    let nanomsg_client = NanomsgClient::new(nanomsg_config, processor_arc.clone());
+    let nanomsg_client = Arc::new(NanomsgClient::new(nanomsg_config, processor_arc.clone()));
     
     info!("🔧 System components initialized");
     
     // 4. 启动系统组件
-    let processor_handle = {
+    let mut processor_handle = {
         let processor = processor_arc.clone();
         tokio::spawn(async move {
             info!("🚀 Starting message processor...");
@@ -54,12 +68,15 @@ async fn main() -> Result<()> {
         })
     };
     
-    let client_handle = tokio::spawn(async move {
-        info!("🌐 Starting Nanomsg client...");
-        if let Err(e) = nanomsg_client.start().await {
-            warn!("Nanomsg client error: {}", e);
-        }
-    });
+    let client_handle = {
+        let client = nanomsg_client.clone();
+        tokio::spawn(async move {
+            info!("🌐 Starting Nanomsg client...");
+            if let Err(e) = client.start().await {
+                warn!("Nanomsg client error: {}", e);
+            }
+        })
+    };
     
     // 5. 启动监控任务
     let monitor_handle = {
@@ -69,19 +86,24 @@ async fn main() -> Result<()> {
         })
     };
     
-    // 6. 模拟运行一段时间
-    info!("⏱️  System running for 30 seconds...");
-    tokio::time::sleep(Duration::from_secs(30)).await;
+    // 6. 处理10k条消息后结束，最多运行5分钟
+    info!("⏱️  System running until 10000 messages are processed...");
+    let completed = tokio::select! {
+        _ = &mut processor_handle => true,
+        _ = tokio::time::sleep(Duration::from_secs(300)) => false,
+    };
     
     // 7. 优雅关闭
     info!("🛑 Shutting down system...");
     
-    // 停止处理器
-    processor_arc.stop();
+    // 先停止客户端，等待已接收的消息处理完成后再停止处理器
+    if let Err(e) = shutdown(&nanomsg_client, &processor_arc, Duration::from_secs(5)).await {
+        warn!("⚠️  Shutdown did not drain cleanly: {}", e);
+    }
     
     // 等待任务完成或超时
     tokio::select! {
-        _ = processor_handle => info!("✅ Message processor stopped"),
+        _ = processor_handle, if !completed => info!("✅ Message processor stopped"),
         _ = client_handle => info!("✅ Nanomsg client stopped"),
         _ = monitor_handle => info!("✅ Monitor stopped"),
         _ = tokio::time::sleep(Duration::from_secs(5)) => {
@@ -101,14 +123,14 @@ async fn main() -> Result<()> {
 fn handle_vehicle_message(message: VehicleMessage) -> Result<()> {
     let priority = MessagePriority::from_service(&message.service);
     
-    match message.service.as_str() {
-        "tracking" => handle_tracking_message(&message)?,
-        "route" => handle_route_message(&message)?,
-        "error_info" => handle_error_message(&message)?,
-        "traj" => handle_trajectory_message(&message)?,
-        "moving_obj" => handle_moving_object_message(&message)?,
-        "vcc" => handle_vcc_message(&message)?,
-        "device" => handle_device_message(&message)?,
+    match message.service_kind() {
+        Service::Tracking => handle_tracking_message(&message)?,
+        Service::Route => handle_route_message(&message)?,
+        Service::ErrorInfo => handle_error_message(&message)?,
+        Service::Traj => handle_trajectory_message(&message)?,
+        Service::MovingObj => handle_moving_object_message(&message)?,
+        Service::Vcc => handle_vcc_message(&message)?,
+        Service::Device => handle_device_message(&message)?,
         _ => handle_unknown_message(&message)?,
     }
     
@@ -131,12 +153,9 @@ fn handle_tracking_message(message: &VehicleMessage) -> Result<()> {
         if let Some(obj) = data.as_object() {
             let x = obj.get("x").and_then(|v| v.as_f64()).unwrap_or(0.0);
             let y = obj.get("y").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let speed = obj.get("speed").and_then(|v| v.as_f64()).unwrap_or(0.0);
             
-            // 模拟跟踪数据处理
-            if speed > 50.0 {
-                info!("🚨 High speed detected: {:.1} km/h at ({:.2}, {:.2})", speed, x, y);
-            }
+            // 模拟跟踪数据处理（超速检测由处理器的告警通道完成）
+            tracing::debug!("Tracking position for {}: ({:.2}, {:.2})", message.vin, x, y);
         }
     }
     Ok(())
@@ -160,7 +179,7 @@ fn handle_error_message(message: &VehicleMessage) -> Result<()> {
 }
 
 /// 处理轨迹消息
-fn handle_trajectory_message(message: &VehicleMessage) -> Result<()> {
+fn handle_trajectory_message(_message: &VehicleMessage) -> Result<()> {
     // 轨迹消息处理很快
     tokio::task::block_in_place(|| {
         std::thread::sleep(Duration::from_micros(100));
@@ -169,7 +188,7 @@ fn handle_trajectory_message(message: &VehicleMessage) -> Result<()> {
 }
 
 /// 处理移动对象消息
-fn handle_moving_object_message(message: &VehicleMessage) -> Result<()> {
+fn handle_moving_object_message(_message: &VehicleMessage) -> Result<()> {
     // 移动对象消息处理
     tokio::task::block_in_place(|| {
         std::thread::sleep(Duration::from_micros(200));
@@ -184,7 +203,7 @@ fn handle_vcc_message(message: &VehicleMessage) -> Result<()> {
 }
 
 /// 处理设备消息
-fn handle_device_message(message: &VehicleMessage) -> Result<()> {
+fn handle_device_message(_message: &VehicleMessage) -> Result<()> {
     // 设备消息处理
     Ok(())
 }
@@ -199,7 +218,7 @@ fn handle_unknown_message(message: &VehicleMessage) -> Result<()> {
 async fn monitor_system_performance(processor: Arc<MessageProcessor>) {
     info!("📊 Starting system performance monitor");
     
-    let mut last_stats = processor.get_stats();
+    let monitor = processor.performance_monitor();
     let mut report_count = 0;
     
     loop {
@@ -212,22 +231,16 @@ async fn monitor_system_performance(processor: Arc<MessageProcessor>) {
         let current_stats = processor.get_stats();
         report_count += 1;
         
-        // 计算增量统计
-        let msg_delta = current_stats.messages_received - last_stats.messages_received;
-        let processed_delta = current_stats.messages_processed - last_stats.messages_processed;
-        let dropped_delta = current_stats.messages_dropped - last_stats.messages_dropped;
-        
-        // 计算速率 (消息/秒)
-        let receive_rate = msg_delta as f64 / 5.0;
-        let process_rate = processed_delta as f64 / 5.0;
+        // 最近10秒的滑动窗口吞吐量
+        let throughput = monitor.get_throughput();
         
         info!(
             "📈 Performance Report #{} - Receive: {:.1}/s, Process: {:.1}/s, \
-             Dropped: {}, Queue: {}, Avg Time: {}μs",
+             Dropped: {:.1}/s, Queue: {}, Avg Time: {}μs",
             report_count,
-            receive_rate,
-            process_rate,
-            dropped_delta,
+            throughput.received_per_sec,
+            throughput.processed_per_sec,
+            throughput.dropped_per_sec,
             current_stats.queue_size,
             current_stats.avg_processing_time_us
         );
@@ -241,11 +254,9 @@ async fn monitor_system_performance(processor: Arc<MessageProcessor>) {
             warn!("📦 Large queue size detected: {}", current_stats.queue_size);
         }
         
-        if dropped_delta > 10 {
-            warn!("💧 High drop rate detected: {} messages in 5s", dropped_delta);
+        if throughput.dropped_per_sec > 2.0 {
+            warn!("💧 High drop rate detected: {:.1} messages/s", throughput.dropped_per_sec);
         }
-        
-        last_stats = current_stats;
     }
     
     info!("📊 Performance monitor stopped");
@@ -262,6 +273,7 @@ fn print_final_statistics(stats: &ProcessingStats) {
     println!("⚡ Avg Processing Time:  {:>7}μs", stats.avg_processing_time_us);
     println!("🚀 Processing Rate:      {:>7.1}/s", stats.get_processing_rate());
     println!("📦 Final Queue Size:     {:>10}", stats.queue_size);
+    println!("🎯 Bounded Run Complete: {:>10}", stats.is_completed());
     
     // 性能评级
     let performance_grade = if stats.get_drop_rate() < 0.01 && stats.avg_processing_time_us < 1000 {
@@ -0,0 +1,199 @@
+use crate::types::{Service, VehicleMessage};
+
+use std::sync::Arc;
+use parking_lot::RwLock;
+
+/// 内置车速告警规则名称
+pub const SPEED_THRESHOLD_RULE: &str = "speed_threshold";
+
+/// 告警规则：对处理成功的消息求值，匹配时返回告警
+pub type AlertRule = Arc<dyn Fn(&VehicleMessage) -> Option<Alert> + Send + Sync>;
+
+/// 告警类型
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertKind {
+    /// 车速超过阈值
+    SpeedExceeded {
+        /// 实际车速
+        speed: f64,
+        /// 配置的阈值
+        threshold: f64,
+    },
+    /// 自定义规则告警
+    Custom {
+        /// 告警详情
+        detail: String,
+    },
+}
+
+/// 由消息派生的告警
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    /// 产生告警的规则名称
+    pub rule: String,
+    /// 车辆VIN码
+    pub vin: String,
+    /// 服务类型
+    pub service: String,
+    /// 消息时间戳
+    pub timestamp: f64,
+    /// 车辆位置 (x, y)
+    pub position: Option<(f64, f64)>,
+    /// 告警类型
+    pub kind: AlertKind,
+}
+
+impl Alert {
+    /// 为消息创建自定义告警，规则名称由规则引擎填充
+    pub fn custom(message: &VehicleMessage, detail: impl Into<String>) -> Alert {
+        Alert {
+            rule: String::new(),
+            vin: message.vin.clone(),
+            service: message.service.clone(),
+            timestamp: message.timestamp,
+            position: None,
+            kind: AlertKind::Custom { detail: detail.into() },
+        }
+    }
+
+    /// 检查tracking消息的`data.speed`是否超过阈值
+    pub fn speed_exceeded(message: &VehicleMessage, threshold: f64) -> Option<Alert> {
+        if message.service_kind() != Service::Tracking {
+            return None;
+        }
+
+        let data = message.data()?;
+        let speed = data.get("speed").and_then(|v| v.as_f64())?;
+        if speed <= threshold {
+            return None;
+        }
+
+        let position = match (
+            data.get("x").and_then(|v| v.as_f64()),
+            data.get("y").and_then(|v| v.as_f64()),
+        ) {
+            (Some(x), Some(y)) => Some((x, y)),
+            _ => None,
+        };
+
+        Some(Alert {
+            rule: SPEED_THRESHOLD_RULE.to_string(),
+            vin: message.vin.clone(),
+            service: message.service.clone(),
+            timestamp: message.timestamp,
+            position,
+            kind: AlertKind::SpeedExceeded { speed, threshold },
+        })
+    }
+}
+
+/// 告警规则引擎：按注册顺序对消息求值所有命名规则
+#[derive(Default)]
+pub struct RuleEngine {
+    rules: RwLock<Vec<(String, AlertRule)>>,
+}
+
+impl RuleEngine {
+    /// 创建空的规则引擎
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册规则，同名规则会被替换
+    pub fn add_rule(&self, name: &str, rule: AlertRule) {
+        let mut rules = self.rules.write();
+        if let Some(entry) = rules.iter_mut().find(|(existing, _)| existing == name) {
+            entry.1 = rule;
+        } else {
+            rules.push((name.to_string(), rule));
+        }
+    }
+
+    /// 移除规则，返回规则此前是否存在
+    pub fn remove_rule(&self, name: &str) -> bool {
+        let mut rules = self.rules.write();
+        let before = rules.len();
+        rules.retain(|(existing, _)| existing != name);
+        rules.len() != before
+    }
+
+    /// 获取已注册的规则名称
+    pub fn rule_names(&self) -> Vec<String> {
+        self.rules.read().iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// 检查是否没有注册任何规则
+    pub fn is_empty(&self) -> bool {
+        self.rules.read().is_empty()
+    }
+
+    /// 对消息求值所有规则，返回匹配的告警（规则名称已填充）
+    pub fn evaluate(&self, message: &VehicleMessage) -> Vec<Alert> {
+        self.rules
+            .read()
+            .iter()
+            .filter_map(|(name, rule)| {
+                rule(message).map(|mut alert| {
+                    alert.rule = name.clone();
+                    alert
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracking_message(data: serde_json::Value) -> VehicleMessage {
+        let mut message = VehicleMessage::new(
+            "tracking".to_string(),
+            "TEST_VIN_123".to_string(),
+            1234567890.0,
+        );
+        message.params.insert("data".to_string(), data);
+        message
+    }
+
+    #[test]
+    fn test_speed_exceeded() {
+        let message = tracking_message(serde_json::json!({"x": 1.0, "y": 2.0, "speed": 65.0}));
+
+        let alert = Alert::speed_exceeded(&message, 50.0).unwrap();
+        assert_eq!(alert.vin, "TEST_VIN_123");
+        assert_eq!(alert.position, Some((1.0, 2.0)));
+        assert_eq!(alert.kind, AlertKind::SpeedExceeded { speed: 65.0, threshold: 50.0 });
+
+        // 未超过阈值不告警
+        assert!(Alert::speed_exceeded(&message, 80.0).is_none());
+    }
+
+    #[test]
+    fn test_speed_alert_ignores_other_messages() {
+        let no_speed = tracking_message(serde_json::json!({"x": 1.0, "y": 2.0}));
+        assert!(Alert::speed_exceeded(&no_speed, 50.0).is_none());
+
+        let mut traj = tracking_message(serde_json::json!({"speed": 90.0}));
+        traj.service = "traj".to_string();
+        assert!(Alert::speed_exceeded(&traj, 50.0).is_none());
+    }
+
+    #[test]
+    fn test_rule_engine() {
+        let engine = RuleEngine::new();
+        engine.add_rule("always", Arc::new(|message| Some(Alert::custom(message, "matched"))));
+        engine.add_rule("never", Arc::new(|_| None));
+        assert_eq!(engine.rule_names(), vec!["always", "never"]);
+
+        let message = tracking_message(serde_json::json!({"speed": 10.0}));
+        let alerts = engine.evaluate(&message);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].rule, "always");
+        assert_eq!(alerts[0].kind, AlertKind::Custom { detail: "matched".to_string() });
+
+        assert!(engine.remove_rule("always"));
+        assert!(!engine.remove_rule("always"));
+        assert!(engine.evaluate(&message).is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use parking_lot::Mutex;
+
+/// 缓冲区池统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BufferPoolStats {
+    /// 从池中复用的次数
+    pub hits: u64,
+    /// 池为空时新分配的次数
+    pub misses: u64,
+    /// 因池已满或容量过大而丢弃的次数
+    pub discarded: u64,
+}
+
+/// 帧缓冲区池，回收接入路径上的`Vec<u8>`以减少高频分配
+pub struct BufferPool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+    max_buffers: usize,
+    buffer_capacity: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    discarded: AtomicU64,
+}
+
+impl BufferPool {
+    /// 创建缓冲区池，最多缓存`max_buffers`个初始容量为`buffer_capacity`的缓冲区
+    pub fn new(max_buffers: usize, buffer_capacity: usize) -> Self {
+        Self {
+            buffers: Mutex::new(Vec::with_capacity(max_buffers)),
+            max_buffers,
+            buffer_capacity,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            discarded: AtomicU64::new(0),
+        }
+    }
+
+    /// 取出一个空缓冲区，池为空时新分配
+    pub fn acquire(&self) -> Vec<u8> {
+        if let Some(buffer) = self.buffers.lock().pop() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return buffer;
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        Vec::with_capacity(self.buffer_capacity)
+    }
+
+    /// 取出缓冲区并复制数据
+    pub fn acquire_copy(&self, data: &[u8]) -> Vec<u8> {
+        let mut buffer = self.acquire();
+        buffer.extend_from_slice(data);
+        buffer
+    }
+
+    /// 归还缓冲区；池已满或缓冲区扩容过大时直接丢弃，避免长期占用内存
+    pub fn release(&self, mut buffer: Vec<u8>) {
+        if buffer.capacity() > self.buffer_capacity * 4 {
+            self.discarded.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        let mut buffers = self.buffers.lock();
+        if buffers.len() >= self.max_buffers {
+            self.discarded.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        buffer.clear();
+        buffers.push(buffer);
+    }
+
+    /// 池中可复用的缓冲区数量
+    pub fn len(&self) -> usize {
+        self.buffers.lock().len()
+    }
+
+    /// 检查池是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 获取复用统计
+    pub fn stats(&self) -> BufferPoolStats {
+        BufferPoolStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            discarded: self.discarded.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_reuse() {
+        let pool = BufferPool::new(2, 64);
+
+        let buffer = pool.acquire_copy(b"frame");
+        assert_eq!(buffer, b"frame");
+        pool.release(buffer);
+        assert_eq!(pool.len(), 1);
+
+        let buffer = pool.acquire();
+        assert!(buffer.is_empty());
+        assert!(buffer.capacity() >= 64);
+        assert_eq!(pool.stats(), BufferPoolStats { hits: 1, misses: 1, discarded: 0 });
+        pool.release(buffer);
+    }
+
+    #[test]
+    fn test_release_limits() {
+        let pool = BufferPool::new(1, 64);
+        pool.release(Vec::with_capacity(64));
+        pool.release(Vec::with_capacity(64));
+        assert_eq!(pool.len(), 1);
+
+        // 扩容过大的缓冲区不回收
+        let pool = BufferPool::new(4, 64);
+        pool.release(Vec::with_capacity(1024));
+        assert!(pool.is_empty());
+        assert_eq!(pool.stats().discarded, 1);
+    }
+}
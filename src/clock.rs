@@ -0,0 +1,96 @@
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 时间源，便于测试时控制时间
+pub trait Clock: Send + Sync + Debug {
+    /// 当前单调时间
+    fn now(&self) -> Instant;
+
+    /// 当前Unix时间戳（秒）
+    fn unix_timestamp(&self) -> f64;
+}
+
+/// 系统时间源
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn unix_timestamp(&self) -> f64 {
+        chrono::Utc::now().timestamp_micros() as f64 / 1_000_000.0
+    }
+}
+
+/// 可手动推进的模拟时间源
+#[derive(Debug)]
+pub struct MockClock {
+    base: Instant,
+    base_unix: f64,
+    offset_nanos: AtomicU64,
+}
+
+impl MockClock {
+    /// 默认起始Unix时间戳
+    pub const DEFAULT_UNIX_START: f64 = 1_700_000_000.0;
+
+    /// 创建模拟时间源，Unix时间从默认起点开始
+    pub fn new() -> Self {
+        Self::starting_at(Self::DEFAULT_UNIX_START)
+    }
+
+    /// 创建从指定Unix时间戳开始的模拟时间源
+    pub fn starting_at(unix_timestamp: f64) -> Self {
+        Self {
+            base: Instant::now(),
+            base_unix: unix_timestamp,
+            offset_nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// 推进时间
+    pub fn advance(&self, duration: Duration) {
+        self.offset_nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::SeqCst);
+    }
+
+    /// 已推进的总时长
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.offset_nanos.load(Ordering::SeqCst))
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.base + self.elapsed()
+    }
+
+    fn unix_timestamp(&self) -> f64 {
+        self.base_unix + self.elapsed().as_secs_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_advance() {
+        let clock = MockClock::starting_at(1000.0);
+        let start = clock.now();
+        assert_eq!(clock.unix_timestamp(), 1000.0);
+
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(clock.now().duration_since(start), Duration::from_millis(1500));
+        assert_eq!(clock.unix_timestamp(), 1001.5);
+    }
+}
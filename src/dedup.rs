@@ -0,0 +1,179 @@
+use crate::clock::{Clock, SystemClock};
+
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use dashmap::DashMap;
+
+/// 去重存储后端，可替换为跨进程共享的实现（如Redis）
+pub trait DedupStore: Send + Sync + Debug {
+    /// 记录消息hash，返回窗口内是否已出现过
+    ///
+    /// 窗口内的重复不刷新首次出现时间
+    fn seen(&self, hash: u64, window: Duration) -> bool;
+
+    /// 清理超过保留时长的条目，返回清理数量；自带过期机制的后端无需实现
+    ///
+    /// `limit`限制单次清理的条目数，剩余的过期条目留待下次清理
+    fn evict_expired(&self, _ttl: Duration, _limit: Option<usize>) -> usize {
+        0
+    }
+
+    /// 列出缓存中的hash及其距首次出现的时长，用于调试；不支持枚举的后端返回空
+    fn snapshot(&self) -> Vec<(u64, Duration)> {
+        Vec::new()
+    }
+
+    /// 清空缓存，之后到达的消息都不视为重复
+    fn clear(&self) {}
+}
+
+/// 进程内去重存储 (hash -> last_seen_time)
+#[derive(Debug)]
+pub struct InMemoryDedupStore {
+    entries: DashMap<u64, Instant>,
+    clock: Arc<dyn Clock>,
+}
+
+impl InMemoryDedupStore {
+    /// 使用系统时间创建
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// 使用指定时间源创建
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            entries: DashMap::new(),
+            clock,
+        }
+    }
+
+    /// 当前缓存的条目数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 检查缓存是否为空
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Default for InMemoryDedupStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DedupStore for InMemoryDedupStore {
+    fn seen(&self, hash: u64, window: Duration) -> bool {
+        let now = self.clock.now();
+
+        if let Some(last_seen) = self.entries.get(&hash) {
+            if now.duration_since(*last_seen) < window {
+                return true;
+            }
+        }
+
+        self.entries.insert(hash, now);
+        false
+    }
+
+    fn evict_expired(&self, ttl: Duration, limit: Option<usize>) -> usize {
+        let now = self.clock.now();
+        let expired = |last_seen: &Instant| now.duration_since(*last_seen) >= ttl;
+
+        let Some(limit) = limit else {
+            let mut removed_count = 0;
+            self.entries.retain(|_, last_seen| {
+                let should_keep = !expired(last_seen);
+                if !should_keep {
+                    removed_count += 1;
+                }
+                should_keep
+            });
+            return removed_count;
+        };
+
+        // 先在读锁下收集至多limit个过期条目，再逐个删除，避免长时间持有分片写锁
+        let keys: Vec<u64> = self
+            .entries
+            .iter()
+            .filter(|entry| expired(entry.value()))
+            .map(|entry| *entry.key())
+            .take(limit)
+            .collect();
+        keys.into_iter()
+            .filter(|key| self.entries.remove_if(key, |_, last_seen| expired(last_seen)).is_some())
+            .count()
+    }
+
+    fn snapshot(&self) -> Vec<(u64, Duration)> {
+        let now = self.clock.now();
+        self.entries
+            .iter()
+            .map(|entry| (*entry.key(), now.saturating_duration_since(*entry.value())))
+            .collect()
+    }
+
+    fn clear(&self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn test_in_memory_expiry() {
+        let clock = Arc::new(MockClock::new());
+        let store = InMemoryDedupStore::with_clock(clock.clone());
+        let window = Duration::from_secs(1);
+
+        assert!(!store.seen(1, window));
+        clock.advance(Duration::from_secs(200));
+        assert!(!store.seen(2, window));
+
+        clock.advance(Duration::from_secs(100));
+        assert_eq!(store.evict_expired(Duration::from_secs(300), None), 1);
+        assert_eq!(store.len(), 1);
+        // 条目1已被清理，再次出现不视为重复
+        assert!(!store.seen(1, Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn test_snapshot_and_clear() {
+        let clock = Arc::new(MockClock::new());
+        let store = InMemoryDedupStore::with_clock(clock.clone());
+        store.seen(7, Duration::from_secs(1));
+        clock.advance(Duration::from_secs(3));
+        store.seen(9, Duration::from_secs(1));
+
+        let mut snapshot = store.snapshot();
+        snapshot.sort();
+        assert_eq!(snapshot, vec![(7, Duration::from_secs(3)), (9, Duration::ZERO)]);
+
+        store.clear();
+        assert!(store.snapshot().is_empty());
+        assert!(!store.seen(7, Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn test_eviction_limit_per_pass() {
+        let clock = Arc::new(MockClock::new());
+        let store = InMemoryDedupStore::with_clock(clock.clone());
+        for hash in 0..5 {
+            store.seen(hash, Duration::from_secs(1));
+        }
+        clock.advance(Duration::from_secs(10));
+
+        let ttl = Duration::from_secs(5);
+        assert_eq!(store.evict_expired(ttl, Some(2)), 2);
+        assert_eq!(store.evict_expired(ttl, Some(2)), 2);
+        assert_eq!(store.evict_expired(ttl, Some(2)), 1);
+        assert!(store.is_empty());
+    }
+}
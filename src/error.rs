@@ -15,29 +15,116 @@ pub enum VehicleError {
     #[error("Invalid message format: {0}")]
     InvalidMessage(String),
     
-    #[error("Nanomsg error: {0}")]
-    NanomsgError(String),
+    #[error("MessagePack error: {0}")]
+    MsgPackError(String),
+    
+    #[error("Nanomsg error ({kind:?}): {msg}")]
+    NanomsgError {
+        kind: NanomsgErrorKind,
+        msg: String,
+    },
     
     #[error("Processing timeout")]
     Timeout,
     
+    #[error("No message available")]
+    WouldBlock,
+    
     #[error("Service not found: {0}")]
     ServiceNotFound(String),
     
     #[error("Configuration error: {0}")]
     ConfigError(String),
+    
+    #[error("{} of {total} callbacks failed: {}", errors.len(), join_errors(errors))]
+    CallbacksFailed {
+        /// 注册的回调总数
+        total: usize,
+        /// 失败回调的序号及错误
+        errors: Vec<(usize, VehicleError)>,
+    },
+}
+
+fn join_errors(errors: &[(usize, VehicleError)]) -> String {
+    errors
+        .iter()
+        .map(|(index, e)| format!("#{}: {}", index, e))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Nanomsg错误类别，用于区分瞬时错误和致命错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NanomsgErrorKind {
+    /// 消息流已结束
+    EndOfStream,
+    /// socket未连接或不可用
+    NotConnected,
+    /// 连接被对端重置
+    ConnectionReset,
+    /// 绑定地址失败
+    BindFailed,
+    /// 多次重试后仍无法建立连接
+    ConnectFailed,
+    /// 长度前缀帧无效，字节流已错位
+    InvalidFrame,
+}
+
+impl NanomsgErrorKind {
+    /// 检查该类错误是否可通过重试或重连恢复
+    pub fn is_recoverable(&self) -> bool {
+        matches!(
+            self,
+            NanomsgErrorKind::EndOfStream
+                | NanomsgErrorKind::NotConnected
+                | NanomsgErrorKind::ConnectionReset
+                | NanomsgErrorKind::InvalidFrame
+        )
+    }
 }
 
 /// 统一的Result类型
 pub type Result<T> = std::result::Result<T, VehicleError>;
 
 impl VehicleError {
+    /// 创建Nanomsg错误
+    pub fn nanomsg(kind: NanomsgErrorKind, msg: impl Into<String>) -> Self {
+        VehicleError::NanomsgError { kind, msg: msg.into() }
+    }
+    
     /// 检查是否为可恢复的错误
     pub fn is_recoverable(&self) -> bool {
-        matches!(self, 
-            VehicleError::QueueFull | 
-            VehicleError::Timeout |
-            VehicleError::NanomsgError(_)
-        )
+        match self {
+            VehicleError::QueueFull | VehicleError::Timeout | VehicleError::WouldBlock => true,
+            VehicleError::NanomsgError { kind, .. } => kind.is_recoverable(),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_nanomsg_error_recoverability() {
+        assert!(VehicleError::WouldBlock.is_recoverable());
+        
+        let transient = VehicleError::nanomsg(NanomsgErrorKind::ConnectionReset, "Connection reset");
+        assert!(transient.is_recoverable());
+        assert_eq!(transient.to_string(), "Nanomsg error (ConnectionReset): Connection reset");
+        
+        let fatal = VehicleError::nanomsg(NanomsgErrorKind::BindFailed, "Address in use");
+        assert!(!fatal.is_recoverable());
+        assert!(!VehicleError::InvalidMessage("bad".to_string()).is_recoverable());
+    }
+    
+    #[test]
+    fn test_callbacks_failed_message() {
+        let error = VehicleError::CallbacksFailed {
+            total: 3,
+            errors: vec![(0, VehicleError::Timeout), (2, VehicleError::QueueFull)],
+        };
+        assert_eq!(error.to_string(), "2 of 3 callbacks failed: #0: Processing timeout; #2: Message queue full");
     }
 }
\ No newline at end of file
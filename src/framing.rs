@@ -0,0 +1,132 @@
+use crate::error::{NanomsgErrorKind, Result, VehicleError};
+
+/// 长度前缀的字节数（4字节大端）
+const LENGTH_PREFIX_BYTES: usize = 4;
+
+/// 单帧允许的最大长度，超出视为流已错位
+pub const DEFAULT_MAX_FRAME_BYTES: usize = 16 * 1024 * 1024;
+
+/// 接收数据的分帧方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FramingMode {
+    /// 每次接收即一条消息（nanomsg消息边界）
+    #[default]
+    MessageBoundary,
+    /// 4字节大端长度前缀 + 消息体，一次接收可包含多条或半条消息
+    LengthPrefixed,
+}
+
+/// 长度前缀帧解码器，缓存跨接收调用的不完整帧
+#[derive(Debug)]
+pub struct LengthPrefixedDecoder {
+    pending: Vec<u8>,
+    // pending中尚未消费数据的起始位置
+    start: usize,
+    max_frame_bytes: usize,
+}
+
+impl Default for LengthPrefixedDecoder {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_FRAME_BYTES)
+    }
+}
+
+impl LengthPrefixedDecoder {
+    /// 创建解码器
+    pub fn new(max_frame_bytes: usize) -> Self {
+        Self {
+            pending: Vec::new(),
+            start: 0,
+            max_frame_bytes,
+        }
+    }
+
+    /// 追加接收到的数据
+    pub fn extend(&mut self, data: &[u8]) {
+        // 丢弃已消费的数据，避免缓冲区无限增长
+        if self.start > 0 {
+            self.pending.drain(..self.start);
+            self.start = 0;
+        }
+        self.pending.extend_from_slice(data);
+    }
+
+    /// 取出下一条完整帧，数据不足时返回None
+    ///
+    /// 长度超出上限时清空缓冲区并返回错误，由调用方重建连接
+    pub fn next_frame(&mut self) -> Result<Option<&[u8]>> {
+        let available = &self.pending[self.start..];
+        let Some(prefix) = available.first_chunk::<LENGTH_PREFIX_BYTES>() else {
+            return Ok(None);
+        };
+
+        let frame_len = u32::from_be_bytes(*prefix) as usize;
+        if frame_len > self.max_frame_bytes {
+            self.clear();
+            return Err(VehicleError::nanomsg(
+                NanomsgErrorKind::InvalidFrame,
+                format!("Frame length {} exceeds limit {}", frame_len, self.max_frame_bytes),
+            ));
+        }
+        if available.len() < LENGTH_PREFIX_BYTES + frame_len {
+            return Ok(None);
+        }
+
+        let frame_start = self.start + LENGTH_PREFIX_BYTES;
+        self.start = frame_start + frame_len;
+        Ok(Some(&self.pending[frame_start..self.start]))
+    }
+
+    /// 缓存中尚未组成完整帧的字节数
+    pub fn pending_len(&self) -> usize {
+        self.pending.len() - self.start
+    }
+
+    /// 清空缓存（如连接断开时丢弃半帧）
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.start = 0;
+    }
+}
+
+/// 为消息体添加4字节大端长度前缀
+pub fn encode_length_prefixed(payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(LENGTH_PREFIX_BYTES + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_spanning_receives() {
+        let mut stream = encode_length_prefixed(b"first");
+        stream.extend(encode_length_prefixed(b""));
+        stream.extend(encode_length_prefixed(b"second"));
+
+        let mut decoder = LengthPrefixedDecoder::default();
+        decoder.extend(&stream[..7]);
+        assert_eq!(decoder.next_frame().unwrap(), None);
+
+        decoder.extend(&stream[7..15]);
+        assert_eq!(decoder.next_frame().unwrap(), Some(&b"first"[..]));
+        assert_eq!(decoder.next_frame().unwrap(), Some(&b""[..]));
+        assert_eq!(decoder.next_frame().unwrap(), None);
+        assert_eq!(decoder.pending_len(), 2);
+
+        decoder.extend(&stream[15..]);
+        assert_eq!(decoder.next_frame().unwrap(), Some(&b"second"[..]));
+        assert_eq!(decoder.pending_len(), 0);
+    }
+
+    #[test]
+    fn test_oversize_frame_rejected() {
+        let mut decoder = LengthPrefixedDecoder::new(8);
+        decoder.extend(&encode_length_prefixed(b"too long payload"));
+        assert!(decoder.next_frame().is_err());
+        assert_eq!(decoder.pending_len(), 0);
+    }
+}
@@ -0,0 +1,38 @@
+use crate::error::{Result, VehicleError};
+
+/// 原地解析使用的JSON后端名称
+pub const BACKEND: &str = if cfg!(feature = "simd-json") { "simd-json" } else { "serde_json" };
+
+/// 解析只读的接入帧；simd-json需要可写缓冲区，只读帧始终使用serde_json，避免复制
+pub fn parse_frame(raw_data: &[u8]) -> Result<serde_json::Value> {
+    serde_json::from_slice(raw_data).map_err(VehicleError::JsonError)
+}
+
+/// 原地解析自有的接入帧，解析过程中会修改缓冲区内容
+#[cfg(feature = "simd-json")]
+pub fn parse_in_place(buffer: &mut [u8]) -> Result<serde_json::Value> {
+    simd_json::serde::from_slice(buffer)
+        .map_err(|e| VehicleError::InvalidMessage(format!("Invalid JSON: {}", e)))
+}
+
+/// 原地解析自有的接入帧，解析过程中会修改缓冲区内容
+#[cfg(not(feature = "simd-json"))]
+pub fn parse_in_place(buffer: &mut [u8]) -> Result<serde_json::Value> {
+    parse_frame(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_frame_preserves_order() {
+        let raw = br#"{"service": "tracking", "params": {"vin": "V", "b": 1, "a": 2}}"#;
+        for value in [parse_frame(raw).unwrap(), parse_in_place(&mut raw.to_vec()).unwrap()] {
+            let keys: Vec<&str> = value["params"].as_object().unwrap().keys().map(|k| k.as_str()).collect();
+            assert_eq!(keys, ["vin", "b", "a"]);
+        }
+        assert!(parse_frame(b"{not json").is_err());
+        assert!(parse_in_place(&mut b"{not json".to_vec()).is_err());
+    }
+}
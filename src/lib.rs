@@ -6,17 +6,67 @@ pub mod types;
 pub mod message_processor;
 pub mod nanomsg_client;
 pub mod performance;
+pub mod metrics;
 pub mod error;
+pub mod alerts;
+pub mod sampling_watcher;
+pub mod clock;
+pub mod buffer_pool;
+pub mod dedup;
+pub mod sequence;
+pub mod ordering;
+pub mod presence;
+pub mod json;
+pub mod router;
+pub mod transport;
+pub mod framing;
+pub mod spill;
+pub mod persistence;
+pub mod recording;
+pub mod shutdown;
+mod runtime;
+
+#[cfg(feature = "otel")]
+pub mod otel;
+
+#[cfg(feature = "nats")]
+pub mod nats;
+
+#[cfg(feature = "redis")]
+pub mod redis_stream;
 
 #[cfg(test)]
 mod tests;
 
 // 重新导出主要类型
 pub use types::*;
-pub use message_processor::MessageProcessor;
-pub use nanomsg_client::{NanomsgClient, NanomsgConfig, ConnectionState};
-pub use performance::{PerformanceMonitor, HealthStatus};
-pub use error::{VehicleError, Result};
+pub use message_processor::{MessageProcessor, MessageCallback, ProcessorConfig, WatermarkHandler};
+pub use nanomsg_client::{BatchAutoTune, NanomsgClient, NanomsgConfig, NanomsgConfigBuilder, NanomsgStats, ConnectionState, MockNanomsgSocket, SocketFactory};
+pub use performance::{
+    PerformanceMonitor, HealthHysteresis, HealthState, HealthStatus, HealthTransition, Histogram, ReportCallback,
+    ThroughputMeter, ThroughputRates, Warning, WarningKind,
+};
+pub use metrics::{MetricsSink, NoopMetricsSink};
+pub use error::{VehicleError, NanomsgErrorKind, Result};
+pub use alerts::{Alert, AlertKind, AlertRule};
+pub use sampling_watcher::{SamplingConfigFile, SamplingConfigWatcher};
+pub use clock::{Clock, SystemClock, MockClock};
+pub use buffer_pool::{BufferPool, BufferPoolStats};
+pub use dedup::{DedupStore, InMemoryDedupStore};
+pub use sequence::{GapStats, SequenceTracker};
+pub use ordering::{OrderStats, TimestampOrderTracker};
+pub use presence::VinRegistry;
+pub use router::ProcessorRouter;
+pub use transport::{InprocSender, InprocTransport, Transport};
+pub use framing::{encode_length_prefixed, FramingMode, LengthPrefixedDecoder};
+pub use spill::{SpillConfig, SpillStats};
+pub use persistence::{persist_stats, StatsSnapshot};
+pub use recording::{read_recording, Compression, Recorder, RecordingReader};
+pub use shutdown::shutdown;
+#[cfg(feature = "nats")]
+pub use nats::{NatsConfig, NatsTransport};
+#[cfg(feature = "redis")]
+pub use redis_stream::{RedisConsumerGroup, RedisStreamConfig, RedisStreamStats, RedisStreamTransport};
 
 /// 库版本信息
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
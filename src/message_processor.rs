@@ -1,71 +1,1078 @@
 use crate::types::*;
 use crate::error::{Result, VehicleError};
-use crate::performance::PerformanceMonitor;
+use crate::performance::{PerformanceMonitor, Warning};
+use crate::metrics::MetricsSink;
+use crate::sampling_watcher::SamplingConfigWatcher;
+use crate::alerts::{Alert, AlertRule, RuleEngine, SPEED_THRESHOLD_RULE};
+use crate::clock::{Clock, SystemClock};
+use crate::buffer_pool::BufferPool;
+use crate::dedup::{DedupStore, InMemoryDedupStore};
+use crate::json;
+use crate::runtime;
+use crate::sequence::{GapStats, SequenceTracker};
+use crate::ordering::{OrderStats, TimestampOrderTracker};
+use crate::presence::VinRegistry;
+use crate::spill::{SpillBuffer, SpillConfig, SpillStats, SpillWriter};
+use crate::recording::{Compression, Recorder, RecordingReader};
 
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::task::Poll;
 use std::time::{Duration, Instant};
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc, OwnedSemaphorePermit, Semaphore};
 use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
 use dashmap::DashMap;
-use parking_lot::RwLock;
-use tracing::{debug, info, warn, error};
+use parking_lot::{RwLock, RwLockReadGuard};
+use tracing::{debug, info, info_span, warn, error};
 
 /// 消息处理回调函数类型
 pub type MessageCallback = Arc<dyn Fn(VehicleMessage) -> Result<()> + Send + Sync>;
 
+/// 队列水位回调：(优先级, 当前队列深度, 跨越的水位)
+pub type WatermarkHandler = Arc<dyn Fn(MessagePriority, usize, Watermark) + Send + Sync>;
+
+/// 处理完成事件通道容量
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// 告警通道容量
+const ALERT_CHANNEL_CAPACITY: usize = 256;
+
+/// 同步接入通道容量
+const INGEST_CHANNEL_CAPACITY: usize = 1024;
+
+/// 配置回调超时但未设置`max_concurrent_callbacks`时的默认回调并发上限
+const TIMED_CALLBACK_CONCURRENCY: usize = 64;
+
+/// 控制命令通道容量
+const CONTROL_CHANNEL_CAPACITY: usize = 64;
+
+/// 接入缓冲区池中单个缓冲区的初始容量
+const POOLED_BUFFER_CAPACITY: usize = 8192;
+
+/// 去重窗口，窗口内相同hash的消息视为重复
+const DEDUP_WINDOW: Duration = Duration::from_secs(1);
+
+/// 溢写消息重新接入的轮询间隔
+const SPILL_RECOVERY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// 读取溢写文件失败后的重试间隔
+const SPILL_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// 实际采样率统计窗口，计数达到该值后减半，使统计偏向近期消息
+const EFFECTIVE_RATE_WINDOW: u64 = 10_000;
+
+/// 消息处理器配置
+#[derive(Debug, Clone)]
+pub struct ProcessorConfig {
+    /// 是否为每条消息的回调创建独立的tracing span（携带service/vin/priority和消息ID）
+    ///
+    /// 启用`otel`特性时，消息携带的`traceparent`会被设置为该span的父上下文
+    pub trace_messages: bool,
+    /// 车速告警阈值，tracking消息的`data.speed`超过该值时发布告警（注册为内置规则）
+    pub speed_alert_threshold: Option<f64>,
+    /// 去重键配置，决定哪些字段参与去重hash
+    pub dedup_key: DedupKeyConfig,
+    /// 单条消息最大字节数，超出的消息在解析前丢弃
+    pub max_message_bytes: Option<usize>,
+    /// 时间源，用于去重窗口、缓存过期和时间戳补全
+    pub clock: Arc<dyn Clock>,
+    /// 接入缓冲区池最多缓存的缓冲区数量
+    pub buffer_pool_size: usize,
+    /// 去重开关及按服务关闭去重
+    pub dedup: DedupConfig,
+    /// 去重存储后端，None时使用进程内存储
+    pub dedup_store: Option<Arc<dyn DedupStore>>,
+    /// 各服务的处理延迟预算，超出时记录慢处理
+    pub latency_budgets: HashMap<String, Duration>,
+    /// 队列高水位（占容量比例），队列深度升至该值时触发水位回调
+    pub high_watermark: f32,
+    /// 队列低水位（占容量比例），越过高水位后回落至该值时触发水位回调
+    pub low_watermark: f32,
+    /// 延迟取数模式：消息共享原始params（`VehicleMessage::lazy_params`），不复制`data`子树
+    pub lazy_data: bool,
+    /// 处理任务和清理任务所在的运行时，None时使用调用`start`的运行时
+    ///
+    /// 接入循环借用处理器，始终运行在调用`start`的任务中
+    pub runtime: Option<tokio::runtime::Handle>,
+    /// 消息校验配置（如允许不携带`data`的服务）
+    pub validation: ValidationConfig,
+    /// 平均处理时间的平滑系数（0~1]，越大越敏感
+    pub processing_time_alpha: f64,
+    /// 有界运行：本次启动处理（含处理失败）指定数量的消息后自动停止，None时持续运行
+    ///
+    /// 用于回放、基准测试和测试中可复现的定量运行
+    pub stop_after: Option<u64>,
+    /// 接入帧中service/params/vin/timestamp的位置，默认为`{"service", "params": {"vin", "timestamp"}}`
+    pub field_paths: FieldPaths,
+    /// 加权公平调度：设置后由单个任务按权重轮流消费各优先级队列，None时每条队列独立一个任务
+    ///
+    /// 小运行时上可避免大量Normal消息挤占Critical的CPU时间，同时低优先级队列不会被无限期饿死
+    pub lane_weights: Option<LaneWeights>,
+    /// 严格模式：队列满时`submit_message`返回`QueueFull`错误而非静默丢弃，便于生产者自行重试或背压
+    ///
+    /// 去重、采样、过滤仍返回`Ok`；解析和校验失败在两种模式下都返回错误
+    ///
+    /// 时间戳存在但无法解析时，严格模式下丢弃并返回错误，非严格模式下按到达时间补全
+    pub strict: bool,
+    /// VIN在线登记及序列号、时间戳顺序跟踪的过期时长，超过该时长未出现的条目由清理任务移除
+    pub vin_expiry: Duration,
+    /// 指标输出端，None时不输出；也可通过`performance_monitor().set_metrics_sink`在运行中设置
+    pub metrics_sink: Option<Arc<dyn MetricsSink>>,
+    /// 性能警告通道，None时警告只写入日志；也可通过`performance_monitor().set_warning_sender`在运行中设置
+    pub warning_sender: Option<mpsc::Sender<Warning>>,
+    /// 启动后的预热时长，期间不输出性能警告且健康状态视为`Healthy`，避免启动初期的误报
+    pub warmup: Duration,
+    /// 关键消息溢写：Critical队列满时把消息写入磁盘文件，队列腾出空间后按顺序重新接入
+    ///
+    /// None时队列满即丢弃；溢写文件达到大小上限后新的溢出消息仍被丢弃
+    pub spill: Option<SpillConfig>,
+    /// 内联处理的服务：`submit_message`在调用内直接执行回调而不经过队列，消除排队延迟
+    ///
+    /// 以提交方的吞吐换取延迟，适合少量最敏感的服务（如`tracking`）；回调在释放提交路径的配置读锁后执行。
+    /// 处理器未运行或暂停时、以及`submit_batch`提交的消息仍按正常路径入队
+    pub inline_services: HashSet<String>,
+    /// 所有队列（含内联处理）同时执行回调的数量上限，None时不限制；配置了`callback_timeouts`时默认为64
+    ///
+    /// 积压消化时保护回调访问的下游依赖；内联服务在达到上限时改为入队
+    pub max_concurrent_callbacks: Option<usize>,
+    /// 试运行：照常解析、校验、去重、采样、分配优先级并记录统计，但从不调用回调
+    ///
+    /// 用于在生产流量上验证新的采样、过滤配置而不产生副作用，出队的消息计为试运行处理
+    pub dry_run: bool,
+    /// 整数服务编码映射：服务类型字段为整数或只携带`service_code`时按此还原为服务名
+    pub service_codes: ServiceCodes,
+    /// 未注册回调时出队消息的处理方式，默认只记录统计后丢弃
+    ///
+    /// `BufferUpTo`用于消除先启动、后`set_callback`时启动窗口内的消息丢失
+    pub no_callback_policy: NoCallbackPolicy,
+    /// 保留最近分发的N条消息摘要，可通过`recent_messages`读取（如在panic处理中输出），None时不记录
+    pub recent_messages: Option<usize>,
+    /// 按优先级的回调超时：回调在阻塞线程池上执行，超时后消息计为丢弃（原因"callback timeout"），队列继续处理
+    ///
+    /// 超时的回调不会被中断，仍在后台运行直至返回，期间继续占用`max_concurrent_callbacks`的并发许可，
+    /// 因此配置超时后并发上限不能为空（未设置时取默认值），卡死的回调不会耗尽阻塞线程池；
+    /// 阻塞任务被取消（运行时关闭）时计为丢弃（原因"callback cancelled"）。未配置的优先级在处理任务内直接调用回调
+    pub callback_timeouts: HashMap<MessagePriority, Duration>,
+}
+
+impl Default for ProcessorConfig {
+    fn default() -> Self {
+        Self {
+            trace_messages: false,
+            speed_alert_threshold: None,
+            dedup_key: DedupKeyConfig::default(),
+            max_message_bytes: None,
+            clock: Arc::new(SystemClock),
+            buffer_pool_size: 64,
+            dedup: DedupConfig::default(),
+            dedup_store: None,
+            latency_budgets: HashMap::new(),
+            high_watermark: 0.8,
+            low_watermark: 0.2,
+            lazy_data: false,
+            runtime: None,
+            validation: ValidationConfig::default(),
+            processing_time_alpha: ProcessingStats::DEFAULT_EWMA_ALPHA,
+            stop_after: None,
+            field_paths: FieldPaths::default(),
+            lane_weights: None,
+            strict: false,
+            vin_expiry: Duration::from_secs(600),
+            metrics_sink: None,
+            warning_sender: None,
+            warmup: Duration::ZERO,
+            spill: None,
+            inline_services: HashSet::new(),
+            max_concurrent_callbacks: None,
+            dry_run: false,
+            service_codes: ServiceCodes::default(),
+            no_callback_policy: NoCallbackPolicy::default(),
+            recent_messages: None,
+            callback_timeouts: HashMap::new(),
+        }
+    }
+}
+
+/// 队列中的消息
+struct QueuedMessage {
+    /// 处理器分配的消息ID
+    id: u64,
+    message: VehicleMessage,
+    /// 入队时间，用于统计队列等待耗时
+    enqueued_at: Instant,
+}
+
+/// 提交路径的结果：内联服务的消息交回调用方，在释放提交路径的读锁后再执行回调
+enum Submission {
+    /// 已入队、已丢弃或被过滤
+    Done(SubmitOutcome),
+    /// 待内联处理的消息，持有回调并发许可
+    Inline {
+        priority: MessagePriority,
+        queued: Box<QueuedMessage>,
+        permit: OwnedSemaphorePermit,
+    },
+}
+
+/// 按VIN+服务跟踪的状态，由缓存清理任务按`vin_expiry`一同过期
+struct ExpiringTrackers {
+    vins: Arc<VinRegistry>,
+    sequences: Arc<SequenceTracker>,
+    timestamp_order: Arc<TimestampOrderTracker>,
+}
+
+impl ExpiringTrackers {
+    fn evict_expired(&self, expiry: Duration) {
+        let expired_vins = self.vins.evict_expired(expiry);
+        if expired_vins > 0 {
+            debug!("Expired {} inactive VINs", expired_vins);
+        }
+        let expired_sequences = self.sequences.evict_expired(expiry);
+        if expired_sequences > 0 {
+            debug!("Expired {} inactive sequences", expired_sequences);
+        }
+        let expired_series = self.timestamp_order.evict_expired(expiry);
+        if expired_series > 0 {
+            debug!("Expired {} inactive timestamp series", expired_series);
+        }
+    }
+}
+
+/// 待提交的原始帧
+enum RawFrame<'a> {
+    /// 调用方借出的只读帧
+    Shared(&'a [u8]),
+    /// 接入通道取出的自有帧，启用`simd-json`时原地解析，不再复制
+    Owned(&'a mut [u8]),
+}
+
+impl RawFrame<'_> {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            RawFrame::Shared(bytes) => bytes,
+            RawFrame::Owned(bytes) => bytes,
+        }
+    }
+    
+    fn parse(self) -> Result<serde_json::Value> {
+        match self {
+            RawFrame::Shared(bytes) => json::parse_frame(bytes),
+            RawFrame::Owned(bytes) => json::parse_in_place(bytes),
+        }
+    }
+}
+
+/// 提交路径上持有的读锁，批量提交时整批共用
+struct SubmitGuards<'a> {
+    geo_filter: RwLockReadGuard<'a, Option<GeoFilter>>,
+    scene_filter: RwLockReadGuard<'a, Option<SceneFilter>>,
+    sampling: Option<RwLockReadGuard<'a, SamplingConfig>>,
+    lane_senders: RwLockReadGuard<'a, HashMap<MessagePriority, mpsc::Sender<QueuedMessage>>>,
+}
+
+/// 队列水位跟踪，每条队列在越过高水位和回落到低水位时各触发一次回调
+struct WatermarkTracker {
+    handler: RwLock<Option<WatermarkHandler>>,
+    high: f32,
+    low: f32,
+    above_high: [AtomicBool; MessagePriority::COUNT],
+}
+
+impl WatermarkTracker {
+    fn new(high: f32, low: f32) -> Self {
+        Self {
+            handler: RwLock::new(None),
+            high,
+            low,
+            above_high: Default::default(),
+        }
+    }
+    
+    /// 根据当前队列深度检查是否跨越水位
+    fn observe(&self, priority: MessagePriority, depth: usize, capacity: usize) {
+        let Some(handler) = self.handler.read().clone() else {
+            return;
+        };
+        
+        let fill = depth as f32 / capacity.max(1) as f32;
+        let above_high = &self.above_high[priority as usize];
+        if fill >= self.high {
+            if !above_high.swap(true, Ordering::SeqCst) {
+                handler(priority, depth, Watermark::High);
+            }
+        } else if fill <= self.low && above_high.swap(false, Ordering::SeqCst) {
+            handler(priority, depth, Watermark::Low);
+        }
+    }
+}
+
+/// 最近分发消息的有界环形缓冲区，满时覆盖最早的记录
+struct RecentMessages {
+    capacity: usize,
+    entries: parking_lot::Mutex<VecDeque<RecentMessage>>,
+}
+
+impl RecentMessages {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: parking_lot::Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+    
+    fn push(&self, entry: RecentMessage) {
+        let mut entries = self.entries.lock();
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+    
+    fn snapshot(&self) -> Vec<RecentMessage> {
+        self.entries.lock().iter().cloned().collect()
+    }
+}
+
+/// 按服务统计采样判定的保留比例
+#[derive(Default)]
+struct SamplingObserver {
+    // service -> (seen, kept)
+    counts: DashMap<String, (u64, u64)>,
+}
+
+impl SamplingObserver {
+    fn record(&self, service: &str, kept: bool) {
+        let mut entry = match self.counts.get_mut(service) {
+            Some(entry) => entry,
+            None => self.counts.entry(service.to_string()).or_default(),
+        };
+        let (seen, kept_count) = entry.value_mut();
+        *seen += 1;
+        *kept_count += kept as u64;
+        if *seen >= EFFECTIVE_RATE_WINDOW {
+            *seen /= 2;
+            *kept_count /= 2;
+        }
+    }
+    
+    fn rates(&self, config: &SamplingConfig) -> HashMap<String, EffectiveSamplingRate> {
+        self.counts
+            .iter()
+            .map(|entry| {
+                let (seen, kept) = *entry.value();
+                let rate = EffectiveSamplingRate {
+                    configured: config.get_rate(entry.key()),
+                    effective: if seen == 0 { 0.0 } else { kept as f32 / seen as f32 },
+                    seen,
+                };
+                (entry.key().clone(), rate)
+            })
+            .collect()
+    }
+}
+
+/// 等待可选任务结束，未启动的任务永不结束
+async fn join_optional(task: Option<tokio::task::JoinHandle<()>>) {
+    match task {
+        Some(task) => {
+            let _ = task.await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// 等待任一优先级处理任务结束并返回其优先级，没有任务时永不结束
+async fn join_any_lane(mut tasks: Vec<(MessagePriority, tokio::task::JoinHandle<()>)>) -> MessagePriority {
+    if tasks.is_empty() {
+        return std::future::pending().await;
+    }
+    std::future::poll_fn(|cx| {
+        for (priority, task) in tasks.iter_mut() {
+            if Pin::new(task).poll(cx).is_ready() {
+                return Poll::Ready(*priority);
+            }
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+/// 依次调用所有回调，单个回调失败不影响其余回调；任一回调失败即视为处理失败
+///
+/// 只有一个回调时原样返回其错误，多个回调时汇总为`CallbacksFailed`
+fn invoke_callbacks(callbacks: &[MessageCallback], message: VehicleMessage) -> Result<()> {
+    let Some((last, rest)) = callbacks.split_last() else {
+        return Ok(());
+    };
+    
+    let mut errors = Vec::new();
+    for (index, callback) in rest.iter().enumerate() {
+        if let Err(e) = callback(message.clone()) {
+            errors.push((index, e));
+        }
+    }
+    // 最后一个回调直接取得消息所有权，单回调时不产生克隆
+    if let Err(e) = last(message) {
+        errors.push((rest.len(), e));
+    }
+    
+    match errors.len() {
+        0 => Ok(()),
+        1 if callbacks.len() == 1 => Err(errors.remove(0).1),
+        _ => Err(VehicleError::CallbacksFailed { total: callbacks.len(), errors }),
+    }
+}
+
+/// 在途回调计数，回调返回（含panic）时减一
+struct InFlightGuard(Arc<AtomicUsize>);
+
+impl InFlightGuard {
+    fn new(counter: &Arc<AtomicUsize>) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self(counter.clone())
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 交给回调的消息及调用前后共用的信息
+struct Delivery {
+    id: u64,
+    message: VehicleMessage,
+    callbacks: Arc<[MessageCallback]>,
+    alerts: Vec<Alert>,
+    start_time: Instant,
+}
+
+/// 回调调用后发布事件所需的信息（消息本身已交给回调）
+struct DeliveryInfo {
+    id: u64,
+    service: String,
+    vin: String,
+    correlation_id: Option<String>,
+    source: Option<String>,
+    alerts: Vec<Alert>,
+    start_time: Instant,
+}
+
+impl Delivery {
+    /// 拆分为交给回调的消息、回调快照和调用后使用的信息
+    fn split(self) -> (VehicleMessage, Arc<[MessageCallback]>, DeliveryInfo) {
+        let info = DeliveryInfo {
+            id: self.id,
+            service: self.message.service.clone(),
+            vin: self.message.vin.clone(),
+            correlation_id: self.message.correlation_id.clone(),
+            source: self.message.source.clone(),
+            alerts: self.alerts,
+            start_time: self.start_time,
+        };
+        (self.message, self.callbacks, info)
+    }
+}
+
+/// JSON值的类型名，用于错误信息
+fn json_type_name(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "bool",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+/// 解析时间戳：接受数字或数字字符串（如`"1700000000.5"`），非有限值视为无法解析
+fn parse_timestamp(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.trim().parse::<f64>().ok(),
+        _ => None,
+    }
+    .filter(|timestamp| timestamp.is_finite())
+}
+
+/// 处理任务共享的分发上下文
+#[derive(Clone)]
+struct DispatchContext {
+    callbacks: Arc<RwLock<Arc<[MessageCallback]>>>,
+    monitor: Arc<PerformanceMonitor>,
+    event_tx: broadcast::Sender<ProcessedEvent>,
+    alert_tx: broadcast::Sender<Alert>,
+    rules: Arc<RuleEngine>,
+    watermarks: Arc<WatermarkTracker>,
+    trace_messages: bool,
+    dry_run: bool,
+    no_callback_policy: NoCallbackPolicy,
+    pending_callback: Arc<parking_lot::Mutex<VecDeque<(MessagePriority, QueuedMessage)>>>,
+    dispatched: Arc<AtomicU64>,
+    stop_after: Option<u64>,
+    shutdown: CancellationToken,
+    callback_permits: Arc<Semaphore>,
+    callbacks_in_flight: Arc<AtomicUsize>,
+    recent: Option<Arc<RecentMessages>>,
+    callback_timeouts: HashMap<MessagePriority, Duration>,
+}
+
+impl DispatchContext {
+    /// 处理从队列取出的消息：记录队列等待、检查水位、分发并按优先级计数
+    async fn handle_queued(
+        &self,
+        priority: MessagePriority,
+        queued: QueuedMessage,
+        depth: usize,
+        permit: Option<OwnedSemaphorePermit>,
+    ) {
+        self.monitor.record_stage(PipelineStage::QueueWait, queued.enqueued_at.elapsed());
+        self.watermarks.observe(priority, depth, priority.queue_capacity());
+        
+        // 调用回调函数处理消息，成功时按优先级计数
+        if self.dispatch(priority, queued, permit).await {
+            self.monitor.record_priority_processed(priority);
+        }
+    }
+    
+    /// 等待回调并发许可
+    async fn acquire_callback_permit(&self) -> Option<OwnedSemaphorePermit> {
+        // 信号量不会被关闭，获取失败时不限制并发
+        self.callback_permits.clone().acquire_owned().await.ok()
+    }
+    
+    /// 在提交调用内直接处理消息（内联服务），不经过队列
+    async fn handle_inline(&self, priority: MessagePriority, queued: QueuedMessage, permit: OwnedSemaphorePermit) {
+        if self.dispatch(priority, queued, Some(permit)).await {
+            self.monitor.record_priority_processed(priority);
+        }
+    }
+    
+    /// 处理单条消息，达到`stop_after`时请求关闭；返回是否处理成功
+    ///
+    /// 回调并发许可保持到回调返回后释放
+    async fn dispatch(
+        &self,
+        priority: MessagePriority,
+        queued: QueuedMessage,
+        permit: Option<OwnedSemaphorePermit>,
+    ) -> bool {
+        // 在调用回调前记录，回调panic时该消息也在诊断记录中
+        if let Some(ref recent) = self.recent {
+            recent.push(RecentMessage {
+                message_id: queued.id,
+                service: queued.message.service.clone(),
+                vin: queued.message.vin.clone(),
+                timestamp: queued.message.timestamp,
+                priority,
+            });
+        }
+        // 缓存等待回调注册的消息在回放时才计数
+        let Some(processed) = self.deliver_with_permit(priority, queued, permit).await else {
+            return false;
+        };
+        self.count_dispatched();
+        processed
+    }
+    
+    /// 计入本次运行已处理的消息，达到`stop_after`时请求关闭
+    fn count_dispatched(&self) {
+        let dispatched = self.dispatched.fetch_add(1, Ordering::SeqCst) + 1;
+        if self.stop_after == Some(dispatched) {
+            info!("Processed {} messages, stopping bounded run", dispatched);
+            self.shutdown.cancel();
+        }
+    }
+    
+    /// 调用回调处理单条消息，返回是否处理成功，None表示消息已缓存等待回调注册；
+    /// 不应用回调超时，在当前线程同步执行
+    fn deliver(&self, priority: MessagePriority, queued: QueuedMessage) -> Option<bool> {
+        let delivery = match self.prepare(priority, queued) {
+            ControlFlow::Continue(delivery) => delivery,
+            ControlFlow::Break(processed) => return processed,
+        };
+        let span = self.callback_span(priority, &delivery);
+        let (message, callbacks, info) = delivery.split();
+        
+        let in_flight = InFlightGuard::new(&self.callbacks_in_flight);
+        let result = match span {
+            Some(span) => span.in_scope(|| invoke_callbacks(&callbacks, message)),
+            None => invoke_callbacks(&callbacks, message),
+        };
+        drop(in_flight);
+        Some(self.complete(priority, info, Some(result), None))
+    }
+    
+    /// 调用回调处理单条消息，配置了该优先级的回调超时时在阻塞线程池上执行并限时等待
+    ///
+    /// 超时的回调不会被中断，其并发许可和在途计数保持到回调真正返回，
+    /// 因此堆积的超时回调仍受`max_concurrent_callbacks`限制
+    async fn deliver_with_permit(
+        &self,
+        priority: MessagePriority,
+        queued: QueuedMessage,
+        permit: Option<OwnedSemaphorePermit>,
+    ) -> Option<bool> {
+        let Some(timeout) = self.callback_timeouts.get(&priority).copied() else {
+            return self.deliver(priority, queued);
+        };
+        let delivery = match self.prepare(priority, queued) {
+            ControlFlow::Continue(delivery) => delivery,
+            ControlFlow::Break(processed) => return processed,
+        };
+        // 当前tracing span随回调一起进入阻塞线程
+        let span = self.callback_span(priority, &delivery).unwrap_or_else(tracing::Span::current);
+        let (message, callbacks, info) = delivery.split();
+        
+        let in_flight = InFlightGuard::new(&self.callbacks_in_flight);
+        let mut task = tokio::task::spawn_blocking(move || {
+            let _in_flight = in_flight;
+            let _permit = permit;
+            span.in_scope(|| invoke_callbacks(&callbacks, message))
+        });
+        let result = match tokio::time::timeout(timeout, &mut task).await {
+            Ok(Ok(result)) => Some(result),
+            Ok(Err(e)) => match e.try_into_panic() {
+                Ok(payload) => std::panic::resume_unwind(payload),
+                // 运行时关闭时阻塞任务可能在执行前被取消，不计为超时
+                Err(_) => {
+                    warn!(
+                        "Callback cancelled for {:?} message: id={}, service={}",
+                        priority, info.id, info.service
+                    );
+                    self.monitor.record_stage(PipelineStage::Callback, info.start_time.elapsed());
+                    self.monitor.record_dropped("callback cancelled");
+                    return Some(false);
+                }
+            },
+            Err(_) => None,
+        };
+        Some(self.complete(priority, info, result, Some(timeout)))
+    }
+    
+    /// 回调调用前的处理：试运行、未注册回调的处理策略和告警规则求值
+    ///
+    /// 返回`Break`表示消息无需调用回调，携带是否处理成功，None表示消息已缓存等待回调注册
+    fn prepare(&self, priority: MessagePriority, queued: QueuedMessage) -> ControlFlow<Option<bool>, Delivery> {
+        let start_time = Instant::now();
+        
+        if self.dry_run {
+            // 试运行不调用回调，也不发布处理事件和告警
+            self.monitor.record_dry_run(start_time.elapsed());
+            debug!(
+                "Dry-run processed {:?} message: id={}, service={}",
+                priority, queued.id, queued.message.service
+            );
+            return ControlFlow::Break(Some(true));
+        }
+        
+        // 每条消息取一次回调快照，调用期间不持有锁，替换回调不会阻塞处理
+        let mut callbacks = self.callbacks.read().clone();
+        
+        if callbacks.is_empty() {
+            match self.no_callback_policy {
+                NoCallbackPolicy::Discard => {
+                    // 没有回调函数，只记录统计
+                    self.monitor.record_processed(start_time.elapsed());
+                    return ControlFlow::Break(Some(true));
+                }
+                NoCallbackPolicy::Error => {
+                    error!("No callback registered, dropping {:?} message: id={}", priority, queued.id);
+                    self.monitor.record_dropped("no callback");
+                    return ControlFlow::Break(Some(false));
+                }
+                NoCallbackPolicy::BufferUpTo(limit) => {
+                    let mut pending = self.pending_callback.lock();
+                    // 持锁复查：注册回调时在同一把锁内回放，避免消息在回放后才进入缓存而滞留
+                    callbacks = self.callbacks.read().clone();
+                    if callbacks.is_empty() {
+                        if pending.len() >= limit {
+                            self.monitor.record_dropped("no callback buffer full");
+                            return ControlFlow::Break(Some(false));
+                        }
+                        pending.push_back((priority, queued));
+                        return ControlFlow::Break(None);
+                    }
+                }
+            }
+        }
+        
+        // 回调会取得消息所有权，告警规则在调用前求值，处理成功后发布
+        let alerts = if self.rules.is_empty() {
+            Vec::new()
+        } else {
+            self.rules.evaluate(&queued.message)
+        };
+        
+        ControlFlow::Continue(Delivery {
+            id: queued.id,
+            message: queued.message,
+            callbacks,
+            alerts,
+            start_time,
+        })
+    }
+    
+    /// 开启`trace_messages`时为回调创建携带service/vin/priority和消息ID的span
+    fn callback_span(&self, priority: MessagePriority, delivery: &Delivery) -> Option<tracing::Span> {
+        if !self.trace_messages {
+            return None;
+        }
+        let message = &delivery.message;
+        let span = info_span!(
+            "vehicle_message",
+            message_id = delivery.id,
+            service = %message.service,
+            vin = %message.vin,
+            priority = ?priority,
+        );
+        #[cfg(feature = "otel")]
+        crate::otel::set_parent_from_message(&span, message);
+        Some(span)
+    }
+    
+    /// 回调返回后记录统计并发布事件和告警，`result`为None表示回调超时；返回是否处理成功
+    fn complete(
+        &self,
+        priority: MessagePriority,
+        info: DeliveryInfo,
+        result: Option<Result<()>>,
+        timeout: Option<Duration>,
+    ) -> bool {
+        let DeliveryInfo { id, service, vin, correlation_id, source, alerts, start_time } = info;
+        self.monitor.record_stage(PipelineStage::Callback, start_time.elapsed());
+        
+        let Some(result) = result else {
+            warn!(
+                "Callback timed out for {:?} message: id={}, service={}, timeout={:?}",
+                priority, id, service, timeout.unwrap_or_default()
+            );
+            self.monitor.record_dropped("callback timeout");
+            return false;
+        };
+        
+        match result {
+            Ok(_) => {
+                let processing_time = start_time.elapsed();
+                self.monitor.record_service_processed(&service, processing_time);
+                
+                debug!(
+                    "Processed {:?} message: id={}, service={}, time={:.2}μs",
+                    priority,
+                    id,
+                    service,
+                    processing_time.as_micros()
+                );
+                
+                // 没有订阅者时发送失败，忽略即可
+                let _ = self.event_tx.send(ProcessedEvent {
+                    message_id: id,
+                    service,
+                    vin,
+                    priority,
+                    correlation_id,
+                    source,
+                    processing_time,
+                });
+                
+                for alert in alerts {
+                    warn!("Alert [{}] for vehicle {}: {:?}", alert.rule, alert.vin, alert.kind);
+                    let _ = self.alert_tx.send(alert);
+                }
+                true
+            }
+            Err(e) => {
+                error!(
+                    "Failed to process {:?} message: id={}, service={}, error={}",
+                    priority, id, service, e
+                );
+                self.monitor.record_dropped("processing error");
+                false
+            }
+        }
+    }
+}
+
 /// 高性能消息处理器
 pub struct MessageProcessor {
-    // 分优先级的消息通道
-    critical_tx: mpsc::Sender<VehicleMessage>,
-    normal_tx: mpsc::Sender<VehicleMessage>,
-    background_tx: mpsc::Sender<VehicleMessage>,
+    // 分优先级的消息通道发送端（start时重建）
+    lane_senders: Arc<RwLock<HashMap<MessagePriority, mpsc::Sender<QueuedMessage>>>>,
     
-    // 消息去重缓存 (hash -> last_seen_time)
-    message_cache: Arc<DashMap<u64, Instant>>,
+    // 消息去重存储
+    dedup_store: Arc<dyn DedupStore>,
     
     // 采样配置
     sampling_config: Arc<RwLock<SamplingConfig>>,
     
+    // 采样开关，关闭时所有消息都被处理
+    sampling_enabled: Arc<AtomicBool>,
+    
+    // 地理围栏过滤
+    geo_filter: Arc<RwLock<Option<GeoFilter>>>,
+    
+    // 运行场景过滤
+    scene_filter: Arc<RwLock<Option<SceneFilter>>>,
+    
     // 性能监控
     pub(crate) performance_monitor: Arc<PerformanceMonitor>,
     
-    // 消息处理回调
-    message_callback: Option<MessageCallback>,
+    // 消息处理回调，每条消息依次调用；运行中可整体替换
+    message_callbacks: Arc<RwLock<Arc<[MessageCallback]>>>,
     
     // 运行状态
     is_running: Arc<parking_lot::RwLock<bool>>,
+    
+    // 暂停状态（暂停时消息保留在队列中，不调用回调）
+    is_paused: Arc<AtomicBool>,
+    
+    // 处理器配置
+    config: ProcessorConfig,
+    
+    // 消息ID生成器
+    next_message_id: Arc<AtomicU64>,
+    
+    // 处理完成事件广播
+    event_tx: broadcast::Sender<ProcessedEvent>,
+    
+    // 告警广播
+    alert_tx: broadcast::Sender<Alert>,
+    
+    // 告警规则
+    rules: Arc<RuleEngine>,
+    
+    // 同步接入通道，供非异步的生产者推送原始帧
+    ingest_tx: mpsc::Sender<Vec<u8>>,
+    ingest_rx: tokio::sync::Mutex<mpsc::Receiver<Vec<u8>>>,
+    
+    // 接入帧缓冲区池
+    buffer_pool: Arc<BufferPool>,
+    
+    // 队列水位跟踪
+    watermarks: Arc<WatermarkTracker>,
+    
+    // 上游序列号缺口检测
+    sequences: Arc<SequenceTracker>,
+    
+    // 按VIN+服务的时间戳乱序检测
+    timestamp_order: Arc<TimestampOrderTracker>,
+    
+    // 各VIN最近一次出现的时间
+    vins: Arc<VinRegistry>,
+    
+    // 关键消息溢写缓冲区（首次启动时打开）
+    spill: Arc<parking_lot::Mutex<Option<SpillBuffer>>>,
+    
+    // 溢写写入线程（与溢写缓冲区一同创建），提交路径不执行文件I/O
+    spill_writer: std::sync::OnceLock<SpillWriter>,
+    
+    // 最近分发的消息摘要（配置recent_messages时创建）
+    recent_messages: Option<Arc<RecentMessages>>,
+    
+    // 接入帧录制器（enable_recording时创建）
+    recorder: Arc<parking_lot::Mutex<Option<Recorder>>>,
+    
+    // 是否在录制，未录制时提交路径不获取录制器锁
+    recording: Arc<AtomicBool>,
+    
+    // 实际采样率统计
+    sampling_observer: Arc<SamplingObserver>,
+    
+    // 本次运行的关闭token（由start传入token派生）
+    shutdown: Arc<parking_lot::Mutex<CancellationToken>>,
+    
+    // 本次运行已处理的消息数（含处理失败），用于stop_after
+    dispatched: Arc<AtomicU64>,
+    
+    // 按服务覆盖的优先级
+    priority_overrides: Arc<RwLock<HashMap<String, MessagePriority>>>,
+    
+    // 回调并发许可，未配置上限时许可数不受限
+    callback_permits: Arc<Semaphore>,
+    
+    // 正在执行的回调数
+    callbacks_in_flight: Arc<AtomicUsize>,
+    
+    // 本次运行的分发上下文（start时创建），供内联处理和按需启动的队列任务复用
+    inline_dispatch: RwLock<Option<Arc<DispatchContext>>>,
+    
+    // 尚未启动处理任务的优先级队列接收端，覆盖到该优先级时按需启动
+    idle_lanes: parking_lot::Mutex<HashMap<MessagePriority, mpsc::Receiver<QueuedMessage>>>,
+    
+    // 未注册回调时缓存的消息（NoCallbackPolicy::BufferUpTo），注册回调时回放
+    pending_callback: Arc<parking_lot::Mutex<VecDeque<(MessagePriority, QueuedMessage)>>>,
+    
+    // 运行时控制命令通道，接收端由运行中的控制循环独占
+    control_tx: mpsc::Sender<ControlCommand>,
+    control_rx: tokio::sync::Mutex<mpsc::Receiver<ControlCommand>>,
 }
 
 impl MessageProcessor {
     /// 创建新的消息处理器
     pub fn new() -> Self {
-        let critical_capacity = MessagePriority::Critical.queue_capacity();
-        let normal_capacity = MessagePriority::Normal.queue_capacity();
-        let background_capacity = MessagePriority::Background.queue_capacity();
+        Self::with_config(ProcessorConfig::default())
+    }
+    
+    /// 使用指定配置创建消息处理器
+    pub fn with_config(mut config: ProcessorConfig) -> Self {
+        // 超时的回调仍占用阻塞线程，必须有并发上限
+        if !config.callback_timeouts.is_empty() {
+            config.max_concurrent_callbacks.get_or_insert(TIMED_CALLBACK_CONCURRENCY);
+        }
+        // 接收端在start时创建，启动前提交的消息会因通道关闭而丢弃
+        let lane_senders = MessagePriority::all()
+            .into_iter()
+            .map(|priority| (priority, mpsc::channel(priority.queue_capacity()).0))
+            .collect();
+        let (event_tx, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let (alert_tx, _) = broadcast::channel(ALERT_CHANNEL_CAPACITY);
+        let (ingest_tx, ingest_rx) = mpsc::channel(INGEST_CHANNEL_CAPACITY);
+        let (control_tx, control_rx) = mpsc::channel(CONTROL_CHANNEL_CAPACITY);
+        
+        let performance_monitor =
+            PerformanceMonitor::new(Duration::from_secs(10))
+                .with_clock(config.clock.clone())
+                .with_ewma_alpha(config.processing_time_alpha)
+                .with_warmup(config.warmup);
+        for (service, budget) in &config.latency_budgets {
+            performance_monitor.set_latency_budget(service, *budget);
+        }
+        if let Some(ref sink) = config.metrics_sink {
+            performance_monitor.set_metrics_sink(sink.clone());
+        }
+        if let Some(ref sender) = config.warning_sender {
+            performance_monitor.set_warning_sender(sender.clone());
+        }
         
-        let (critical_tx, _) = mpsc::channel(critical_capacity);
-        let (normal_tx, _) = mpsc::channel(normal_capacity);
-        let (background_tx, _) = mpsc::channel(background_capacity);
+        let rules = Arc::new(RuleEngine::new());
+        if let Some(threshold) = config.speed_alert_threshold {
+            rules.add_rule(
+                SPEED_THRESHOLD_RULE,
+                Arc::new(move |message| Alert::speed_exceeded(message, threshold)),
+            );
+        }
         
         Self {
-            critical_tx,
-            normal_tx,
-            background_tx,
-            message_cache: Arc::new(DashMap::new()),
+            lane_senders: Arc::new(RwLock::new(lane_senders)),
+            dedup_store: config.dedup_store.clone().unwrap_or_else(|| {
+                Arc::new(InMemoryDedupStore::with_clock(config.clock.clone()))
+            }),
             sampling_config: Arc::new(RwLock::new(SamplingConfig::default())),
-            performance_monitor: Arc::new(PerformanceMonitor::new(Duration::from_secs(10))),
-            message_callback: None,
+            sampling_enabled: Arc::new(AtomicBool::new(true)),
+            geo_filter: Arc::new(RwLock::new(None)),
+            scene_filter: Arc::new(RwLock::new(None)),
+            performance_monitor: Arc::new(performance_monitor),
+            message_callbacks: Arc::new(RwLock::new(Arc::from([]))),
             is_running: Arc::new(parking_lot::RwLock::new(false)),
+            is_paused: Arc::new(AtomicBool::new(false)),
+            next_message_id: Arc::new(AtomicU64::new(1)),
+            event_tx,
+            alert_tx,
+            rules,
+            ingest_tx,
+            ingest_rx: tokio::sync::Mutex::new(ingest_rx),
+            buffer_pool: Arc::new(BufferPool::new(config.buffer_pool_size, POOLED_BUFFER_CAPACITY)),
+            watermarks: Arc::new(WatermarkTracker::new(config.high_watermark, config.low_watermark)),
+            sequences: Arc::new(SequenceTracker::with_clock(config.clock.clone())),
+            timestamp_order: Arc::new(TimestampOrderTracker::with_clock(config.clock.clone())),
+            vins: Arc::new(VinRegistry::new(config.clock.clone())),
+            spill: Arc::new(parking_lot::Mutex::new(None)),
+            spill_writer: std::sync::OnceLock::new(),
+            recorder: Arc::new(parking_lot::Mutex::new(None)),
+            recording: Arc::new(AtomicBool::new(false)),
+            recent_messages: config.recent_messages.filter(|&n| n > 0).map(|n| Arc::new(RecentMessages::new(n))),
+            sampling_observer: Arc::new(SamplingObserver::default()),
+            shutdown: Arc::new(parking_lot::Mutex::new(CancellationToken::new())),
+            dispatched: Arc::new(AtomicU64::new(0)),
+            callback_permits: Arc::new(Semaphore::new(
+                config.max_concurrent_callbacks.map_or(Semaphore::MAX_PERMITS, |max| max.max(1)),
+            )),
+            callbacks_in_flight: Arc::new(AtomicUsize::new(0)),
+            inline_dispatch: RwLock::new(None),
+            idle_lanes: parking_lot::Mutex::new(HashMap::new()),
+            pending_callback: Arc::new(parking_lot::Mutex::new(VecDeque::new())),
+            priority_overrides: Arc::new(RwLock::new(HashMap::new())),
+            control_tx,
+            control_rx: tokio::sync::Mutex::new(control_rx),
+            config,
+        }
+    }
+    
+    /// 设置消息处理回调，替换已注册的所有回调
+    ///
+    /// 运行中也可调用，从下一条消息开始生效，无需重启处理器；
+    /// 未注册回调期间缓存的消息（见`NoCallbackPolicy::BufferUpTo`）在本调用内回放
+    pub fn set_callback(&self, callback: MessageCallback) {
+        self.install_callbacks(|_| Arc::from([callback]));
+    }
+    
+    /// 追加消息处理回调，每条消息会依次交给所有回调（如日志、转发、分析各自独立消费）
+    ///
+    /// 单个回调失败不影响其余回调，任一回调失败时该消息计为处理失败
+    pub fn add_callback(&self, callback: MessageCallback) {
+        self.install_callbacks(|callbacks| {
+            callbacks.iter().cloned().chain(std::iter::once(callback)).collect()
+        });
+    }
+    
+    /// 替换回调并回放未注册回调期间缓存的消息
+    fn install_callbacks(&self, update: impl FnOnce(&[MessageCallback]) -> Arc<[MessageCallback]>) {
+        let pending = {
+            // 与处理任务缓存消息使用同一把锁，保证回放后不会再有消息进入缓存
+            let mut pending = self.pending_callback.lock();
+            let mut callbacks = self.message_callbacks.write();
+            *callbacks = update(&callbacks);
+            std::mem::take(&mut *pending)
+        };
+        if pending.is_empty() {
+            return;
         }
+        
+        info!("Replaying {} messages buffered before callback registration", pending.len());
+        let dispatch = self.dispatch_context();
+        for (priority, queued) in pending {
+            let Some(processed) = dispatch.deliver(priority, queued) else {
+                continue;
+            };
+            dispatch.count_dispatched();
+            if processed {
+                self.performance_monitor.record_priority_processed(priority);
+            }
+        }
+    }
+    
+    /// 未注册回调期间缓存、等待回放的消息数
+    pub fn pending_callback_messages(&self) -> usize {
+        self.pending_callback.lock().len()
+    }
+    
+    /// 移除所有回调，之后的消息只记录统计
+    pub fn clear_callbacks(&self) {
+        *self.message_callbacks.write() = Arc::from([]);
     }
     
-    /// 设置消息处理回调
-    pub fn set_callback(&mut self, callback: MessageCallback) {
-        self.message_callback = Some(callback);
+    /// 获取当前注册的回调
+    pub fn callbacks(&self) -> Vec<MessageCallback> {
+        self.message_callbacks.read().to_vec()
     }
     
-    /// 启动消息处理器
+    /// 启动消息处理器，直到调用`stop`
     pub async fn start(&self) -> Result<()> {
+        self.start_with_token(CancellationToken::new()).await
+    }
+    
+    /// 启动消息处理器，直到调用`stop`或`token`被取消
+    ///
+    /// 便于接入应用统一的关闭流程；`stop`只取消内部派生的子token，不影响传入的token
+    pub async fn start_with_token(&self, token: CancellationToken) -> Result<()> {
+        self.open_spill()?;
         {
             let mut running = self.is_running.write();
             if *running {
@@ -75,61 +1082,116 @@ impl MessageProcessor {
         }
         
         info!("Starting message processor with priority queues");
+        self.performance_monitor.begin_warmup();
         
-        // 重新创建接收端（因为之前的接收端被丢弃了）
-        let (critical_tx, critical_rx) = mpsc::channel(MessagePriority::Critical.queue_capacity());
-        let (normal_tx, normal_rx) = mpsc::channel(MessagePriority::Normal.queue_capacity());
-        let (background_tx, background_rx) = mpsc::channel(MessagePriority::Background.queue_capacity());
-        
-        // 更新发送端
-        let processor = MessageProcessor {
-            critical_tx,
-            normal_tx,
-            background_tx,
-            message_cache: self.message_cache.clone(),
-            sampling_config: self.sampling_config.clone(),
-            performance_monitor: self.performance_monitor.clone(),
-            message_callback: self.message_callback.clone(),
-            is_running: self.is_running.clone(),
-        };
-        
-        // 启动处理任务
-        let critical_task = Self::spawn_processor_task(
-            critical_rx,
-            MessagePriority::Critical,
-            processor.message_callback.clone(),
-            processor.performance_monitor.clone(),
-            processor.is_running.clone(),
-        );
+        // 重新创建通道，并将发送端替换到共享的发送端表中，
+        // 使submit_message写入的正是本次启动的处理任务所消费的队列
+        let lanes: Vec<(MessagePriority, mpsc::Receiver<QueuedMessage>)> = {
+            let mut senders = self.lane_senders.write();
+            MessagePriority::all()
+                .into_iter()
+                .map(|priority| {
+                    let (tx, rx) = mpsc::channel(priority.queue_capacity());
+                    senders.insert(priority, tx);
+                    (priority, rx)
+                })
+                .collect()
+        };
         
-        let normal_task = Self::spawn_processor_task(
-            normal_rx,
-            MessagePriority::Normal,
-            processor.message_callback.clone(),
-            processor.performance_monitor.clone(),
-            processor.is_running.clone(),
-        );
+        let shutdown = token.child_token();
+        *self.shutdown.lock() = shutdown.clone();
+        self.dispatched.store(0, Ordering::SeqCst);
         
-        let background_task = Self::spawn_processor_task(
-            background_rx,
-            MessagePriority::Background,
-            processor.message_callback.clone(),
-            processor.performance_monitor.clone(),
-            processor.is_running.clone(),
-        );
+        let dispatch = self.dispatch_context();
+        *self.inline_dispatch.write() = Some(Arc::new(dispatch.clone()));
+        let runtime = self.config.runtime.as_ref();
+        self.idle_lanes.lock().clear();
         
-        // 启动缓存清理任务
-        let cache_cleanup_task = Self::spawn_cache_cleanup_task(
-            processor.message_cache.clone(),
-            processor.is_running.clone(),
-        );
+        // 启动处理任务：加权公平模式下由单个任务消费所有优先级队列
+        let (lane_tasks, weighted_task) = match self.config.lane_weights {
+            Some(weights) => {
+                let weighted_task = Self::spawn_weighted_task(
+                    runtime,
+                    lanes,
+                    weights,
+                    dispatch,
+                    self.is_running.clone(),
+                    self.is_paused.clone(),
+                    shutdown.clone(),
+                );
+                (Vec::new(), Some(weighted_task))
+            }
+            None => {
+                // 只为默认映射和已覆盖的优先级启动任务，其余队列在覆盖到时按需启动
+                let used = self.used_priorities();
+                let (lanes, idle): (Vec<_>, Vec<_>) =
+                    lanes.into_iter().partition(|(priority, _)| used.contains(priority));
+                self.idle_lanes.lock().extend(idle);
+                let lane_tasks = lanes
+                    .into_iter()
+                    .map(|(priority, receiver)| {
+                        let task = Self::spawn_processor_task(
+                            runtime,
+                            receiver,
+                            priority,
+                            dispatch.clone(),
+                            self.is_running.clone(),
+                            self.is_paused.clone(),
+                            shutdown.clone(),
+                        );
+                        (priority, task)
+                    })
+                    .collect();
+                (lane_tasks, None)
+            }
+        };
         
-        // 等待所有任务完成
-        tokio::select! {
-            _ = critical_task => warn!("Critical processor task ended"),
-            _ = normal_task => warn!("Normal processor task ended"),
-            _ = background_task => warn!("Background processor task ended"),
-            _ = cache_cleanup_task => warn!("Cache cleanup task ended"),
+        // 启动缓存清理任务（去重缓存、VIN在线登记、序列号及时间戳顺序跟踪）
+        let cache_cleanup_task = Some(Self::spawn_cache_cleanup_task(
+            runtime,
+            self.dedup_store.clone(),
+            self.config.dedup.clone(),
+            ExpiringTrackers {
+                vins: self.vins.clone(),
+                sequences: self.sequences.clone(),
+                timestamp_order: self.timestamp_order.clone(),
+            },
+            self.config.vin_expiry,
+            self.is_running.clone(),
+            shutdown.clone(),
+        ));
+        
+        // 启动溢写消息重新接入任务
+        let spill_recovery_task = self.config.spill.is_some().then(|| {
+            Self::spawn_spill_recovery_task(
+                runtime,
+                self.spill.clone(),
+                self.lane_senders.clone(),
+                self.next_message_id.clone(),
+                self.performance_monitor.clone(),
+                self.is_running.clone(),
+                shutdown.clone(),
+            )
+        });
+        
+        // 接入循环和控制循环借用self运行在当前任务中
+        let ingest_loop = self.run_ingest_loop();
+        let control_loop = self.run_control_loop();
+        
+        // 等待所有任务完成
+        tokio::select! {
+            priority = join_any_lane(lane_tasks) => warn!("{:?} processor task ended", priority),
+            _ = join_optional(weighted_task) => warn!("Weighted fair processor task ended"),
+            _ = join_optional(cache_cleanup_task) => warn!("Cache cleanup task ended"),
+            _ = join_optional(spill_recovery_task) => warn!("Spill recovery task ended"),
+            _ = ingest_loop => warn!("Ingest loop ended"),
+            _ = control_loop => warn!("Control loop ended"),
+            _ = shutdown.cancelled() => info!("Message processor shutdown requested"),
+        }
+        
+        // 外部取消时各任务可能先于上面的分支结束，统一在此更新运行状态
+        if shutdown.is_cancelled() {
+            *self.is_running.write() = false;
         }
         
         Ok(())
@@ -138,91 +1200,357 @@ impl MessageProcessor {
     /// 停止消息处理器
     pub fn stop(&self) {
         info!("Stopping message processor");
-        let mut running = self.is_running.write();
-        *running = false;
+        {
+            let mut running = self.is_running.write();
+            *running = false;
+        }
+        self.shutdown.lock().cancel();
+    }
+    
+    /// 暂停向回调投递消息，消息继续在队列中缓冲
+    pub fn pause(&self) {
+        if !self.is_paused.swap(true, Ordering::SeqCst) {
+            info!("Pausing message processor");
+        }
+    }
+    
+    /// 恢复向回调投递消息
+    pub fn resume(&self) {
+        if self.is_paused.swap(false, Ordering::SeqCst) {
+            info!("Resuming message processor");
+        }
+    }
+    
+    /// 检查处理器是否处于暂停状态
+    pub fn is_paused(&self) -> bool {
+        self.is_paused.load(Ordering::SeqCst)
+    }
+    
+    /// 获取处理器当前状态
+    pub fn get_state(&self) -> ProcessorState {
+        if !self.is_running() {
+            ProcessorState::Stopped
+        } else if self.is_paused() {
+            ProcessorState::Paused
+        } else {
+            ProcessorState::Running
+        }
     }
     
     /// 提交消息进行处理
+    ///
+    /// 严格模式（`ProcessorConfig::strict`）下队列满返回`QueueFull`错误
     pub async fn submit_message(&self, raw_data: &[u8]) -> Result<()> {
+        self.submit_message_detailed(raw_data).await.map(|_| ())
+    }
+    
+    /// 提交消息进行处理，并返回入队或丢弃的决策
+    ///
+    /// 消息解析或校验失败时返回错误，并分别计入`parse_errors`、`validation_errors`和丢弃数
+    pub async fn submit_message_detailed(&self, raw_data: &[u8]) -> Result<SubmitOutcome> {
+        self.submit_single(RawFrame::Shared(raw_data), None).await
+    }
+    
+    /// 提交消息进行处理，并标记消息来源，来源会写入`VehicleMessage::source`和`ProcessedEvent::source`
+    pub async fn submit_message_from(&self, raw_data: &[u8], source: &str) -> Result<()> {
+        self.submit_single(RawFrame::Shared(raw_data), Some(source)).await.map(|_| ())
+    }
+    
+    /// 提交消息并标记来源，返回入队或丢弃的决策
+    pub async fn submit_message_detailed_from(&self, raw_data: &[u8], source: &str) -> Result<SubmitOutcome> {
+        self.submit_single(RawFrame::Shared(raw_data), Some(source)).await
+    }
+    
+    /// 批量提交消息，整批共用一次配置与队列表的加锁，适用于回放和桥接场景
+    ///
+    /// 批次处理期间会阻塞采样配置、地理围栏的更新；内联服务的消息在批量提交时照常入队
+    pub fn submit_batch(&self, frames: &[&[u8]]) -> BatchResult {
+        let guards = self.submit_guards();
+        let mut result = BatchResult::default();
+        for frame in frames {
+            result.push(self.submit_batched(RawFrame::Shared(frame), &guards));
+        }
+        debug!(
+            "Submitted batch of {} frames: {} enqueued, {} failed",
+            result.len(),
+            result.enqueued,
+            result.failed
+        );
+        result
+    }
+    
+    /// 批量提交其中一帧，不做内联处理
+    fn submit_batched(&self, frame: RawFrame<'_>, guards: &SubmitGuards<'_>) -> Result<SubmitOutcome> {
+        self.submit_with(frame, None, guards, false).map(|submission| match submission {
+            Submission::Done(outcome) => outcome,
+            Submission::Inline { .. } => unreachable!("inline processing disabled for batches"),
+        })
+    }
+    
+    /// 获取提交路径所需的读锁
+    fn submit_guards(&self) -> SubmitGuards<'_> {
+        SubmitGuards {
+            geo_filter: self.geo_filter.read(),
+            scene_filter: self.scene_filter.read(),
+            // 关闭采样时跳过采样计算
+            sampling: self.is_sampling_enabled().then(|| self.sampling_config.read()),
+            lane_senders: self.lane_senders.read(),
+        }
+    }
+    
+    /// 提交单条消息，内联服务的回调在释放读锁后执行
+    async fn submit_single(&self, frame: RawFrame<'_>, source: Option<&str>) -> Result<SubmitOutcome> {
+        let submission = {
+            let guards = self.submit_guards();
+            self.submit_with(frame, source, &guards, true)?
+        };
+        match submission {
+            Submission::Done(outcome) => Ok(outcome),
+            Submission::Inline { priority, queued, permit } => {
+                let dispatch = self.inline_dispatch.read().clone();
+                match dispatch {
+                    Some(dispatch) => {
+                        let service = queued.message.service.clone();
+                        dispatch.handle_inline(priority, *queued, permit).await;
+                        debug!("Message processed inline: service={}, priority={:?}", service, priority);
+                    }
+                    None => self.performance_monitor.record_dropped("processor stopped"),
+                }
+                Ok(SubmitOutcome::ProcessedInline { priority })
+            }
+        }
+    }
+    
+    /// 在已持有读锁的情况下提交单条消息，`allow_inline`为false时内联服务的消息也入队
+    fn submit_with(
+        &self,
+        frame: RawFrame<'_>,
+        source: Option<&str>,
+        guards: &SubmitGuards<'_>,
+        allow_inline: bool,
+    ) -> Result<Submission> {
         let start_time = Instant::now();
+        // 原地解析会修改帧内容，录制需在解析之前
+        let raw_data = frame.bytes();
+        self.record_frame(raw_data);
+        
+        // 记录消息大小并拦截超大消息，避免解析异常输入
+        self.performance_monitor.record_message_size(raw_data.len());
+        if self.config.max_message_bytes.is_some_and(|max| raw_data.len() > max) {
+            self.performance_monitor.record_dropped("oversize");
+            return Ok(Submission::Done(SubmitOutcome::Dropped { reason: "oversize" }));
+        }
         
         // 解析JSON消息
-        let parsed_data: serde_json::Value = serde_json::from_slice(raw_data)
-            .map_err(|e| VehicleError::JsonError(e))?;
+        let stage_start = Instant::now();
+        let mut parsed_data = match frame.parse() {
+            Ok(parsed_data) => parsed_data,
+            Err(e) => {
+                self.performance_monitor.record_parse_error();
+                return Err(e);
+            }
+        };
         
-        // 提取基本字段
-        let service = parsed_data["service"]
-            .as_str()
-            .ok_or_else(|| VehicleError::InvalidMessage("Missing service field".to_string()))?;
+        // 按配置的字段位置提取基本字段
+        let paths = &self.config.field_paths;
+        // 服务类型可以是服务名，也可以是整数编码（服务类型字段或service_code字段）
+        let service = match parsed_data.pointer(paths.service()) {
+            Some(serde_json::Value::String(service)) => service.clone(),
+            Some(code @ serde_json::Value::Number(_)) => self.resolve_service_code(code)?,
+            _ => match parsed_data.pointer(paths.service_code()) {
+                Some(code) => self.resolve_service_code(code)?,
+                None => {
+                    self.performance_monitor.record_invalid("missing service");
+                    return Err(VehicleError::InvalidMessage("Missing service field".to_string()));
+                }
+            },
+        };
             
-        let params = parsed_data["params"]
-            .as_object()
-            .ok_or_else(|| VehicleError::InvalidMessage("Missing params field".to_string()))?;
+        // 区分params缺失与类型错误，分别计入丢弃原因
+        let params = match parsed_data.pointer(paths.params()) {
+            Some(serde_json::Value::Object(params)) => params,
+            Some(other) => {
+                self.performance_monitor.record_invalid("params not object");
+                return Err(VehicleError::InvalidMessage(format!(
+                    "params is not an object (got {})",
+                    json_type_name(other)
+                )));
+            }
+            None => {
+                self.performance_monitor.record_invalid("missing params");
+                return Err(VehicleError::InvalidMessage("Missing params field".to_string()));
+            }
+        };
             
-        let vin = params["vin"]
-            .as_str()
+        // 空白VIN与缺失VIN一致处理
+        let vin = parsed_data
+            .pointer(paths.vin())
+            .and_then(|v| v.as_str())
+            .filter(|vin| !vin.trim().is_empty())
             .unwrap_or("UNKNOWN");
             
-        let timestamp = params["timestamp"]
-            .as_f64()
-            .unwrap_or_else(|| chrono::Utc::now().timestamp() as f64);
+        // 时间戳可以是数字或数字字符串；缺失或无法解析时使用到达时间补全，并标记来源
+        let raw_timestamp = parsed_data.pointer(paths.timestamp()).filter(|v| !v.is_null());
+        let (timestamp, origin) = match raw_timestamp.map(parse_timestamp) {
+            Some(Some(timestamp)) => (timestamp, TimestampOrigin::Provided),
+            Some(None) if self.config.strict => {
+                self.performance_monitor.record_invalid("bad timestamp");
+                return Err(VehicleError::InvalidMessage(format!(
+                    "Unparseable timestamp: {}",
+                    raw_timestamp.map_or_else(String::new, |v| v.to_string())
+                )));
+            }
+            _ => {
+                self.performance_monitor.record_backfilled();
+                (self.config.clock.unix_timestamp().trunc(), TimestampOrigin::Backfilled)
+            }
+        };
         
         // 构造消息对象
         let mut message = VehicleMessage::new(
-            service.to_string(),
+            service.clone(),
             vin.to_string(),
             timestamp,
         );
+        message.origin = origin;
+        message.source = source.map(str::to_string);
+        
+        // 提取params中的data字段，延迟取数模式下改为共享原始params
+        if !self.config.lazy_data {
+            if let Some(data) = params.get("data") {
+                message.params.insert("data".to_string(), data.clone());
+            }
+        }
         
-        // 提取params中的data字段
-        if let Some(data) = params.get("data") {
-            message.params.insert("data".to_string(), data.clone());
+        // 保留trace上下文字段，用于链路传播
+        #[cfg(feature = "otel")]
+        for key in ["traceparent", "tracestate"] {
+            if let Some(value) = params.get(key).filter(|v| v.is_string()) {
+                message.params.insert(key.to_string(), value.clone());
+            }
         }
         
         // 添加其他字段
-        message.channel = service.to_string();
+        message.channel = service.clone();
         message.run_scene = params.get("run_scene")
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
+        message.correlation_id = params.get("id").and_then(|v| match v {
+            serde_json::Value::String(s) => Some(s.clone()),
+            serde_json::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        });
+        let seq = params.get("seq").and_then(|v| v.as_u64());
+        
+        if self.config.lazy_data {
+            let params = parsed_data.pointer_mut(paths.params()).map(serde_json::Value::take);
+            message.lazy_params = params.map(Arc::new);
+        }
+        
+        self.performance_monitor.record_stage(PipelineStage::Parse, stage_start.elapsed());
         
         // 验证消息
-        if !message.is_valid() {
-            self.performance_monitor.record_dropped("invalid message");
-            return Err(VehicleError::InvalidMessage("Message validation failed".to_string()));
+        let stage_start = Instant::now();
+        let validation = message.validate_with(&self.config.validation);
+        self.performance_monitor.record_stage(PipelineStage::Validate, stage_start.elapsed());
+        if let Err(reason) = validation {
+            self.performance_monitor.record_invalid(reason);
+            return Err(VehicleError::InvalidMessage(format!("Message validation failed: {}", reason)));
+        }
+        
+        // 上游序列号缺口检测，在本地过滤之前进行，只反映到达前的丢失
+        if let Some(seq) = seq {
+            self.sequences.observe(&message.vin, &message.service, seq);
+        }
+        
+        // 时间戳乱序检测，补全的到达时间不反映上游顺序，不参与
+        if message.origin == TimestampOrigin::Provided {
+            self.timestamp_order.observe(&message.vin, &message.service, message.timestamp);
+        }
+        
+        // 记录车辆在线，同样在本地过滤之前进行
+        if message.vin != "UNKNOWN" {
+            self.vins.touch(&message.vin);
+        }
+        
+        // 运行场景过滤
+        if let Some(ref filter) = *guards.scene_filter {
+            if !filter.allows(&message) {
+                self.performance_monitor.record_dropped("scene filtered");
+                return Ok(Submission::Done(SubmitOutcome::Filtered));
+            }
+        }
+        
+        // 地理围栏过滤
+        if let Some(ref filter) = *guards.geo_filter {
+            if !filter.allows(&message) {
+                self.performance_monitor.record_dropped("geo filtered");
+                return Ok(Submission::Done(SubmitOutcome::Filtered));
+            }
         }
         
         // 消息去重检查
-        let message_hash = message.get_hash();
-        if self.is_duplicate_message(message_hash) {
-            self.performance_monitor.record_dropped("duplicate message");
-            return Ok(());
+        if self.config.dedup.applies_to(&message.service) {
+            let stage_start = Instant::now();
+            let message_hash = message.hash_with(&self.config.dedup_key);
+            let is_duplicate = self.is_duplicate_message(&message.service, message_hash);
+            self.performance_monitor.record_stage(PipelineStage::Dedup, stage_start.elapsed());
+            if is_duplicate {
+                self.performance_monitor.record_dropped("duplicate message");
+                return Ok(Submission::Done(SubmitOutcome::Deduplicated));
+            }
         }
         
         // 采样检查
-        if !self.should_process_message(&message.service) {
-            self.performance_monitor.record_dropped("sampling");
-            return Ok(());
+        if let Some(ref sampling) = guards.sampling {
+            let stage_start = Instant::now();
+            let should_process = sampling.should_process_vin(&message.service, &message.vin);
+            self.sampling_observer.record(&message.service, should_process);
+            self.performance_monitor.record_stage(PipelineStage::Sample, stage_start.elapsed());
+            if !should_process {
+                self.performance_monitor.record_dropped("sampling");
+                return Ok(Submission::Done(SubmitOutcome::Sampled));
+            }
         }
         
         // 根据优先级分发消息
-        let priority = MessagePriority::from_service(&message.service);
-        let result = match priority {
-            MessagePriority::Critical => {
-                self.critical_tx.try_send(message)
-                    .map_err(|_| VehicleError::QueueFull)
-            }
-            MessagePriority::Normal => {
-                self.normal_tx.try_send(message)
-                    .map_err(|_| VehicleError::QueueFull)
-            }
-            MessagePriority::Background => {
-                self.background_tx.try_send(message)
-                    .map_err(|_| VehicleError::QueueFull)
+        let stage_start = Instant::now();
+        let priority = self.priority_for(&message.service);
+        let queued = QueuedMessage {
+            id: self.next_message_id.fetch_add(1, Ordering::Relaxed),
+            message,
+            enqueued_at: stage_start,
+        };
+        
+        // 内联服务交回调用方，在释放读锁后直接执行回调
+        if allow_inline
+            && self.config.inline_services.contains(&service)
+            && self.is_running()
+            && !self.is_paused()
+        {
+            // 并发回调已达上限时按正常路径入队
+            if let Ok(permit) = self.callback_permits.clone().try_acquire_owned() {
+                self.performance_monitor.record_received();
+                return Ok(Submission::Inline { priority, queued: Box::new(queued), permit });
             }
+        }
+        
+        // 队列满时取回消息，供关键消息溢写
+        let result = match guards.lane_senders.get(&priority) {
+            Some(sender) => sender
+                .try_send(queued)
+                .map(|_| sender.max_capacity() - sender.capacity())
+                .map_err(|e| match e {
+                    mpsc::error::TrySendError::Full(queued) => Some(queued),
+                    mpsc::error::TrySendError::Closed(_) => None,
+                }),
+            None => Err(None),
         };
+        self.performance_monitor.record_stage(PipelineStage::Enqueue, stage_start.elapsed());
         
         match result {
-            Ok(_) => {
+            Ok(depth) => {
+                self.watermarks.observe(priority, depth, priority.queue_capacity());
                 self.performance_monitor.record_received();
                 let submission_time = start_time.elapsed();
                 
@@ -235,284 +1563,2500 @@ impl MessageProcessor {
                 }
                 
                 debug!("Message submitted: service={}, priority={:?}", service, priority);
+                Ok(Submission::Done(SubmitOutcome::Enqueued { priority }))
             }
-            Err(VehicleError::QueueFull) => {
+            Err(overflow) => {
+                if let Some(queued) = overflow.filter(|_| priority == MessagePriority::Critical) {
+                    if self.spill_message(queued.message) {
+                        debug!("Critical queue full, spilled message: service={}", service);
+                        return Ok(Submission::Done(SubmitOutcome::Spilled));
+                    }
+                }
+                
                 self.performance_monitor.record_dropped("queue full");
                 warn!("Queue full for priority {:?}, service: {}", priority, service);
+                if self.config.strict {
+                    Err(VehicleError::QueueFull)
+                } else {
+                    Ok(Submission::Done(SubmitOutcome::Dropped { reason: "queue full" }))
+                }
+            }
+        }
+    }
+    
+    /// 将整数服务编码还原为服务名
+    fn resolve_service_code(&self, code: &serde_json::Value) -> Result<String> {
+        match code.as_u64().and_then(|code| self.config.service_codes.name(code)) {
+            Some(service) => Ok(service.to_string()),
+            None => {
+                self.performance_monitor.record_invalid("unknown service code");
+                Err(VehicleError::InvalidMessage(format!("Unknown service code: {}", code)))
+            }
+        }
+    }
+    
+    /// 打开配置的溢写文件，已打开时不做处理
+    fn open_spill(&self) -> Result<()> {
+        let Some(ref config) = self.config.spill else {
+            return Ok(());
+        };
+        {
+            let mut spill = self.spill.lock();
+            if spill.is_none() {
+                *spill = Some(SpillBuffer::open(config)?);
+                info!("Critical overflow spill enabled: {}", config.path.display());
+            }
+        }
+        if self.spill_writer.get().is_none() {
+            let monitor = self.performance_monitor.clone();
+            let writer = SpillWriter::spawn(self.spill.clone(), move |reason| monitor.record_dropped(reason))?;
+            let _ = self.spill_writer.set(writer);
+        }
+        Ok(())
+    }
+    
+    /// 把溢出的关键消息交给溢写线程，返回是否接收
+    ///
+    /// 写盘在溢写线程上异步完成，超出文件大小上限或写入失败时由溢写线程计入丢弃
+    fn spill_message(&self, message: VehicleMessage) -> bool {
+        self.spill_writer.get().is_some_and(|writer| writer.try_write(message))
+    }
+    
+    /// 开始录制接入帧（不压缩），录制内容可通过`replay_recording`回放
+    pub fn enable_recording(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.enable_recording_compressed(path, Compression::None)
+    }
+    
+    /// 开始以指定压缩方式录制接入帧，已在录制时先结束之前的录制
+    pub fn enable_recording_compressed(&self, path: impl AsRef<Path>, compression: Compression) -> Result<()> {
+        let recorder = Recorder::create(path.as_ref(), compression)?;
+        let previous = {
+            let mut slot = self.recorder.lock();
+            self.recording.store(true, Ordering::Release);
+            slot.replace(recorder)
+        };
+        if let Some(previous) = previous {
+            previous.finish()?;
+        }
+        info!("Recording frames to {} ({:?})", path.as_ref().display(), compression);
+        Ok(())
+    }
+    
+    /// 结束录制并刷新文件，返回录制的帧数；未在录制时返回0
+    pub fn disable_recording(&self) -> Result<u64> {
+        let recorder = {
+            let mut slot = self.recorder.lock();
+            self.recording.store(false, Ordering::Release);
+            slot.take()
+        };
+        let Some(recorder) = recorder else {
+            return Ok(0);
+        };
+        let frames = recorder.frames();
+        recorder.finish()?;
+        Ok(frames)
+    }
+    
+    /// 回放录制文件中的帧，压缩格式按文件头自动识别
+    ///
+    /// 帧逐条从文件流式读取并提交，读取出错时返回错误，此前的帧已提交
+    pub fn replay_recording(&self, path: impl AsRef<Path>) -> Result<BatchResult> {
+        let reader = RecordingReader::open(path)?;
+        let guards = self.submit_guards();
+        let mut result = BatchResult::default();
+        for frame in reader {
+            let mut frame = frame?;
+            result.push(self.submit_batched(RawFrame::Owned(&mut frame), &guards));
+        }
+        debug!("Replayed {} recorded frames, {} failed", result.len(), result.failed);
+        Ok(result)
+    }
+    
+    /// 录制中时写入一帧，写入失败只记录日志，不影响接入
+    fn record_frame(&self, raw_data: &[u8]) {
+        if !self.recording.load(Ordering::Acquire) {
+            return;
+        }
+        let mut recorder = self.recorder.lock();
+        if let Some(recorder) = recorder.as_mut() {
+            if let Err(e) = recorder.record(raw_data) {
+                error!("Failed to record frame: {}", e);
+            }
+        }
+    }
+    
+    /// 获取最近分发的消息摘要（从旧到新），未配置`recent_messages`时返回空
+    pub fn recent_messages(&self) -> Vec<RecentMessage> {
+        self.recent_messages.as_ref().map_or_else(Vec::new, |recent| recent.snapshot())
+    }
+    
+    /// 获取溢写统计，未配置溢写时返回None
+    pub fn spill_stats(&self) -> Option<SpillStats> {
+        self.spill.lock().as_ref().map(SpillBuffer::stats)
+    }
+    
+    /// 获取同步接入通道的发送端
+    ///
+    /// 发送端可克隆，线程或FFI等非异步生产者用`blocking_send`推送原始帧（不能在异步上下文中调用），
+    /// 异步生产者用`send`；处理器运行期间由内部循环取出并提交，通道满时`try_send`返回错误
+    pub fn ingest_sender(&self) -> mpsc::Sender<Vec<u8>> {
+        self.ingest_tx.clone()
+    }
+    
+    /// 获取接入帧缓冲区池
+    ///
+    /// 生产者从池中取缓冲区填充帧后推送到接入通道，帧提交后由处理器归还
+    pub fn buffer_pool(&self) -> Arc<BufferPool> {
+        self.buffer_pool.clone()
+    }
+    
+    /// 从接入通道取出原始帧并提交处理
+    async fn run_ingest_loop(&self) {
+        info!("Started ingest loop");
+        
+        // 空闲时挂起等待新帧，停止时随start中的select一起取消，recv取消安全不会丢帧
+        let mut ingest_rx = self.ingest_rx.lock().await;
+        while let Some(mut frame) = ingest_rx.recv().await {
+            // 接入通道的帧归处理器所有，可原地解析
+            if let Err(e) = self.submit_single(RawFrame::Owned(&mut frame), None).await {
+                debug!("Rejected ingested frame: {}", e);
+            }
+            self.buffer_pool.release(frame);
+        }
+        
+        info!("Ingest loop stopped");
+    }
+    
+    /// 获取优先级队列当前的消息数
+    pub fn queue_depth(&self, priority: MessagePriority) -> usize {
+        self.lane_senders
+            .read()
+            .get(&priority)
+            .map_or(0, |sender| sender.max_capacity() - sender.capacity())
+    }
+    
+    /// 获取处理器积压程度：各优先级队列填充率的最大值
+    pub fn backlog_ratio(&self) -> f32 {
+        MessagePriority::all()
+            .into_iter()
+            .map(|priority| self.queue_depth(priority) as f32 / priority.queue_capacity() as f32)
+            .fold(0.0, f32::max)
+    }
+    
+    /// 当前正在执行的回调数
+    pub fn callbacks_in_flight(&self) -> usize {
+        self.callbacks_in_flight.load(Ordering::SeqCst)
+    }
+    
+    /// 获取运行时控制命令的发送端，供管理接口或REPL在不重启的情况下调整处理器
+    ///
+    /// 命令在处理器运行期间由控制循环依次执行，未运行时在下次启动后执行
+    pub fn control_sender(&self) -> mpsc::Sender<ControlCommand> {
+        self.control_tx.clone()
+    }
+    
+    /// 依次执行控制命令
+    async fn run_control_loop(&self) {
+        let mut control_rx = self.control_rx.lock().await;
+        while let Some(command) = control_rx.recv().await {
+            self.execute_control(command);
+        }
+    }
+    
+    /// 执行单条控制命令
+    fn execute_control(&self, command: ControlCommand) {
+        info!("Executing control command: {:?}", command);
+        match command {
+            ControlCommand::SetSampling { service, rate } => self.update_sampling_config(&service, rate),
+            ControlCommand::Pause => self.pause(),
+            ControlCommand::Resume => self.resume(),
+            ControlCommand::ResetStats => self.performance_monitor.reset_stats(),
+            ControlCommand::SetPriority { service, priority } => self.set_service_priority(&service, priority),
+        }
+    }
+    
+    /// 覆盖服务的优先级，None时恢复按服务类型的默认映射
+    ///
+    /// 运行中覆盖到尚未启动处理任务的优先级（如High/Low）时启动该队列的任务
+    pub fn set_service_priority(&self, service: &str, priority: Option<MessagePriority>) {
+        let mut overrides = self.priority_overrides.write();
+        match priority {
+            Some(priority) => {
+                overrides.insert(service.to_string(), priority);
+                info!("Overrode priority for {}: {:?}", service, priority);
+                drop(overrides);
+                self.spawn_idle_lane(priority);
+            }
+            None => {
+                if overrides.remove(service).is_some() {
+                    info!("Restored default priority for {}", service);
+                }
+            }
+        }
+    }
+    
+    /// 默认映射可能产生的优先级，加上已覆盖的优先级
+    fn used_priorities(&self) -> HashSet<MessagePriority> {
+        let mut used = HashSet::from([MessagePriority::Critical, MessagePriority::Normal, MessagePriority::Background]);
+        used.extend(self.priority_overrides.read().values().copied());
+        used
+    }
+    
+    /// 为尚未启动处理任务的优先级队列启动任务，任务随本次运行的关闭令牌结束
+    fn spawn_idle_lane(&self, priority: MessagePriority) {
+        let Some(dispatch) = self.inline_dispatch.read().clone() else {
+            return;
+        };
+        let Some(receiver) = self.idle_lanes.lock().remove(&priority) else {
+            return;
+        };
+        info!("Starting {:?} priority processor on demand", priority);
+        Self::spawn_processor_task(
+            self.config.runtime.as_ref(),
+            receiver,
+            priority,
+            (*dispatch).clone(),
+            self.is_running.clone(),
+            self.is_paused.clone(),
+            self.shutdown.lock().clone(),
+        );
+    }
+    
+    /// 获取服务的优先级（含覆盖）
+    pub fn priority_for(&self, service: &str) -> MessagePriority {
+        self.priority_overrides
+            .read()
+            .get(service)
+            .copied()
+            .unwrap_or_else(|| MessagePriority::from_service(service))
+    }
+    
+    /// 设置队列水位回调，队列深度越过高水位或回落至低水位时调用
+    ///
+    /// 回调在提交或处理消息的路径上同步调用，应尽量轻量
+    pub fn set_watermark_handler(&self, handler: WatermarkHandler) {
+        *self.watermarks.handler.write() = Some(handler);
+    }
+    
+    /// 获取上游序列号缺口统计（消息携带`params.seq`时）
+    pub fn get_gap_stats(&self) -> GapStats {
+        self.sequences.stats()
+    }
+    
+    /// 获取按VIN+服务统计的时间戳乱序情况
+    pub fn get_order_stats(&self) -> OrderStats {
+        self.timestamp_order.stats()
+    }
+    
+    /// 调试用：列出去重缓存中的消息hash及其距首次出现的时长
+    ///
+    /// 用于排查消息为何被判为重复；外部去重后端可能不支持枚举，此时返回空
+    pub fn dedup_snapshot(&self) -> Vec<(u64, Duration)> {
+        self.dedup_store.snapshot()
+    }
+    
+    /// 清空去重缓存，如生产者合理地重发消息时手动放行
+    pub fn clear_dedup_cache(&self) {
+        self.dedup_store.clear();
+        info!("Dedup cache cleared");
+    }
+    
+    /// 最近`within`时间内发送过消息的VIN（不受采样、过滤和去重影响）
+    pub fn active_vins(&self, within: Duration) -> Vec<String> {
+        self.vins.active_vins(within)
+    }
+    
+    /// 当前登记的VIN数量，超过`vin_expiry`未出现的VIN不计入
+    pub fn vin_count(&self) -> usize {
+        self.vins.len()
+    }
+    
+    /// 检查是否为重复消息
+    fn is_duplicate_message(&self, service: &str, message_hash: u64) -> bool {
+        // 如果在去重窗口内见过相同消息，认为是重复；放行的服务仍写入缓存
+        let seen = self.dedup_store.seen(message_hash, DEDUP_WINDOW);
+        if seen && self.config.dedup.bypasses(service) {
+            debug!("Duplicate {} message passed by dedup bypass", service);
+            return false;
+        }
+        seen
+    }
+    
+    /// 构造处理任务的分发上下文
+    fn dispatch_context(&self) -> DispatchContext {
+        DispatchContext {
+            callbacks: self.message_callbacks.clone(),
+            monitor: self.performance_monitor.clone(),
+            event_tx: self.event_tx.clone(),
+            alert_tx: self.alert_tx.clone(),
+            rules: self.rules.clone(),
+            watermarks: self.watermarks.clone(),
+            trace_messages: self.config.trace_messages,
+            dry_run: self.config.dry_run,
+            no_callback_policy: self.config.no_callback_policy,
+            pending_callback: self.pending_callback.clone(),
+            dispatched: self.dispatched.clone(),
+            stop_after: self.config.stop_after,
+            shutdown: self.shutdown.lock().clone(),
+            callback_permits: self.callback_permits.clone(),
+            callbacks_in_flight: self.callbacks_in_flight.clone(),
+            recent: self.recent_messages.clone(),
+            callback_timeouts: self.config.callback_timeouts.clone(),
+        }
+    }
+    
+    /// 生成处理任务
+    fn spawn_processor_task(
+        runtime: Option<&tokio::runtime::Handle>,
+        mut receiver: mpsc::Receiver<QueuedMessage>,
+        priority: MessagePriority,
+        dispatch: DispatchContext,
+        is_running: Arc<parking_lot::RwLock<bool>>,
+        is_paused: Arc<AtomicBool>,
+        shutdown: CancellationToken,
+    ) -> tokio::task::JoinHandle<()> {
+        runtime::spawn(runtime, async move {
+            let interval = priority.processing_interval();
+            info!("Started {:?} priority processor", priority);
+            
+            while *is_running.read() && !shutdown.is_cancelled() {
+                // 暂停期间不取消息，让消息留在队列中
+                if is_paused.load(Ordering::SeqCst) {
+                    sleep(interval).await;
+                    continue;
+                }
+                
+                match receiver.try_recv() {
+                    Ok(queued) => {
+                        let permit = dispatch.acquire_callback_permit().await;
+                        dispatch.handle_queued(priority, queued, receiver.len(), permit).await;
+                    }
+                    Err(mpsc::error::TryRecvError::Empty) => {
+                        // 没有消息，休眠一段时间
+                        sleep(interval).await;
+                    }
+                    Err(mpsc::error::TryRecvError::Disconnected) => {
+                        warn!("{:?} priority processor: channel disconnected", priority);
+                        break;
+                    }
+                }
+            }
+            
+            info!("{:?} priority processor stopped", priority);
+        })
+    }
+    
+    /// 生成加权公平调度任务，每轮按权重依次从高到低各优先级队列取消息
+    fn spawn_weighted_task(
+        runtime: Option<&tokio::runtime::Handle>,
+        mut lanes: Vec<(MessagePriority, mpsc::Receiver<QueuedMessage>)>,
+        weights: LaneWeights,
+        dispatch: DispatchContext,
+        is_running: Arc<parking_lot::RwLock<bool>>,
+        is_paused: Arc<AtomicBool>,
+        shutdown: CancellationToken,
+    ) -> tokio::task::JoinHandle<()> {
+        runtime::spawn(runtime, async move {
+            // 空闲时按Critical的轮询间隔等待，保证关键消息的延迟
+            let interval = MessagePriority::Critical.processing_interval();
+            info!("Started weighted fair processor with weights {:?}", weights);
+            
+            while *is_running.read() && !shutdown.is_cancelled() && !lanes.is_empty() {
+                if is_paused.load(Ordering::SeqCst) {
+                    sleep(interval).await;
+                    continue;
+                }
+                
+                let mut dispatched = 0;
+                let mut index = 0;
+                while index < lanes.len() {
+                    let (priority, ref mut receiver) = lanes[index];
+                    let mut disconnected = false;
+                    for _ in 0..weights.weight(priority) {
+                        // 并发回调已达上限时结束本轮，稍后重试
+                        let Ok(permit) = dispatch.callback_permits.clone().try_acquire_owned() else {
+                            break;
+                        };
+                        match receiver.try_recv() {
+                            Ok(queued) => {
+                                let depth = receiver.len();
+                                dispatch.handle_queued(priority, queued, depth, Some(permit)).await;
+                                dispatched += 1;
+                            }
+                            Err(mpsc::error::TryRecvError::Empty) => break,
+                            Err(mpsc::error::TryRecvError::Disconnected) => {
+                                warn!("{:?} priority lane: channel disconnected", priority);
+                                disconnected = true;
+                                break;
+                            }
+                        }
+                    }
+                    if disconnected {
+                        lanes.remove(index);
+                    } else {
+                        index += 1;
+                    }
+                }
+                
+                if dispatched == 0 {
+                    sleep(interval).await;
+                } else {
+                    // 未配置超时的回调是同步执行的，每轮结束后让出执行权
+                    tokio::task::yield_now().await;
+                }
+            }
+            
+            info!("Weighted fair processor stopped");
+        })
+    }
+    
+    /// 生成溢写消息重新接入任务：Critical队列有空位时按写入顺序取回溢写的消息
+    fn spawn_spill_recovery_task(
+        runtime: Option<&tokio::runtime::Handle>,
+        spill: Arc<parking_lot::Mutex<Option<SpillBuffer>>>,
+        lane_senders: Arc<RwLock<HashMap<MessagePriority, mpsc::Sender<QueuedMessage>>>>,
+        next_message_id: Arc<AtomicU64>,
+        monitor: Arc<PerformanceMonitor>,
+        is_running: Arc<parking_lot::RwLock<bool>>,
+        shutdown: CancellationToken,
+    ) -> tokio::task::JoinHandle<()> {
+        runtime::spawn(runtime, async move {
+            info!("Started spill recovery task");
+            
+            while *is_running.read() {
+                let sender = lane_senders.read().get(&MessagePriority::Critical).cloned();
+                let mut backoff = SPILL_RECOVERY_INTERVAL;
+                
+                // 溢写线程正在写盘时跳过本轮，不在异步任务中等待文件锁
+                let pending = spill
+                    .try_lock()
+                    .and_then(|buffer| buffer.as_ref().map(|buffer| buffer.stats().pending_messages))
+                    .unwrap_or(0) as usize;
+                
+                // 先占用队列空位再取出消息，避免取出后因队列满而丢失
+                let permits: Vec<_> = sender
+                    .as_ref()
+                    .map(|sender| std::iter::from_fn(|| sender.try_reserve().ok()).take(pending).collect())
+                    .unwrap_or_default();
+                if !permits.is_empty() {
+                    // 在阻塞线程池上读取溢写文件，不占用异步工作线程
+                    let count = permits.len();
+                    let buffer = spill.clone();
+                    let popped = tokio::task::spawn_blocking(move || {
+                        let mut messages = Vec::new();
+                        let mut guard = buffer.lock();
+                        let Some(buffer) = guard.as_mut() else {
+                            return (messages, None);
+                        };
+                        while messages.len() < count {
+                            match buffer.pop() {
+                                Ok(Some(message)) => messages.push(message),
+                                Ok(None) => break,
+                                Err(e) => return (messages, Some(e)),
+                            }
+                        }
+                        (messages, None)
+                    })
+                    .await;
+                    let (messages, read_error) = match popped {
+                        Ok(popped) => popped,
+                        Err(e) => (Vec::new(), Some(VehicleError::ConfigError(format!("Spill read task failed: {}", e)))),
+                    };
+                    
+                    let recovered = messages.len();
+                    for (permit, message) in permits.into_iter().zip(messages) {
+                        permit.send(QueuedMessage {
+                            id: next_message_id.fetch_add(1, Ordering::Relaxed),
+                            message,
+                            enqueued_at: Instant::now(),
+                        });
+                        monitor.record_received();
+                    }
+                    if recovered > 0 {
+                        debug!("Recovered {} spilled critical messages", recovered);
+                    }
+                    // 读取失败时退避，避免对同一处错误反复重试
+                    if let Some(e) = read_error {
+                        error!("Failed to read spilled message: {}", e);
+                        backoff = SPILL_ERROR_BACKOFF;
+                    }
+                }
+                
+                tokio::select! {
+                    _ = sleep(backoff) => {}
+                    _ = shutdown.cancelled() => break,
+                }
+            }
+            
+            info!("Spill recovery task stopped");
+        })
+    }
+    
+    /// 生成缓存清理任务
+    fn spawn_cache_cleanup_task(
+        runtime: Option<&tokio::runtime::Handle>,
+        dedup_store: Arc<dyn DedupStore>,
+        dedup: DedupConfig,
+        trackers: ExpiringTrackers,
+        vin_expiry: Duration,
+        is_running: Arc<parking_lot::RwLock<bool>>,
+        shutdown: CancellationToken,
+    ) -> tokio::task::JoinHandle<()> {
+        runtime::spawn(runtime, async move {
+            info!("Started cache cleanup task");
+            
+            while *is_running.read() {
+                // 清理超过保留时长的缓存条目（关闭去重时缓存为空，无需清理）
+                if dedup.enabled {
+                    let removed_count = dedup_store.evict_expired(dedup.retention, dedup.max_evictions_per_pass);
+                    if removed_count > 0 {
+                        debug!("Cleaned {} expired cache entries", removed_count);
+                    }
+                }
+                
+                trackers.evict_expired(vin_expiry);
+                
+                // 按配置间隔清理，关闭时立即退出
+                tokio::select! {
+                    _ = sleep(dedup.cleanup_interval) => {}
+                    _ = shutdown.cancelled() => break,
+                }
+            }
+            
+            info!("Cache cleanup task stopped");
+        })
+    }
+    
+    /// 订阅消息处理完成事件
+    pub fn subscribe_events(&self) -> broadcast::Receiver<ProcessedEvent> {
+        self.event_tx.subscribe()
+    }
+    
+    /// 注册告警规则，规则在消息处理成功后求值，同名规则会被替换
+    pub fn add_rule(&self, name: &str, rule: AlertRule) {
+        self.rules.add_rule(name, rule);
+        info!("Added alert rule: {}", name);
+    }
+    
+    /// 移除告警规则
+    pub fn remove_rule(&self, name: &str) -> bool {
+        let removed = self.rules.remove_rule(name);
+        if removed {
+            info!("Removed alert rule: {}", name);
+        }
+        removed
+    }
+    
+    /// 订阅告警
+    pub fn subscribe_alerts(&self) -> broadcast::Receiver<Alert> {
+        self.alert_tx.subscribe()
+    }
+    
+    /// 获取性能监控器，用于设置延迟预算、报告回调等
+    pub fn performance_monitor(&self) -> Arc<PerformanceMonitor> {
+        self.performance_monitor.clone()
+    }
+    
+    /// 获取各流水线阶段（解析/校验/去重/采样/入队/排队/回调）的耗时汇总
+    pub fn get_stage_timings(&self) -> StageTimings {
+        self.performance_monitor.get_stage_timings()
+    }
+    
+    /// 获取性能统计
+    pub fn get_stats(&self) -> ProcessingStats {
+        let mut stats = self.performance_monitor.get_stats();
+        self.fill_run_stats(&mut stats);
+        stats
+    }
+    
+    /// 累加上次运行保存的处理统计（如`StatsSnapshot::load`读取的快照），用于重启后延续统计
+    pub fn restore_stats(&self, previous: &ProcessingStats) {
+        self.performance_monitor.restore_stats(previous);
+    }
+    
+    /// 原子地获取统计快照并重置计数，用于按区间上报
+    pub fn snapshot_and_reset_stats(&self) -> ProcessingStats {
+        let mut stats = self.performance_monitor.snapshot_and_reset();
+        self.fill_run_stats(&mut stats);
+        stats
+    }
+    
+    /// 填充处理器运行相关的统计字段
+    fn fill_run_stats(&self, stats: &mut ProcessingStats) {
+        stats.processor_state = self.get_state();
+        stats.messages_dispatched = self.dispatched.load(Ordering::SeqCst);
+        stats.stop_after = self.config.stop_after;
+        stats.callbacks_in_flight = self.callbacks_in_flight.load(Ordering::SeqCst);
+    }
+    
+    /// 更新采样配置
+    pub fn update_sampling_config(&self, service: &str, rate: f32) {
+        let mut config = self.sampling_config.write();
+        config.set_rate(service, rate);
+        info!("Updated sampling rate for {}: {:.2}", service, rate);
+    }
+    
+    /// 更新服务的采样策略（概率、每n条取1、按VIN hash取模）
+    pub fn update_sampling_strategy(&self, service: &str, strategy: SamplingStrategy) -> Result<()> {
+        self.sampling_config.write().set_strategy(service, strategy)?;
+        info!("Updated sampling strategy for {}: {:?}", service, strategy);
+        Ok(())
+    }
+    
+    /// 开启或关闭采样，关闭时忽略采样配置处理所有消息（用于录制回放、调试）
+    pub fn set_sampling_enabled(&self, enabled: bool) {
+        if self.sampling_enabled.swap(enabled, Ordering::SeqCst) != enabled {
+            info!("Sampling {}", if enabled { "enabled" } else { "disabled" });
+        }
+    }
+    
+    /// 检查采样是否开启
+    pub fn is_sampling_enabled(&self) -> bool {
+        self.sampling_enabled.load(Ordering::Relaxed)
+    }
+    
+    /// 更新未配置服务的默认采样率
+    pub fn update_default_sampling_rate(&self, rate: f32) {
+        self.sampling_config.write().set_default_rate(rate);
+        info!("Updated default sampling rate: {:.2}", rate);
+    }
+    
+    /// 从TOML/JSON文件加载采样配置，并在文件变化时热加载
+    ///
+    /// 返回的监听器被drop后停止监听；无效文件会被拒绝，不影响运行中的配置；
+    /// 热加载是增量的，从文件中删除的条目保持上次设置的采样率
+    pub fn watch_sampling_config(&self, path: impl AsRef<Path>) -> Result<SamplingConfigWatcher> {
+        SamplingConfigWatcher::start(path.as_ref(), self.sampling_config.clone())
+    }
+    
+    /// 按glob模式更新采样率，如`sensor_*`
+    pub fn update_sampling_glob(&self, glob: &str, rate: f32) -> Result<()> {
+        self.sampling_config.write().set_glob_rate(glob, rate)?;
+        info!("Updated sampling rate for pattern {}: {:.2}", glob, rate);
+        Ok(())
+    }
+    
+    /// 按正则表达式更新采样率
+    pub fn update_sampling_regex(&self, regex: &str, rate: f32) -> Result<()> {
+        self.sampling_config.write().set_regex_rate(regex, rate)?;
+        info!("Updated sampling rate for regex {}: {:.2}", regex, rate);
+        Ok(())
+    }
+    
+    /// 设置地理围栏过滤，None表示不过滤
+    pub fn set_geo_filter(&self, filter: Option<GeoFilter>) {
+        *self.geo_filter.write() = filter;
+        info!("Updated geo filter: {:?}", filter);
+    }
+    
+    /// 获取当前地理围栏过滤
+    pub fn get_geo_filter(&self) -> Option<GeoFilter> {
+        *self.geo_filter.read()
+    }
+    
+    /// 设置运行场景过滤，None表示不过滤
+    pub fn set_scene_filter(&self, filter: Option<SceneFilter>) {
+        info!("Updated scene filter: {:?}", filter);
+        *self.scene_filter.write() = filter;
+    }
+    
+    /// 获取当前运行场景过滤
+    pub fn get_scene_filter(&self) -> Option<SceneFilter> {
+        self.scene_filter.read().clone()
+    }
+    
+    /// 添加始终全量处理的VIN（绕过服务采样率）
+    pub fn add_priority_vin(&self, vin: &str) {
+        self.sampling_config.write().add_priority_vin(vin);
+        info!("Added priority VIN: {}", vin);
+    }
+    
+    /// 移除始终全量处理的VIN
+    pub fn remove_priority_vin(&self, vin: &str) -> bool {
+        let removed = self.sampling_config.write().remove_priority_vin(vin);
+        if removed {
+            info!("Removed priority VIN: {}", vin);
+        }
+        removed
+    }
+    
+    /// 获取各服务近期的实际采样率，用于核对配置的采样率是否按预期生效
+    pub fn get_effective_sampling_rates(&self) -> HashMap<String, EffectiveSamplingRate> {
+        self.sampling_observer.rates(&self.sampling_config.read())
+    }
+    
+    /// 获取当前采样配置
+    pub fn get_sampling_config(&self) -> SamplingConfig {
+        self.sampling_config.read().clone()
+    }
+    
+    /// 接入帧字段位置
+    pub fn field_paths(&self) -> &FieldPaths {
+        &self.config.field_paths
+    }
+    
+    /// 获取当前生效配置的快照：构建时的配置加上运行中修改的采样、优先级和过滤设置
+    pub fn config_snapshot(&self) -> ConfigSnapshot {
+        let sampling = self.sampling_config.read();
+        let callback_timeouts = MessagePriority::all()
+            .into_iter()
+            .filter_map(|priority| self.config.callback_timeouts.get(&priority).map(|timeout| (priority, *timeout)))
+            .collect();
+        
+        ConfigSnapshot {
+            state: self.get_state(),
+            sampling_enabled: self.is_sampling_enabled(),
+            sampling_rates: sampling.rates.iter().map(|(service, rate)| (service.clone(), *rate)).collect(),
+            sampling_strategies: sampling
+                .strategies()
+                .map(|(service, strategy)| (service.to_string(), strategy))
+                .collect(),
+            sampling_patterns: sampling.patterns.iter().map(|p| (p.pattern.clone(), p.rate)).collect(),
+            default_sampling_rate: sampling.default_rate,
+            priority_vins: sampling.priority_vins.iter().cloned().collect(),
+            priority_overrides: self
+                .priority_overrides
+                .read()
+                .iter()
+                .map(|(service, priority)| (service.clone(), *priority))
+                .collect(),
+            queue_capacities: MessagePriority::all()
+                .into_iter()
+                .map(|priority| (priority, priority.queue_capacity()))
+                .collect(),
+            lane_weights: self.config.lane_weights,
+            dedup: self.config.dedup.clone(),
+            dedup_window: DEDUP_WINDOW,
+            geo_filter: self.get_geo_filter(),
+            scene_filter: self.get_scene_filter(),
+            strict: self.config.strict,
+            dry_run: self.config.dry_run,
+            lazy_data: self.config.lazy_data,
+            max_message_bytes: self.config.max_message_bytes,
+            inline_services: self.config.inline_services.iter().cloned().collect(),
+            max_concurrent_callbacks: self.config.max_concurrent_callbacks,
+            callback_timeouts,
+            latency_budgets: self.performance_monitor.latency_budgets().into_iter().collect(),
+            stop_after: self.config.stop_after,
+            recent_messages: self.config.recent_messages,
+            recording: self.recording.load(Ordering::Acquire),
+        }
+    }
+    
+    /// 检查处理器是否正在运行
+    pub fn is_running(&self) -> bool {
+        *self.is_running.read()
+    }
+}
+
+impl Default for MessageProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for MessageProcessor {
+    fn drop(&mut self) {
+        // 处理任务只持有运行标志等共享状态，不持有处理器本身，标志翻转后即退出
+        if self.is_running() {
+            self.stop();
+        }
+        // 结束未关闭的录制，写出gzip尾部
+        if let Err(e) = self.disable_recording() {
+            error!("Failed to finish recording: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    
+    #[tokio::test]
+    async fn test_message_processor_creation() {
+        let processor = MessageProcessor::new();
+        assert!(!processor.is_running());
+        
+        let stats = processor.get_stats();
+        assert_eq!(stats.messages_received, 0);
+        assert_eq!(stats.messages_processed, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_message_submission() {
+        let processor = MessageProcessor::new();
+        
+        let test_message = r#"{
+            "service": "tracking",
+            "params": {
+                "vin": "TEST_VIN_123",
+                "timestamp": 1234567890.0,
+                "data": {"x": 1.0, "y": 2.0}
+            }
+        }"#;
+        
+        let result = processor.submit_message(test_message.as_bytes()).await;
+        // 由于没有接收端，消息会被丢弃，但这不应该导致错误
+        // 我们只测试消息解析和验证是否正确
+        assert!(result.is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_message_callback() {
+        let processor = MessageProcessor::new();
+        let processed_count = Arc::new(AtomicUsize::new(0));
+        let count_clone = processed_count.clone();
+        
+        // 设置回调函数
+        processor.set_callback(Arc::new(move |message| {
+            count_clone.fetch_add(1, Ordering::SeqCst);
+            assert_eq!(message.service, "tracking");
+            Ok(())
+        }));
+        
+        // 启动处理器
+        let processor_handle = {
+            let processor_clone = Arc::new(processor);
+            let processor_ref = processor_clone.clone();
+            tokio::spawn(async move {
+                processor_ref.start().await
+            })
+        };
+        
+        // 等待处理器启动
+        sleep(Duration::from_millis(100)).await;
+        
+        // 提交测试消息
+        // let test_message = r#"{
+        //     "service": "tracking",
+        //     "params": {
+        //         "vin": "TEST_VIN_123",
+        //         "timestamp": 1234567890.0,
+        //         "data": {"x": 1.0, "y": 2.0}
+        //     }
+        // }"#;
+        
+        // 注意：这里需要重新获取processor的引用来提交消息
+        // 由于processor已经被移动到Arc中，我们需要重新设计这个测试
+        
+        // 停止处理器
+        sleep(Duration::from_millis(100)).await;
+        processor_handle.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_sampling_config() {
+        let processor = MessageProcessor::new();
+        
+        // 测试默认配置
+        let config = processor.get_sampling_config();
+        assert_eq!(config.get_rate("tracking"), 1.0);
+        assert_eq!(config.get_rate("traj"), 0.1);
+        
+        // 更新配置
+        processor.update_sampling_config("test_service", 0.5);
+        let updated_config = processor.get_sampling_config();
+        assert_eq!(updated_config.get_rate("test_service"), 0.5);
+    }
+    
+    #[tokio::test]
+    async fn test_duplicate_message_detection() {
+        let processor = MessageProcessor::new();
+        
+        let test_message = r#"{
+            "service": "tracking",
+            "params": {
+                "vin": "TEST_VIN_123",
+                "timestamp": 1234567890.0,
+                "data": {"x": 1.0, "y": 2.0}
+            }
+        }"#;
+        
+        // 第一次提交应该成功
+        let result1 = processor.submit_message(test_message.as_bytes()).await;
+        assert!(result1.is_ok());
+        
+        // 等待统计更新
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let stats1 = processor.get_stats();
+        
+        // 立即重复提交应该被去重
+        let result2 = processor.submit_message(test_message.as_bytes()).await;
+        assert!(result2.is_ok()); // 不会报错，但会被去重
+        
+        // 等待统计更新
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let stats2 = processor.get_stats();
+        
+        // 第二次提交应该被去重，所以接收计数不应该增加
+        assert_eq!(stats1.messages_received, stats2.messages_received);
+    }
+    
+    #[tokio::test]
+    async fn test_traced_dispatch_emits_event_with_id() {
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            trace_messages: true,
+            ..Default::default()
+        });
+        processor.set_callback(Arc::new(|message| {
+            assert_eq!(message.vin, "TEST_VIN_123");
+            Ok(())
+        }));
+        
+        let mut events = processor.subscribe_events();
+        let mut message = VehicleMessage::new(
+            "tracking".to_string(),
+            "TEST_VIN_123".to_string(),
+            1234567890.0,
+        );
+        message.params.insert("data".to_string(), serde_json::json!({"x": 1.0}));
+        
+        processor.dispatch_context().dispatch(
+            MessagePriority::Critical,
+            QueuedMessage { id: 42, message, enqueued_at: Instant::now() },
+            None,
+        ).await;
+        
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.message_id, 42);
+        assert_eq!(event.service, "tracking");
+        assert_eq!(event.vin, "TEST_VIN_123");
+        assert_eq!(event.priority, MessagePriority::Critical);
+    }
+    
+    #[tokio::test]
+    async fn test_pause_and_resume() {
+        let processor = MessageProcessor::new();
+        let processed_count = Arc::new(AtomicUsize::new(0));
+        let count_clone = processed_count.clone();
+        processor.set_callback(Arc::new(move |_| {
+            count_clone.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }));
+        
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(processor.get_state(), ProcessorState::Running);
+        
+        processor.pause();
+        assert_eq!(processor.get_stats().processor_state, ProcessorState::Paused);
+        
+        let test_message = r#"{
+            "service": "tracking",
+            "params": {
+                "vin": "TEST_VIN_123",
+                "timestamp": 1234567890.0,
+                "data": {"x": 1.0, "y": 2.0}
+            }
+        }"#;
+        processor.submit_message(test_message.as_bytes()).await.unwrap();
+        sleep(Duration::from_millis(50)).await;
+        
+        // 暂停期间消息已入队但未投递
+        assert_eq!(processor.get_stats().messages_received, 1);
+        assert_eq!(processed_count.load(Ordering::SeqCst), 0);
+        
+        processor.resume();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(processed_count.load(Ordering::SeqCst), 1);
+        
+        processor.stop();
+        assert_eq!(processor.get_state(), ProcessorState::Stopped);
+        let result = tokio::time::timeout(Duration::from_secs(1), handle).await;
+        assert!(result.is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_speed_alert_published_after_processing() {
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            speed_alert_threshold: Some(50.0),
+            ..Default::default()
+        });
+        processor.set_callback(Arc::new(|_| Ok(())));
+        let mut alerts = processor.subscribe_alerts();
+        
+        let dispatch = processor.dispatch_context();
+        for (id, speed) in [(1, 30.0), (2, 72.5)] {
+            let mut message = VehicleMessage::new(
+                "tracking".to_string(),
+                "TEST_VIN_123".to_string(),
+                1234567890.0,
+            );
+            message.params.insert(
+                "data".to_string(),
+                serde_json::json!({"x": 1.0, "y": 2.0, "speed": speed}),
+            );
+            dispatch.dispatch(MessagePriority::Critical, QueuedMessage { id, message, enqueued_at: Instant::now() }, None).await;
+        }
+        
+        let alert = alerts.try_recv().unwrap();
+        assert_eq!(alert.rule, SPEED_THRESHOLD_RULE);
+        assert_eq!(alert.vin, "TEST_VIN_123");
+        assert_eq!(alert.position, Some((1.0, 2.0)));
+        assert!(alerts.try_recv().is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_oversize_message_dropped() {
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            max_message_bytes: Some(16),
+            ..Default::default()
+        });
+        
+        let result = processor.submit_message(br#"{"service": "tracking", "params": {}}"#).await;
+        assert!(result.is_ok());
+        
+        let stats = processor.get_stats();
+        assert_eq!(stats.messages_dropped, 1);
+        assert_eq!(processor.performance_monitor.get_size_histogram().count(), 1);
+    }
+    
+    #[test]
+    fn test_dedup_window_with_mock_clock() {
+        let clock = Arc::new(crate::clock::MockClock::new());
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            clock: clock.clone(),
+            ..Default::default()
+        });
+        
+        assert!(!processor.is_duplicate_message("tracking", 42));
+        clock.advance(Duration::from_millis(999));
+        assert!(processor.is_duplicate_message("tracking", 42));
+        
+        // 重复消息不刷新最后出现时间，窗口从首次出现算起
+        clock.advance(Duration::from_millis(1));
+        assert!(!processor.is_duplicate_message("tracking", 42));
+    }
+    
+    #[test]
+    fn test_clear_dedup_cache() {
+        let clock = Arc::new(crate::clock::MockClock::new());
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            clock: clock.clone(),
+            ..Default::default()
+        });
+        
+        assert!(!processor.is_duplicate_message("tracking", 42));
+        clock.advance(Duration::from_millis(200));
+        assert_eq!(processor.dedup_snapshot(), vec![(42, Duration::from_millis(200))]);
+        
+        // 清空后重发的消息不再视为重复
+        processor.clear_dedup_cache();
+        assert!(processor.dedup_snapshot().is_empty());
+        assert!(!processor.is_duplicate_message("tracking", 42));
+    }
+    
+    #[test]
+    fn test_custom_dedup_store() {
+        /// 所有消息都视为重复的存储
+        #[derive(Debug)]
+        struct AlwaysSeen;
+        
+        impl DedupStore for AlwaysSeen {
+            fn seen(&self, _hash: u64, _window: Duration) -> bool {
+                true
+            }
+        }
+        
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            dedup_store: Some(Arc::new(AlwaysSeen)),
+            ..Default::default()
+        });
+        assert!(processor.is_duplicate_message("tracking", 42));
+        assert!(!processor.is_duplicate_message("error_info", 42));
+    }
+    
+    #[tokio::test]
+    async fn test_ingest_sender_from_thread() {
+        let processor = MessageProcessor::new();
+        let processed_count = Arc::new(AtomicUsize::new(0));
+        let count_clone = processed_count.clone();
+        processor.set_callback(Arc::new(move |_| {
+            count_clone.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }));
+        
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        
+        let sender = processor.ingest_sender();
+        let pool = processor.buffer_pool();
+        std::thread::spawn(move || {
+            for i in 0..3 {
+                let frame = format!(
+                    r#"{{"service": "tracking", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                    i
+                );
+                sender.blocking_send(pool.acquire_copy(frame.as_bytes())).unwrap();
+            }
+            sender.blocking_send(b"not json".to_vec()).unwrap();
+        })
+        .join()
+        .unwrap();
+        
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(processed_count.load(Ordering::SeqCst), 3);
+        // 提交后的帧已归还缓冲区池
+        assert!(!processor.buffer_pool().is_empty());
+        
+        processor.stop();
+        let result = tokio::time::timeout(Duration::from_secs(1), handle).await;
+        assert!(result.is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_ingest_frames_recorded_before_in_place_parse() {
+        let path = std::env::temp_dir().join(format!("vehicle_processor_ingest_recording_{}.bin", std::process::id()));
+        let processor = MessageProcessor::new();
+        let processed_count = Arc::new(AtomicUsize::new(0));
+        let count_clone = processed_count.clone();
+        processor.set_callback(Arc::new(move |message| {
+            assert_eq!(message.vin, "VIN_INGEST");
+            count_clone.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }));
+        processor.enable_recording(&path).unwrap();
+        
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        
+        let frame = br#"{"service": "tracking", "params": {"vin": "VIN_INGEST", "timestamp": 1234567890.0, "data": {"speed": 1}}}"#;
+        processor.ingest_sender().send(frame.to_vec()).await.unwrap();
+        for _ in 0..100 {
+            if processed_count.load(Ordering::SeqCst) == 1 {
+                break;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(processed_count.load(Ordering::SeqCst), 1);
+        
+        // 原地解析不影响录制内容
+        assert_eq!(processor.disable_recording().unwrap(), 1);
+        assert_eq!(crate::recording::read_recording(&path).unwrap(), vec![frame.to_vec()]);
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
+        let _ = std::fs::remove_file(&path);
+    }
+    
+    #[tokio::test]
+    async fn test_correlation_id_in_event() {
+        let processor = MessageProcessor::new();
+        processor.set_callback(Arc::new(|message| {
+            assert_eq!(message.correlation_id.as_deref(), Some("req-1"));
+            Ok(())
+        }));
+        let mut events = processor.subscribe_events();
+        
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        let test_message = r#"{
+            "service": "tracking",
+            "params": {
+                "id": "req-1",
+                "vin": "TEST_VIN_123",
+                "timestamp": 1234567890.0,
+                "data": {"x": 1.0, "y": 2.0}
+            }
+        }"#;
+        processor.submit_message(test_message.as_bytes()).await.unwrap();
+        
+        let event = tokio::time::timeout(Duration::from_secs(1), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.correlation_id.as_deref(), Some("req-1"));
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
+    }
+    
+    #[tokio::test]
+    async fn test_source_in_message_and_event() {
+        let processor = MessageProcessor::new();
+        processor.set_callback(Arc::new(|message| {
+            assert_eq!(message.source.as_deref(), Some("gateway-1"));
+            Ok(())
+        }));
+        let mut events = processor.subscribe_events();
+        
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        let test_message = br#"{"service": "tracking", "params": {"vin": "TEST_VIN_123", "timestamp": 1234567890.0, "data": {}}}"#;
+        processor.submit_message_from(test_message, "gateway-1").await.unwrap();
+        
+        let event = tokio::time::timeout(Duration::from_secs(1), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.source.as_deref(), Some("gateway-1"));
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
+    }
+    
+    #[tokio::test]
+    async fn test_submit_message_detailed_outcomes() {
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            max_message_bytes: Some(512),
+            ..Default::default()
+        });
+        processor.set_callback(Arc::new(|_| Ok(())));
+        processor.update_sampling_config("traj", 1.0);
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        let message = |vin: &str| {
+            format!(
+                r#"{{"service": "traj", "params": {{"vin": "{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                vin
+            )
+        };
+        
+        let outcome = processor.submit_message_detailed(message("VIN_1").as_bytes()).await.unwrap();
+        assert_eq!(outcome, SubmitOutcome::Enqueued { priority: MessagePriority::Background });
+        
+        let outcome = processor.submit_message_detailed(message("VIN_1").as_bytes()).await.unwrap();
+        assert_eq!(outcome, SubmitOutcome::Deduplicated);
+        
+        processor.update_sampling_config("traj", 0.0);
+        let outcome = processor.submit_message_detailed(message("VIN_2").as_bytes()).await.unwrap();
+        assert_eq!(outcome, SubmitOutcome::Sampled);
+        
+        let oversize = vec![b' '; 1024];
+        let outcome = processor.submit_message_detailed(&oversize).await.unwrap();
+        assert_eq!(outcome, SubmitOutcome::Dropped { reason: "oversize" });
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
+    }
+    
+    #[tokio::test]
+    async fn test_empty_vin_defaults_to_unknown() {
+        let processor = MessageProcessor::new();
+        processor.set_callback(Arc::new(|message| {
+            assert_eq!(message.vin, "UNKNOWN");
+            Ok(())
+        }));
+        let mut events = processor.subscribe_events();
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        let test_message = r#"{
+            "service": "tracking",
+            "params": {"vin": "  ", "timestamp": 1234567890.0, "data": {}}
+        }"#;
+        let outcome = processor.submit_message_detailed(test_message.as_bytes()).await.unwrap();
+        assert_eq!(outcome, SubmitOutcome::Enqueued { priority: MessagePriority::Critical });
+        
+        let event = tokio::time::timeout(Duration::from_secs(1), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.vin, "UNKNOWN");
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
+    }
+    
+    #[test]
+    fn test_parse_timestamp_encodings() {
+        use serde_json::json;
+        assert_eq!(parse_timestamp(&json!(1700000000)), Some(1700000000.0));
+        assert_eq!(parse_timestamp(&json!(1700000000.25)), Some(1700000000.25));
+        assert_eq!(parse_timestamp(&json!("1700000000")), Some(1700000000.0));
+        assert_eq!(parse_timestamp(&json!(" 1700000000.5 ")), Some(1700000000.5));
+        assert_eq!(parse_timestamp(&json!("yesterday")), None);
+        assert_eq!(parse_timestamp(&json!("NaN")), None);
+        assert_eq!(parse_timestamp(&json!(true)), None);
+    }
+    
+    #[tokio::test]
+    async fn test_params_missing_or_not_object() {
+        let processor = MessageProcessor::new();
+        
+        let error = processor
+            .submit_message(br#"{"service": "tracking", "params": [1, 2]}"#)
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Invalid message format: params is not an object (got array)");
+        
+        let error = processor
+            .submit_message(br#"{"service": "tracking", "params": "VIN_1"}"#)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("(got string)"));
+        
+        let error = processor.submit_message(br#"{"service": "tracking"}"#).await.unwrap_err();
+        assert_eq!(error.to_string(), "Invalid message format: Missing params field");
+        assert_eq!(processor.get_stats().messages_dropped, 3);
+    }
+    
+    #[tokio::test]
+    async fn test_parse_and_validation_errors_counted_separately() {
+        let processor = MessageProcessor::new();
+        
+        let error = processor.submit_message(b"{not json").await.unwrap_err();
+        assert!(matches!(error, VehicleError::JsonError(_)));
+        let error = processor
+            .submit_message(br#"{"service": "tracking", "params": {"vin": "", "timestamp": 1234567890.0}}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, VehicleError::InvalidMessage(_)));
+        processor.submit_message(br#"{"params": {}}"#).await.unwrap_err();
+        
+        let stats = processor.get_stats();
+        assert_eq!(stats.parse_errors, 1);
+        assert_eq!(stats.validation_errors, 2);
+        assert_eq!(stats.messages_dropped, 3);
+    }
+    
+    #[tokio::test]
+    async fn test_bad_timestamp_rejected_in_strict_mode() {
+        let frame = br#"{"service": "tracking", "params": {"vin": "VIN_1", "timestamp": "yesterday", "data": {}}}"#;
+        
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            strict: true,
+            ..Default::default()
+        });
+        assert!(matches!(processor.submit_message(frame).await, Err(VehicleError::InvalidMessage(_))));
+        assert_eq!(processor.get_stats().messages_dropped, 1);
+        assert_eq!(processor.get_stats().messages_backfilled, 0);
+        
+        // 非严格模式下补全为到达时间
+        let processor = MessageProcessor::new();
+        processor.submit_message(frame).await.unwrap();
+        assert_eq!(processor.get_stats().messages_backfilled, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_missing_timestamp_backfilled() {
+        let clock = Arc::new(crate::clock::MockClock::starting_at(1700000000.0));
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            clock,
+            ..Default::default()
+        });
+        processor.set_callback(Arc::new(|message| {
+            assert_eq!(message.origin, TimestampOrigin::Backfilled);
+            assert_eq!(message.timestamp, 1700000000.0);
+            Ok(())
+        }));
+        let mut events = processor.subscribe_events();
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        let test_message = r#"{"service": "tracking", "params": {"vin": "TEST_VIN_123", "data": {}}}"#;
+        processor.submit_message(test_message.as_bytes()).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(1), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(processor.get_stats().messages_backfilled, 1);
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
+    }
+    
+    #[tokio::test]
+    async fn test_drop_stops_background_tasks() {
+        let processor = Arc::new(MessageProcessor::new());
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        assert!(processor.is_running());
+        
+        // 丢弃start future后，最后一个所有者被drop时停止处理任务
+        handle.abort();
+        let _ = handle.await;
+        let is_running = processor.is_running.clone();
+        drop(processor);
+        assert!(!*is_running.read());
+    }
+    
+    #[tokio::test]
+    async fn test_watermark_handler() {
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            high_watermark: 0.05,
+            low_watermark: 0.01,
+            ..Default::default()
+        });
+        processor.update_sampling_config("traj", 1.0);
+        let crossings = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let crossings_clone = crossings.clone();
+        processor.set_watermark_handler(Arc::new(move |priority, _depth, watermark| {
+            crossings_clone.lock().push((priority, watermark));
+        }));
+        
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        // 暂停后积压消息，背景队列容量100，第5条达到高水位
+        processor.pause();
+        for i in 0..8 {
+            let frame = format!(
+                r#"{{"service": "traj", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                i
+            );
+            processor.submit_message(frame.as_bytes()).await.unwrap();
+        }
+        assert_eq!(*crossings.lock(), vec![(MessagePriority::Background, Watermark::High)]);
+        
+        processor.resume();
+        sleep(Duration::from_millis(200)).await;
+        assert_eq!(
+            *crossings.lock(),
+            vec![
+                (MessagePriority::Background, Watermark::High),
+                (MessagePriority::Background, Watermark::Low),
+            ]
+        );
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
+    }
+    
+    #[tokio::test]
+    async fn test_dedup_disabled_per_service() {
+        let mut dedup = DedupConfig::default();
+        dedup.disabled_services.insert("heartbeat".to_string());
+        let processor = Arc::new(MessageProcessor::with_config(ProcessorConfig {
+            dedup,
+            ..Default::default()
+        }));
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        let frame = |service: &str| {
+            format!(
+                r#"{{"service": "{}", "params": {{"vin": "VIN_1", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                service
+            )
+        };
+        for _ in 0..2 {
+            let outcome = processor.submit_message_detailed(frame("heartbeat").as_bytes()).await.unwrap();
+            assert_eq!(outcome, SubmitOutcome::Enqueued { priority: MessagePriority::Normal });
+        }
+        processor.submit_message(frame("vcc").as_bytes()).await.unwrap();
+        let outcome = processor.submit_message_detailed(frame("vcc").as_bytes()).await.unwrap();
+        assert_eq!(outcome, SubmitOutcome::Deduplicated);
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
+    }
+    
+    #[tokio::test]
+    async fn test_sampling_disabled() {
+        let processor = Arc::new(MessageProcessor::new());
+        processor.update_sampling_config("traj", 0.0);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        let frame = |vin: &str| {
+            format!(
+                r#"{{"service": "traj", "params": {{"vin": "{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                vin
+            )
+        };
+        let outcome = processor.submit_message_detailed(frame("VIN_1").as_bytes()).await.unwrap();
+        assert_eq!(outcome, SubmitOutcome::Sampled);
+        
+        processor.set_sampling_enabled(false);
+        assert!(!processor.is_sampling_enabled());
+        let outcome = processor.submit_message_detailed(frame("VIN_2").as_bytes()).await.unwrap();
+        assert_eq!(outcome, SubmitOutcome::Enqueued { priority: MessagePriority::Background });
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
+    }
+    
+    #[tokio::test]
+    async fn test_sequence_gap_stats() {
+        let processor = MessageProcessor::new();
+        for seq in [1, 2, 6] {
+            let frame = format!(
+                r#"{{"service": "tracking", "params": {{"vin": "VIN_1", "seq": {}, "timestamp": 1234567890.0, "data": {{"seq": {}}}}}}}"#,
+                seq, seq
+            );
+            processor.submit_message(frame.as_bytes()).await.unwrap();
+        }
+        
+        let gaps = processor.get_gap_stats();
+        assert_eq!(gaps.gaps_detected, 1);
+        assert_eq!(gaps.frames_missing, 3);
+    }
+    
+    #[tokio::test]
+    async fn test_out_of_order_timestamp_stats() {
+        let processor = MessageProcessor::new();
+        for timestamp in ["100.0", "102.0", "99.0", "null"] {
+            let frame = format!(
+                r#"{{"service": "tracking", "params": {{"vin": "VIN_1", "timestamp": {}, "data": {{"t": {}}}}}}}"#,
+                timestamp, timestamp
+            );
+            processor.submit_message(frame.as_bytes()).await.unwrap();
+        }
+        
+        // 补全的时间戳不参与乱序检测
+        let order = processor.get_order_stats();
+        assert_eq!(order.out_of_order, 1);
+        assert_eq!(order.max_regression_secs, 3.0);
+    }
+    
+    #[tokio::test]
+    async fn test_active_vins_include_filtered_messages() {
+        let processor = MessageProcessor::new();
+        // traj默认被采样，采样丢弃的消息仍表明车辆在线
+        for vin in ["VIN_1", "VIN_2", "VIN_1", "  "] {
+            let frame = format!(
+                r#"{{"service": "traj", "params": {{"vin": "{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                vin
+            );
+            processor.submit_message(frame.as_bytes()).await.unwrap();
+        }
+        
+        assert_eq!(processor.vin_count(), 2);
+        let mut active = processor.active_vins(Duration::from_secs(60));
+        active.sort();
+        assert_eq!(active, vec!["VIN_1".to_string(), "VIN_2".to_string()]);
+    }
+    
+    #[tokio::test]
+    async fn test_lazy_data_shares_params() {
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            lazy_data: true,
+            ..Default::default()
+        });
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        processor.set_callback(Arc::new(move |message| {
+            let _ = tx.send(message);
+            Ok(())
+        }));
+        
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        let test_message = r#"{"service": "tracking", "params": {"vin": "TEST_VIN_123", "timestamp": 1234567890.0, "data": {"x": 1.0, "y": 2.0}}}"#;
+        processor.submit_message(test_message.as_bytes()).await.unwrap();
+        
+        let message = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!message.params.contains_key("data"));
+        assert_eq!(message.data().unwrap()["y"], 2.0);
+        assert_eq!(message.param("vin").unwrap(), "TEST_VIN_123");
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
+    }
+    
+    #[tokio::test]
+    async fn test_stage_timings_recorded() {
+        let processor = MessageProcessor::new();
+        processor.set_callback(Arc::new(|_| Ok(())));
+        let mut events = processor.subscribe_events();
+        
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        let test_message = r#"{"service": "tracking", "params": {"vin": "TEST_VIN_123", "timestamp": 1234567890.0, "data": {"x": 1.0}}}"#;
+        processor.submit_message(test_message.as_bytes()).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(1), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        
+        let timings = processor.get_stage_timings();
+        for stage in PipelineStage::all() {
+            assert_eq!(timings.get(stage).count, 1, "{:?}", stage);
+        }
+        assert_eq!(timings.report().lines().count(), 7);
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
+    }
+    
+    #[tokio::test]
+    async fn test_submit_batch_outcomes() {
+        let processor = MessageProcessor::new();
+        let runner = Arc::new(processor);
+        let processor = runner.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        let valid = br#"{"service": "tracking", "params": {"vin": "VIN_1", "timestamp": 1234567890.0, "data": {"x": 1.0}}}"#;
+        let other = br#"{"service": "tracking", "params": {"vin": "VIN_2", "timestamp": 1234567890.0, "data": {"x": 1.0}}}"#;
+        let frames: Vec<&[u8]> = vec![valid, b"not json", valid, other];
+        
+        let result = processor.submit_batch(&frames);
+        assert_eq!(result.len(), 4);
+        assert_eq!(result.enqueued, 2);
+        assert_eq!(result.deduplicated, 1);
+        assert_eq!(result.failed, 1);
+        assert!(result.outcomes[1].is_err());
+        assert_eq!(result.outcomes[2].as_ref().unwrap(), &SubmitOutcome::Deduplicated);
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
+    }
+    
+    #[tokio::test]
+    async fn test_scene_filter() {
+        let processor = MessageProcessor::new();
+        processor.set_scene_filter(Some(SceneFilter::allow(["production"])));
+        
+        let frame = |vin: &str, scene: &str| {
+            format!(
+                r#"{{"service": "tracking", "params": {{"vin": "{}", "run_scene": "{}", "timestamp": 1234567890.0, "data": {{"x": 1.0}}}}}}"#,
+                vin, scene
+            )
+        };
+        let outcome = processor.submit_message_detailed(frame("VIN_1", "test").as_bytes()).await.unwrap();
+        assert_eq!(outcome, SubmitOutcome::Filtered);
+        let outcome = processor.submit_message_detailed(frame("VIN_2", "production").as_bytes()).await.unwrap();
+        assert_ne!(outcome, SubmitOutcome::Filtered);
+    }
+    
+    #[tokio::test]
+    async fn test_effective_sampling_rates() {
+        let processor = MessageProcessor::new();
+        processor.update_sampling_config("traj", 0.0);
+        
+        for (i, service) in ["tracking", "traj", "tracking", "traj"].iter().enumerate() {
+            let frame = format!(
+                r#"{{"service": "{}", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{"x": 1.0}}}}}}"#,
+                service, i
+            );
+            processor.submit_message(frame.as_bytes()).await.unwrap();
+        }
+        
+        let rates = processor.get_effective_sampling_rates();
+        assert_eq!(rates["tracking"], EffectiveSamplingRate { configured: 1.0, effective: 1.0, seen: 2 });
+        assert_eq!(rates["traj"], EffectiveSamplingRate { configured: 0.0, effective: 0.0, seen: 2 });
+    }
+    
+    #[tokio::test]
+    async fn test_stop_after_ends_bounded_run() {
+        let processor = Arc::new(MessageProcessor::with_config(ProcessorConfig {
+            stop_after: Some(3),
+            ..Default::default()
+        }));
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        for i in 0..3 {
+            let frame = format!(
+                r#"{{"service": "tracking", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{"x": 1.0}}}}}}"#,
+                i
+            );
+            processor.submit_message(frame.as_bytes()).await.unwrap();
+        }
+        
+        tokio::time::timeout(Duration::from_secs(2), handle).await.unwrap().unwrap().unwrap();
+        assert!(!processor.is_running());
+        
+        let stats = processor.get_stats();
+        assert_eq!(stats.messages_dispatched, 3);
+        assert!(stats.is_completed());
+    }
+    
+    #[tokio::test]
+    async fn test_custom_field_paths() {
+        let field_paths = FieldPaths::default()
+            .with_vin("/params/vehicle/vin").unwrap()
+            .with_timestamp("/params/vehicle/ts").unwrap();
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            field_paths,
+            ..Default::default()
+        });
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        processor.set_callback(Arc::new(move |message| {
+            let _ = tx.send(message);
+            Ok(())
+        }));
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        let frame = br#"{"service": "tracking", "params": {"vehicle": {"vin": "VIN_NESTED", "ts": 1234567890.0}, "data": {"x": 1.0}}}"#;
+        processor.submit_message(frame).await.unwrap();
+        
+        let message = tokio::time::timeout(Duration::from_secs(1), rx.recv()).await.unwrap().unwrap();
+        assert_eq!(message.vin, "VIN_NESTED");
+        assert_eq!(message.timestamp, 1234567890.0);
+        assert_eq!(message.origin, TimestampOrigin::Provided);
+        processor.stop();
+    }
+    
+    #[tokio::test]
+    async fn test_processed_counts_per_priority() {
+        let processor = Arc::new(MessageProcessor::with_config(ProcessorConfig {
+            stop_after: Some(3),
+            ..Default::default()
+        }));
+        processor.set_sampling_enabled(false);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        for service in ["tracking", "error_info", "traj"] {
+            let frame = format!(
+                r#"{{"service": "{}", "params": {{"vin": "VIN_1", "timestamp": 1234567890.0, "data": {{"x": 1.0}}}}}}"#,
+                service
+            );
+            processor.submit_message(frame.as_bytes()).await.unwrap();
+        }
+        tokio::time::timeout(Duration::from_secs(2), handle).await.unwrap().unwrap().unwrap();
+        
+        let stats = processor.get_stats();
+        assert_eq!(stats.processed_for(MessagePriority::Critical), 2);
+        assert_eq!(stats.processed_for(MessagePriority::Normal), 0);
+        assert_eq!(stats.processed_for(MessagePriority::Background), 1);
+        assert_eq!(stats.processed_critical + stats.processed_normal + stats.processed_background, stats.messages_processed);
+    }
+    
+    #[tokio::test]
+    async fn test_high_and_low_tiers_via_override() {
+        let processor = Arc::new(MessageProcessor::with_config(ProcessorConfig {
+            stop_after: Some(3),
+            ..Default::default()
+        }));
+        processor.set_sampling_enabled(false);
+        processor.set_service_priority("vcc", Some(MessagePriority::High));
+        processor.set_service_priority("device", Some(MessagePriority::Low));
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        for service in ["vcc", "device", "uos_config"] {
+            let frame = format!(
+                r#"{{"service": "{}", "params": {{"vin": "VIN_1", "timestamp": 1234567890.0, "data": {{"x": 1.0}}}}}}"#,
+                service
+            );
+            processor.submit_message(frame.as_bytes()).await.unwrap();
+        }
+        tokio::time::timeout(Duration::from_secs(2), handle).await.unwrap().unwrap().unwrap();
+        
+        let stats = processor.get_stats();
+        assert_eq!(stats.processed_for(MessagePriority::High), 1);
+        assert_eq!(stats.processed_for(MessagePriority::Normal), 1);
+        assert_eq!(stats.processed_for(MessagePriority::Low), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_unused_lanes_start_on_demand() {
+        let processor = Arc::new(MessageProcessor::with_config(ProcessorConfig {
+            stop_after: Some(1),
+            ..Default::default()
+        }));
+        processor.set_sampling_enabled(false);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        // 默认映射不会产生High/Low，两条队列不启动任务
+        let mut idle: Vec<MessagePriority> = processor.idle_lanes.lock().keys().copied().collect();
+        idle.sort_by_key(|priority| *priority as usize);
+        assert_eq!(idle, [MessagePriority::High, MessagePriority::Low]);
+        
+        processor.set_service_priority("vcc", Some(MessagePriority::High));
+        assert!(!processor.idle_lanes.lock().contains_key(&MessagePriority::High));
+        let frame = br#"{"service": "vcc", "params": {"vin": "VIN_1", "timestamp": 1234567890.0, "data": {"x": 1.0}}}"#;
+        processor.submit_message(frame).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(2), handle).await.unwrap().unwrap().unwrap();
+        assert_eq!(processor.get_stats().processed_for(MessagePriority::High), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_weighted_fair_scheduling_order() {
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            lane_weights: Some(LaneWeights { critical: 2, normal: 1, background: 1, ..Default::default() }),
+            stop_after: Some(6),
+            ..Default::default()
+        });
+        let order = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let seen = order.clone();
+        processor.set_callback(Arc::new(move |message| {
+            seen.lock().push(MessagePriority::from_service(&message.service));
+            Ok(())
+        }));
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        // 暂停期间入队，恢复后按权重轮流消费
+        processor.pause();
+        for (i, service) in ["vcc", "vcc", "tracking", "tracking", "tracking", "tracking"].iter().enumerate() {
+            let frame = format!(
+                r#"{{"service": "{}", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{"x": 1.0}}}}}}"#,
+                service, i
+            );
+            processor.submit_message(frame.as_bytes()).await.unwrap();
+        }
+        processor.resume();
+        tokio::time::timeout(Duration::from_secs(2), handle).await.unwrap().unwrap().unwrap();
+        
+        use MessagePriority::{Critical, Normal};
+        assert_eq!(*order.lock(), [Critical, Critical, Normal, Critical, Critical, Normal]);
+    }
+    
+    #[tokio::test]
+    async fn test_strict_mode_reports_queue_full() {
+        // 未启动时队列没有消费者，入队失败
+        let frame = br#"{"service": "tracking", "params": {"vin": "VIN_1", "timestamp": 1234567890.0, "data": {"x": 1.0}}}"#;
+        let lenient = MessageProcessor::new();
+        assert_eq!(
+            lenient.submit_message_detailed(frame).await.unwrap(),
+            SubmitOutcome::Dropped { reason: "queue full" }
+        );
+        
+        let strict = MessageProcessor::with_config(ProcessorConfig {
+            strict: true,
+            ..Default::default()
+        });
+        assert!(matches!(strict.submit_message(frame).await, Err(VehicleError::QueueFull)));
+        // 重复消息仍然返回Ok
+        assert_eq!(strict.submit_message_detailed(frame).await.unwrap(), SubmitOutcome::Deduplicated);
+        assert!(strict.submit_message(br#"{"service": "tracking", "params": {}}"#).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_critical_overflow_spills_and_recovers() {
+        let path = std::env::temp_dir().join(format!("vehicle_processor_spill_{}.bin", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            spill: Some(SpillConfig::new(&path)),
+            ..Default::default()
+        });
+        let processed = Arc::new(AtomicUsize::new(0));
+        let counter = processed.clone();
+        processor.set_callback(Arc::new(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }));
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        // 暂停时队列只进不出，超出容量的关键消息写入溢写文件
+        processor.pause();
+        let capacity = MessagePriority::Critical.queue_capacity();
+        let mut spilled = 0;
+        for i in 0..capacity + 5 {
+            let frame = format!(
+                r#"{{"service": "tracking", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                i
+            );
+            if processor.submit_message_detailed(frame.as_bytes()).await.unwrap() == SubmitOutcome::Spilled {
+                spilled += 1;
+            }
+        }
+        assert_eq!(spilled, 5);
+        // 溢写线程异步写盘
+        for _ in 0..50 {
+            if processor.spill_stats().unwrap().pending_messages == 5 {
+                break;
             }
-            Err(e) => return Err(e),
+            sleep(Duration::from_millis(10)).await;
         }
+        assert_eq!(processor.spill_stats().unwrap().pending_messages, 5);
         
-        Ok(())
+        processor.resume();
+        for _ in 0..100 {
+            if processed.load(Ordering::SeqCst) == capacity + 5 {
+                break;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(processed.load(Ordering::SeqCst), capacity + 5);
+        let stats = processor.spill_stats().unwrap();
+        assert_eq!((stats.spilled, stats.recovered, stats.pending_messages), (5, 5, 0));
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
+        let _ = std::fs::remove_file(&path);
     }
     
-    /// 检查是否为重复消息
-    fn is_duplicate_message(&self, message_hash: u64) -> bool {
-        let now = Instant::now();
-        
-        if let Some(last_seen) = self.message_cache.get(&message_hash) {
-            // 如果在1秒内见过相同消息，认为是重复
-            if now.duration_since(*last_seen) < Duration::from_secs(1) {
-                return true;
-            }
+    #[tokio::test]
+    async fn test_gzip_recording_replays_frames() {
+        let path = std::env::temp_dir().join(format!("vehicle_processor_recording_{}.bin", std::process::id()));
+        let processor = MessageProcessor::new();
+        processor.enable_recording_compressed(&path, Compression::Gzip).unwrap();
+        for i in 0..3 {
+            let frame = format!(
+                r#"{{"service": "tracking", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                i
+            );
+            processor.submit_message(frame.as_bytes()).await.unwrap();
         }
+        // 无效帧同样被录制，回放时复现同样的错误
+        processor.submit_message(b"{not json").await.unwrap_err();
+        assert_eq!(processor.disable_recording().unwrap(), 4);
         
-        // 更新缓存
-        self.message_cache.insert(message_hash, now);
-        false
+        let replayed = MessageProcessor::new().replay_recording(&path).unwrap();
+        assert_eq!(replayed.len(), 4);
+        assert_eq!(replayed.failed, 1);
+        let _ = std::fs::remove_file(&path);
     }
     
-    /// 检查是否应该处理该消息
-    fn should_process_message(&self, service: &str) -> bool {
-        let config = self.sampling_config.read();
-        config.should_process(service)
+    #[tokio::test]
+    async fn test_drop_finishes_gzip_recording() {
+        let path = std::env::temp_dir().join(format!("vehicle_processor_dropped_recording_{}.bin", std::process::id()));
+        let processor = MessageProcessor::new();
+        processor.enable_recording_compressed(&path, Compression::Gzip).unwrap();
+        for i in 0..3 {
+            let frame = format!(
+                r#"{{"service": "tracking", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                i
+            );
+            processor.submit_message(frame.as_bytes()).await.unwrap();
+        }
+        drop(processor);
+        
+        // gzip尾部已写出，完整解压不报错
+        let mut decoder = flate2::read::GzDecoder::new(std::fs::File::open(&path).unwrap());
+        std::io::copy(&mut decoder, &mut std::io::sink()).unwrap();
+        assert_eq!(MessageProcessor::new().replay_recording(&path).unwrap().len(), 3);
+        let _ = std::fs::remove_file(&path);
     }
     
-    /// 生成处理任务
-    fn spawn_processor_task(
-        mut receiver: mpsc::Receiver<VehicleMessage>,
-        priority: MessagePriority,
-        callback: Option<MessageCallback>,
-        monitor: Arc<PerformanceMonitor>,
-        is_running: Arc<parking_lot::RwLock<bool>>,
-    ) -> tokio::task::JoinHandle<()> {
-        tokio::spawn(async move {
-            let interval = priority.processing_interval();
-            info!("Started {:?} priority processor", priority);
-            
-            while *is_running.read() {
-                match receiver.try_recv() {
-                    Ok(message) => {
-                        let start_time = Instant::now();
-                        
-                        // 调用回调函数处理消息
-                        if let Some(ref callback) = callback {
-                            match callback(message.clone()) {
-                                Ok(_) => {
-                                    let processing_time = start_time.elapsed();
-                                    monitor.record_processed(processing_time);
-                                    
-                                    debug!(
-                                        "Processed {:?} message: service={}, time={:.2}μs",
-                                        priority,
-                                        message.service,
-                                        processing_time.as_micros()
-                                    );
-                                }
-                                Err(e) => {
-                                    error!(
-                                        "Failed to process {:?} message: service={}, error={}",
-                                        priority, message.service, e
-                                    );
-                                    monitor.record_dropped("processing error");
-                                }
-                            }
-                        } else {
-                            // 没有回调函数，只记录统计
-                            let processing_time = start_time.elapsed();
-                            monitor.record_processed(processing_time);
-                        }
-                    }
-                    Err(mpsc::error::TryRecvError::Empty) => {
-                        // 没有消息，休眠一段时间
-                        sleep(interval).await;
-                    }
-                    Err(mpsc::error::TryRecvError::Disconnected) => {
-                        warn!("{:?} priority processor: channel disconnected", priority);
-                        break;
-                    }
-                }
-            }
-            
-            info!("{:?} priority processor stopped", priority);
-        })
+    #[test]
+    fn test_config_snapshot_reflects_runtime_changes() {
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            strict: true,
+            callback_timeouts: HashMap::from([(MessagePriority::Background, Duration::from_millis(50))]),
+            ..Default::default()
+        });
+        processor.update_sampling_config("traj", 0.25);
+        processor.update_sampling_strategy("moving_obj", SamplingStrategy::EveryNth(4)).unwrap();
+        processor.add_priority_vin("VIN_VIP");
+        processor.set_service_priority("vcc", Some(MessagePriority::High));
+        processor.set_geo_filter(Some(GeoFilter::new(0.0, 10.0, 0.0, 10.0)));
+        
+        let snapshot = processor.config_snapshot();
+        assert_eq!(snapshot.state, ProcessorState::Stopped);
+        assert!(snapshot.strict);
+        assert!(!snapshot.recording);
+        assert_eq!(snapshot.sampling_strategies["moving_obj"], SamplingStrategy::EveryNth(4));
+        assert_eq!(snapshot.priority_overrides["vcc"], MessagePriority::High);
+        assert_eq!(snapshot.queue_capacities.len(), MessagePriority::COUNT);
+        assert_eq!(snapshot.callback_timeouts, [(MessagePriority::Background, Duration::from_millis(50))]);
+        
+        let json = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(json["sampling_rates"]["traj"], 0.25);
+        assert_eq!(json["priority_vins"], serde_json::json!(["VIN_VIP"]));
+        assert_eq!(json["geo_filter"]["max_x"], 10.0);
+        assert_eq!(json["dedup_window"]["secs"], 1);
     }
     
-    /// 生成缓存清理任务
-    fn spawn_cache_cleanup_task(
-        cache: Arc<DashMap<u64, Instant>>,
-        is_running: Arc<parking_lot::RwLock<bool>>,
-    ) -> tokio::task::JoinHandle<()> {
-        tokio::spawn(async move {
-            info!("Started cache cleanup task");
-            
-            while *is_running.read() {
-                let now = Instant::now();
-                let mut removed_count = 0;
-                
-                // 清理超过5分钟的缓存条目
-                cache.retain(|_, &mut last_seen| {
-                    let should_keep = now.duration_since(last_seen) < Duration::from_secs(300);
-                    if !should_keep {
-                        removed_count += 1;
-                    }
-                    should_keep
-                });
-                
-                if removed_count > 0 {
-                    debug!("Cleaned {} expired cache entries", removed_count);
-                }
-                
-                // 每分钟清理一次
-                sleep(Duration::from_secs(60)).await;
+    #[tokio::test]
+    async fn test_recent_messages_ring_buffer() {
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            recent_messages: Some(3),
+            ..Default::default()
+        });
+        processor.set_callback(Arc::new(|_| Ok(())));
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        for i in 0..5 {
+            let frame = format!(
+                r#"{{"service": "tracking", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                i
+            );
+            processor.submit_message(frame.as_bytes()).await.unwrap();
+        }
+        for _ in 0..50 {
+            if processor.get_stats().messages_processed == 5 {
+                break;
             }
-            
-            info!("Cache cleanup task stopped");
-        })
+            sleep(Duration::from_millis(10)).await;
+        }
+        
+        let vins: Vec<String> = processor.recent_messages().into_iter().map(|m| m.vin).collect();
+        assert_eq!(vins, ["VIN_2", "VIN_3", "VIN_4"]);
+        assert!(MessageProcessor::new().recent_messages().is_empty());
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
     }
     
-    /// 获取性能统计
-    pub fn get_stats(&self) -> ProcessingStats {
-        self.performance_monitor.get_stats()
+    #[tokio::test]
+    async fn test_inline_service_processed_in_submit() {
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            inline_services: HashSet::from(["tracking".to_string()]),
+            ..Default::default()
+        });
+        let processed = Arc::new(AtomicUsize::new(0));
+        let counter = processed.clone();
+        processor.set_callback(Arc::new(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }));
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        let frame = br#"{"service": "tracking", "params": {"vin": "VIN_1", "timestamp": 1234567890.0, "data": {}}}"#;
+        assert_eq!(
+            processor.submit_message_detailed(frame).await.unwrap(),
+            SubmitOutcome::ProcessedInline { priority: MessagePriority::Critical }
+        );
+        // 返回时回调已执行完毕
+        assert_eq!(processed.load(Ordering::SeqCst), 1);
+        let stats = processor.get_stats();
+        assert_eq!((stats.messages_received, stats.messages_processed), (1, 1));
+        
+        let frame = br#"{"service": "vcc", "params": {"vin": "VIN_1", "timestamp": 1234567890.0, "data": {}}}"#;
+        assert!(matches!(
+            processor.submit_message_detailed(frame).await.unwrap(),
+            SubmitOutcome::Enqueued { .. }
+        ));
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
     }
     
-    /// 更新采样配置
-    pub fn update_sampling_config(&self, service: &str, rate: f32) {
-        let mut config = self.sampling_config.write();
-        config.set_rate(service, rate);
-        info!("Updated sampling rate for {}: {:.2}", service, rate);
+    #[tokio::test]
+    async fn test_inline_callback_can_update_config() {
+        let processor = Arc::new(MessageProcessor::with_config(ProcessorConfig {
+            inline_services: HashSet::from(["tracking".to_string()]),
+            ..Default::default()
+        }));
+        // 回调执行时提交路径的读锁已释放，回调内修改配置不会死锁
+        let weak = Arc::downgrade(&processor);
+        processor.set_callback(Arc::new(move |_| {
+            let processor = weak.upgrade().unwrap();
+            processor.update_sampling_config("traj", 0.5);
+            processor.set_geo_filter(None);
+            Ok(())
+        }));
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        let frame = br#"{"service": "tracking", "params": {"vin": "VIN_1", "timestamp": 1234567890.0, "data": {}}}"#;
+        assert!(matches!(
+            processor.submit_message_detailed(frame).await.unwrap(),
+            SubmitOutcome::ProcessedInline { .. }
+        ));
+        assert_eq!(processor.get_sampling_config().get_rate("traj"), 0.5);
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
     }
     
-    /// 获取当前采样配置
-    pub fn get_sampling_config(&self) -> SamplingConfig {
-        self.sampling_config.read().clone()
+    #[tokio::test]
+    async fn test_callback_timeout_drops_and_moves_on() {
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            callback_timeouts: HashMap::from([(MessagePriority::Critical, Duration::from_millis(20))]),
+            ..Default::default()
+        });
+        let fast = Arc::new(AtomicUsize::new(0));
+        let counter = fast.clone();
+        processor.set_callback(Arc::new(move |message| {
+            if message.vin == "VIN_SLOW" {
+                std::thread::sleep(Duration::from_millis(600));
+            } else {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+            Ok(())
+        }));
+        // 未设置并发上限时按默认值限制，卡死的回调不会无限占用阻塞线程
+        assert_eq!(processor.config_snapshot().max_concurrent_callbacks, Some(TIMED_CALLBACK_CONCURRENCY));
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        for vin in ["VIN_SLOW", "VIN_FAST"] {
+            let frame = format!(
+                r#"{{"service": "tracking", "params": {{"vin": "{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                vin
+            );
+            processor.submit_message(frame.as_bytes()).await.unwrap();
+        }
+        // 回调返回后才计入处理数，等待统计而不是回调内的计数
+        for _ in 0..40 {
+            if processor.get_stats().messages_processed == 1 {
+                break;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        
+        // 慢回调仍在运行，但队列已继续处理后续消息
+        assert_eq!(fast.load(Ordering::SeqCst), 1);
+        let stats = processor.get_stats();
+        assert_eq!(stats.messages_dropped, 1);
+        assert_eq!(stats.messages_processed, 1);
+        // 超时的回调返回前仍计为在途
+        assert_eq!(processor.callbacks_in_flight(), 1);
+        for _ in 0..100 {
+            if processor.callbacks_in_flight() == 0 {
+                break;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(processor.callbacks_in_flight(), 0);
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
     }
     
-    /// 检查处理器是否正在运行
-    pub fn is_running(&self) -> bool {
-        *self.is_running.read()
+    #[tokio::test]
+    async fn test_timed_out_callback_keeps_concurrency_permit() {
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            callback_timeouts: HashMap::from([(MessagePriority::Critical, Duration::from_millis(20))]),
+            max_concurrent_callbacks: Some(1),
+            ..Default::default()
+        });
+        let fast = Arc::new(AtomicUsize::new(0));
+        let counter = fast.clone();
+        processor.set_callback(Arc::new(move |message| {
+            if message.vin == "VIN_SLOW" {
+                std::thread::sleep(Duration::from_millis(200));
+            } else {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+            Ok(())
+        }));
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        for vin in ["VIN_SLOW", "VIN_FAST"] {
+            let frame = format!(
+                r#"{{"service": "tracking", "params": {{"vin": "{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                vin
+            );
+            processor.submit_message(frame.as_bytes()).await.unwrap();
+        }
+        
+        // 超时后慢回调仍占用唯一的并发许可，后续消息等待其返回
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(processor.get_stats().messages_dropped, 1);
+        assert_eq!(fast.load(Ordering::SeqCst), 0);
+        for _ in 0..50 {
+            if fast.load(Ordering::SeqCst) == 1 {
+                break;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(fast.load(Ordering::SeqCst), 1);
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
     }
-}
-
-impl Default for MessageProcessor {
-    fn default() -> Self {
-        Self::new()
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_max_concurrent_callbacks() {
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            max_concurrent_callbacks: Some(1),
+            ..Default::default()
+        });
+        processor.set_sampling_enabled(false);
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let done = Arc::new(AtomicUsize::new(0));
+        {
+            let (active, peak, done) = (active.clone(), peak.clone(), done.clone());
+            processor.set_callback(Arc::new(move |_| {
+                let running = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(running, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(5));
+                active.fetch_sub(1, Ordering::SeqCst);
+                done.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }));
+        }
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        // 三条队列同时有积压
+        processor.pause();
+        for i in 0..4 {
+            for service in ["tracking", "vcc", "traj"] {
+                let frame = format!(
+                    r#"{{"service": "{}", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                    service, i
+                );
+                processor.submit_message(frame.as_bytes()).await.unwrap();
+            }
+        }
+        processor.resume();
+        for _ in 0..100 {
+            if done.load(Ordering::SeqCst) == 12 {
+                break;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+        
+        assert_eq!(done.load(Ordering::SeqCst), 12);
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+        assert_eq!(processor.get_stats().callbacks_in_flight, 0);
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::sync::atomic::{AtomicUsize, Ordering};
-
     
     #[tokio::test]
-    async fn test_message_processor_creation() {
-        let processor = MessageProcessor::new();
-        assert!(!processor.is_running());
+    async fn test_dry_run_skips_callbacks() {
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            dry_run: true,
+            stop_after: Some(2),
+            ..Default::default()
+        });
+        processor.set_callback(Arc::new(|_| panic!("callback invoked in dry-run mode")));
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        
+        for vin in ["VIN_1", "VIN_2", "VIN_1"] {
+            let frame = format!(
+                r#"{{"service": "tracking", "params": {{"vin": "{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                vin
+            );
+            processor.submit_message(frame.as_bytes()).await.unwrap();
+        }
+        tokio::time::timeout(Duration::from_secs(2), handle).await.unwrap().unwrap().unwrap();
         
+        // 重复消息照常去重，出队的消息计为试运行处理
         let stats = processor.get_stats();
-        assert_eq!(stats.messages_received, 0);
-        assert_eq!(stats.messages_processed, 0);
+        assert_eq!(stats.messages_dry_run, 2);
+        assert_eq!(stats.messages_processed, 2);
+        assert_eq!(stats.processed_critical, 2);
     }
     
     #[tokio::test]
-    async fn test_message_submission() {
-        let processor = MessageProcessor::new();
+    async fn test_buffer_messages_until_callback_registered() {
+        let processor = Arc::new(MessageProcessor::with_config(ProcessorConfig {
+            no_callback_policy: NoCallbackPolicy::BufferUpTo(2),
+            stop_after: Some(3),
+            ..Default::default()
+        }));
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
         
-        let test_message = r#"{
-            "service": "tracking",
-            "params": {
-                "vin": "TEST_VIN_123",
-                "timestamp": 1234567890.0,
-                "data": {"x": 1.0, "y": 2.0}
-            }
-        }"#;
+        for vin in ["VIN_1", "VIN_2", "VIN_3"] {
+            let frame = format!(
+                r#"{{"service": "tracking", "params": {{"vin": "{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                vin
+            );
+            processor.submit_message(frame.as_bytes()).await.unwrap();
+        }
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(processor.pending_callback_messages(), 2);
+        assert_eq!(processor.get_stats().messages_dropped, 1);
+        // 缓存的消息在回放时才计入stop_after
+        assert_eq!(processor.get_stats().messages_dispatched, 1);
+        assert!(!handle.is_finished());
         
-        let result = processor.submit_message(test_message.as_bytes()).await;
-        // 由于没有接收端，消息会被丢弃，但这不应该导致错误
-        // 我们只测试消息解析和验证是否正确
-        assert!(result.is_ok());
+        // 注册回调时按出队顺序回放缓存的消息
+        let received = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let sink = received.clone();
+        processor.set_callback(Arc::new(move |message: VehicleMessage| {
+            sink.lock().push(message.vin);
+            Ok(())
+        }));
+        assert_eq!(*received.lock(), vec!["VIN_1".to_string(), "VIN_2".to_string()]);
+        assert_eq!(processor.pending_callback_messages(), 0);
+        assert_eq!(processor.get_stats().processed_critical, 2);
+        assert_eq!(processor.get_stats().messages_dispatched, 3);
+        assert!(tokio::time::timeout(Duration::from_secs(1), handle).await.is_ok());
     }
     
     #[tokio::test]
-    async fn test_message_callback() {
-        let mut processor = MessageProcessor::new();
-        let processed_count = Arc::new(AtomicUsize::new(0));
-        let count_clone = processed_count.clone();
-        
-        // 设置回调函数
-        processor.set_callback(Arc::new(move |message| {
-            count_clone.fetch_add(1, Ordering::SeqCst);
-            assert_eq!(message.service, "tracking");
-            Ok(())
+    async fn test_no_callback_error_policy() {
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            no_callback_policy: NoCallbackPolicy::Error,
+            ..Default::default()
+        });
+        let message = VehicleMessage::new("tracking".to_string(), "VIN_1".to_string(), 1234567890.0);
+        let processed = processor.dispatch_context().dispatch(
+            MessagePriority::Critical,
+            QueuedMessage { id: 1, message, enqueued_at: Instant::now() },
+            None,
+        ).await;
+        assert!(!processed);
+        assert_eq!(processor.get_stats().messages_dropped, 1);
+        assert_eq!(processor.pending_callback_messages(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_integer_service_codes() {
+        let processor = Arc::new(MessageProcessor::with_config(ProcessorConfig {
+            service_codes: ServiceCodes::new().with_code(1, "tracking").unwrap().with_code(7, "traj").unwrap(),
+            ..Default::default()
         }));
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        processor.set_sampling_enabled(false);
         
-        // 启动处理器
-        let processor_handle = {
-            let processor_clone = Arc::new(processor);
-            let processor_ref = processor_clone.clone();
-            tokio::spawn(async move {
-                processor_ref.start().await
-            })
-        };
-        
-        // 等待处理器启动
-        sleep(Duration::from_millis(100)).await;
-        
-        // 提交测试消息
-        // let test_message = r#"{
-        //     "service": "tracking",
-        //     "params": {
-        //         "vin": "TEST_VIN_123",
-        //         "timestamp": 1234567890.0,
-        //         "data": {"x": 1.0, "y": 2.0}
-        //     }
-        // }"#;
+        let frame = br#"{"service": 1, "params": {"vin": "VIN_1", "timestamp": 1234567890.0, "data": {}}}"#;
+        assert_eq!(
+            processor.submit_message_detailed(frame).await.unwrap(),
+            SubmitOutcome::Enqueued { priority: MessagePriority::Critical }
+        );
+        let frame = br#"{"service_code": 7, "params": {"vin": "VIN_1", "timestamp": 1234567890.0, "data": {}}}"#;
+        assert_eq!(
+            processor.submit_message_detailed(frame).await.unwrap(),
+            SubmitOutcome::Enqueued { priority: MessagePriority::Background }
+        );
         
-        // 注意：这里需要重新获取processor的引用来提交消息
-        // 由于processor已经被移动到Arc中，我们需要重新设计这个测试
+        let frame = br#"{"service": 99, "params": {"vin": "VIN_1", "timestamp": 1234567890.0, "data": {}}}"#;
+        let error = processor.submit_message(frame).await.unwrap_err();
+        assert_eq!(error.to_string(), "Invalid message format: Unknown service code: 99");
         
-        // 停止处理器
-        sleep(Duration::from_millis(100)).await;
-        processor_handle.abort();
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
     }
     
     #[tokio::test]
-    async fn test_sampling_config() {
-        let processor = MessageProcessor::new();
+    async fn test_control_commands() {
+        let processor = Arc::new(MessageProcessor::new());
+        let control = processor.control_sender();
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
         
-        // 测试默认配置
-        let config = processor.get_sampling_config();
-        assert_eq!(config.get_rate("tracking"), 1.0);
-        assert_eq!(config.get_rate("traj"), 0.1);
+        control.send(ControlCommand::Pause).await.unwrap();
+        control.send(ControlCommand::SetSampling { service: "traj".to_string(), rate: 0.5 }).await.unwrap();
+        control
+            .send(ControlCommand::SetPriority { service: "vcc".to_string(), priority: Some(MessagePriority::Critical) })
+            .await
+            .unwrap();
+        sleep(Duration::from_millis(20)).await;
         
-        // 更新配置
-        processor.update_sampling_config("test_service", 0.5);
-        let updated_config = processor.get_sampling_config();
-        assert_eq!(updated_config.get_rate("test_service"), 0.5);
+        assert!(processor.is_paused());
+        assert_eq!(processor.get_sampling_config().get_rate("traj"), 0.5);
+        assert_eq!(processor.priority_for("vcc"), MessagePriority::Critical);
+        
+        let frame = br#"{"service": "vcc", "params": {"vin": "VIN_1", "timestamp": 1234567890.0, "data": {"x": 1.0}}}"#;
+        assert_eq!(
+            processor.submit_message_detailed(frame).await.unwrap(),
+            SubmitOutcome::Enqueued { priority: MessagePriority::Critical }
+        );
+        
+        control.send(ControlCommand::SetPriority { service: "vcc".to_string(), priority: None }).await.unwrap();
+        control.send(ControlCommand::Resume).await.unwrap();
+        control.send(ControlCommand::ResetStats).await.unwrap();
+        sleep(Duration::from_millis(20)).await;
+        assert!(!processor.is_paused());
+        assert_eq!(processor.priority_for("vcc"), MessagePriority::Normal);
+        assert_eq!(processor.get_stats().messages_received, 0);
+        
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
     }
     
     #[tokio::test]
-    async fn test_duplicate_message_detection() {
-        let processor = MessageProcessor::new();
+    async fn test_fan_out_to_multiple_callbacks() {
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            stop_after: Some(1),
+            ..Default::default()
+        });
+        let calls = Arc::new(AtomicUsize::new(0));
+        for fail in [false, true, false] {
+            let calls = calls.clone();
+            processor.add_callback(Arc::new(move |_| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                if fail {
+                    Err(VehicleError::InvalidMessage("forwarder down".to_string()))
+                } else {
+                    Ok(())
+                }
+            }));
+        }
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
         
-        let test_message = r#"{
-            "service": "tracking",
-            "params": {
-                "vin": "TEST_VIN_123",
-                "timestamp": 1234567890.0,
-                "data": {"x": 1.0, "y": 2.0}
-            }
-        }"#;
+        let frame = br#"{"service": "tracking", "params": {"vin": "VIN_1", "timestamp": 1234567890.0, "data": {"x": 1.0}}}"#;
+        processor.submit_message(frame).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(2), handle).await.unwrap().unwrap().unwrap();
         
-        // 第一次提交应该成功
-        let result1 = processor.submit_message(test_message.as_bytes()).await;
-        assert!(result1.is_ok());
+        // 中间回调失败不影响其他回调，但消息计为处理失败
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        let stats = processor.get_stats();
+        assert_eq!(stats.messages_processed, 0);
+        assert_eq!(stats.messages_dropped, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_swap_callback_while_running() {
+        let processor = Arc::new(MessageProcessor::new());
+        processor.set_sampling_enabled(false);
+        let (first, second) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let counter = first.clone();
+        processor.set_callback(Arc::new(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }));
+        let runner = processor.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
         
-        // 等待统计更新
-        tokio::time::sleep(Duration::from_millis(10)).await;
-        let stats1 = processor.get_stats();
+        let frame = |i: u32| {
+            format!(r#"{{"service": "tracking", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#, i)
+        };
+        processor.submit_message(frame(1).as_bytes()).await.unwrap();
+        sleep(Duration::from_millis(50)).await;
         
-        // 立即重复提交应该被去重
-        let result2 = processor.submit_message(test_message.as_bytes()).await;
-        assert!(result2.is_ok()); // 不会报错，但会被去重
+        // 通过共享引用替换回调，之后的消息交给新回调
+        let counter = second.clone();
+        processor.set_callback(Arc::new(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }));
+        assert_eq!(processor.callbacks().len(), 1);
+        processor.submit_message(frame(2).as_bytes()).await.unwrap();
+        sleep(Duration::from_millis(50)).await;
         
-        // 等待统计更新
-        tokio::time::sleep(Duration::from_millis(10)).await;
-        let stats2 = processor.get_stats();
+        assert_eq!(first.load(Ordering::SeqCst), 1);
+        assert_eq!(second.load(Ordering::SeqCst), 1);
         
-        // 第二次提交应该被去重，所以接收计数不应该增加
-        assert_eq!(stats1.messages_received, stats2.messages_received);
+        processor.clear_callbacks();
+        assert!(processor.callbacks().is_empty());
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
     }
-}
\ No newline at end of file
+}
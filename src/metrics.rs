@@ -0,0 +1,45 @@
+use std::fmt::Debug;
+
+/// 接收的消息数（计数器）
+pub const MESSAGES_RECEIVED: &str = "messages_received";
+/// 处理成功的消息数（计数器）
+pub const MESSAGES_PROCESSED: &str = "messages_processed";
+/// 丢弃的消息数（计数器）
+pub const MESSAGES_DROPPED: &str = "messages_dropped";
+/// JSON解析失败的消息数（计数器）
+pub const PARSE_ERRORS: &str = "parse_errors";
+/// 字段缺失或校验失败的消息数（计数器）
+pub const VALIDATION_ERRORS: &str = "validation_errors";
+/// 补全时间戳的消息数（计数器）
+pub const MESSAGES_BACKFILLED: &str = "messages_backfilled";
+/// 超出延迟预算的消息数（计数器）
+pub const SLOW_MESSAGES: &str = "slow_messages";
+/// 当前队列大小（仪表）
+pub const QUEUE_SIZE: &str = "queue_size";
+/// 已接收但尚未处理完成或丢弃的消息数（仪表）
+pub const IN_FLIGHT: &str = "in_flight";
+/// 单条消息处理耗时，微秒（直方图）
+pub const PROCESSING_TIME_US: &str = "processing_time_us";
+/// 消息大小，字节（直方图）
+pub const MESSAGE_SIZE_BYTES: &str = "message_size_bytes";
+
+/// 指标输出端，由使用方对接Prometheus、statsd等具体后端
+///
+/// 在统计记录路径上同步调用，实现应尽量轻量（如只更新原子计数或写入无锁队列）；
+/// 各方法默认不做任何事，只需实现关心的指标类型
+pub trait MetricsSink: Send + Sync + Debug {
+    /// 计数器增加`value`
+    fn counter(&self, _name: &str, _value: u64) {}
+
+    /// 仪表设置为`value`
+    fn gauge(&self, _name: &str, _value: f64) {}
+
+    /// 直方图记录一个样本
+    fn histogram(&self, _name: &str, _value: f64) {}
+}
+
+/// 不输出任何指标的默认实现
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopMetricsSink;
+
+impl MetricsSink for NoopMetricsSink {}
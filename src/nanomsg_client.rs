@@ -1,9 +1,19 @@
-use crate::error::{Result, VehicleError};
+use crate::error::{NanomsgErrorKind, Result, VehicleError};
+use crate::framing::{FramingMode, LengthPrefixedDecoder};
 use crate::message_processor::MessageProcessor;
+use crate::persistence::{self, StatsSnapshot};
+use crate::runtime;
+use crate::transport::{is_inproc_url, InprocTransport, Transport};
+use crate::types::SubmitOutcome;
 
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::sync::Notify;
 use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
 use parking_lot::RwLock;
 use tracing::{info, warn, error};
 
@@ -24,6 +34,20 @@ pub struct NanomsgConfig {
     pub batch_size: usize,
     /// 批量接收超时
     pub batch_timeout: Duration,
+    /// 连接最长存活时间，超过后主动关闭并重连（None表示不限制）
+    pub connection_max_lifetime: Option<Duration>,
+    /// 连接状态下超过该时长未收到消息则视为连接已失效并重连（None表示不检测）
+    pub idle_reconnect_timeout: Option<Duration>,
+    /// 无消息时的初始休眠时间，连续空接收时逐次翻倍
+    pub idle_backoff_min: Duration,
+    /// 无消息时的最长休眠时间
+    pub idle_backoff_max: Duration,
+    /// 分帧方式：按nanomsg消息边界，或按4字节大端长度前缀从字节流中切分
+    pub framing: FramingMode,
+    /// 按批次填充率和处理器积压自动调整`batch_size`，None时使用固定值
+    pub batch_auto_tune: Option<BatchAutoTune>,
+    /// 来源标识，设置后写入本客户端接收的每条消息的`source`字段（None表示不标记）
+    pub source_id: Option<String>,
 }
 
 impl Default for NanomsgConfig {
@@ -36,10 +60,203 @@ impl Default for NanomsgConfig {
             buffer_size: 8192,
             batch_size: 100,
             batch_timeout: Duration::from_millis(10),
+            connection_max_lifetime: None,
+            idle_reconnect_timeout: None,
+            idle_backoff_min: Duration::from_micros(100),
+            idle_backoff_max: Duration::from_millis(10),
+            framing: FramingMode::MessageBoundary,
+            batch_auto_tune: None,
+            source_id: None,
         }
     }
 }
 
+impl NanomsgConfig {
+    /// 从默认配置开始构建
+    pub fn builder() -> NanomsgConfigBuilder {
+        NanomsgConfigBuilder {
+            config: NanomsgConfig::default(),
+        }
+    }
+    
+    /// 校验配置
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: &str| Err(VehicleError::ConfigError(format!("Invalid nanomsg config: {}", reason)));
+        
+        if self.listen_url.is_empty() {
+            return invalid("listen_url must not be empty");
+        }
+        if self.buffer_size == 0 {
+            return invalid("buffer_size must be greater than 0");
+        }
+        if self.batch_size == 0 {
+            return invalid("batch_size must be greater than 0");
+        }
+        if self.max_reconnect_attempts == 0 {
+            return invalid("max_reconnect_attempts must be greater than 0");
+        }
+        if self.receive_timeout.is_zero() || self.batch_timeout.is_zero() {
+            return invalid("receive_timeout and batch_timeout must be non-zero");
+        }
+        if self.idle_backoff_min.is_zero() || self.idle_backoff_min > self.idle_backoff_max {
+            return invalid("idle_backoff_min must be non-zero and not exceed idle_backoff_max");
+        }
+        if self.connection_max_lifetime.is_some_and(|d| d.is_zero())
+            || self.idle_reconnect_timeout.is_some_and(|d| d.is_zero())
+        {
+            return invalid("connection_max_lifetime and idle_reconnect_timeout must be non-zero when set");
+        }
+        if let Some(ref tune) = self.batch_auto_tune {
+            if tune.min_batch_size == 0 || tune.min_batch_size > tune.max_batch_size {
+                return invalid("batch auto-tune bounds must satisfy 0 < min_batch_size <= max_batch_size");
+            }
+            if tune.adjust_every == 0 {
+                return invalid("batch auto-tune adjust_every must be greater than 0");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// batch_size自动调整配置
+///
+/// 批次持续填满且处理器跟得上时增大batch_size；批次耗时超过目标或处理器积压时减小
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchAutoTune {
+    /// batch_size下限
+    pub min_batch_size: usize,
+    /// batch_size上限
+    pub max_batch_size: usize,
+    /// 批次接收耗时目标，平均耗时超过该值时减小batch_size
+    pub target_batch_latency: Duration,
+    /// 平均填充率不低于该值时视为批次持续填满
+    pub grow_fill_ratio: f64,
+    /// 处理器队列填充率超过该值时视为积压
+    pub max_backlog_ratio: f32,
+    /// 每隔多少个非空批次调整一次
+    pub adjust_every: u32,
+}
+
+impl Default for BatchAutoTune {
+    fn default() -> Self {
+        Self {
+            min_batch_size: 10,
+            max_batch_size: 1000,
+            target_batch_latency: Duration::from_millis(5),
+            grow_fill_ratio: 0.9,
+            max_backlog_ratio: 0.5,
+            adjust_every: 20,
+        }
+    }
+}
+
+impl BatchAutoTune {
+    /// 根据平均填充率、平均批次耗时和处理器积压计算下一个batch_size
+    pub fn next_batch_size(&self, current: usize, fill_ratio: f64, batch_latency: Duration, backlog_ratio: f32) -> usize {
+        let next = if batch_latency > self.target_batch_latency || backlog_ratio > self.max_backlog_ratio {
+            current - current / 4
+        } else if fill_ratio >= self.grow_fill_ratio {
+            current + current / 4 + 1
+        } else {
+            current
+        };
+        next.clamp(self.min_batch_size, self.max_batch_size)
+    }
+}
+
+/// Nanomsg客户端配置构建器
+#[derive(Debug, Clone)]
+pub struct NanomsgConfigBuilder {
+    config: NanomsgConfig,
+}
+
+impl NanomsgConfigBuilder {
+    /// 监听URL
+    pub fn listen_url(mut self, url: &str) -> Self {
+        self.config.listen_url = url.to_string();
+        self
+    }
+    
+    /// 接收超时时间
+    pub fn receive_timeout(mut self, timeout: Duration) -> Self {
+        self.config.receive_timeout = timeout;
+        self
+    }
+    
+    /// 重连间隔
+    pub fn reconnect_interval(mut self, interval: Duration) -> Self {
+        self.config.reconnect_interval = interval;
+        self
+    }
+    
+    /// 最大重连次数
+    pub fn max_reconnect_attempts(mut self, attempts: u32) -> Self {
+        self.config.max_reconnect_attempts = attempts;
+        self
+    }
+    
+    /// 接收缓冲区大小
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.config.buffer_size = size;
+        self
+    }
+    
+    /// 批量接收大小
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.config.batch_size = size;
+        self
+    }
+    
+    /// 批量接收超时
+    pub fn batch_timeout(mut self, timeout: Duration) -> Self {
+        self.config.batch_timeout = timeout;
+        self
+    }
+    
+    /// 连接最长存活时间
+    pub fn connection_max_lifetime(mut self, lifetime: Duration) -> Self {
+        self.config.connection_max_lifetime = Some(lifetime);
+        self
+    }
+    
+    /// 空闲重连超时
+    pub fn idle_reconnect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.idle_reconnect_timeout = Some(timeout);
+        self
+    }
+    
+    /// 空接收休眠的初始值和上限
+    pub fn idle_backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.config.idle_backoff_min = min;
+        self.config.idle_backoff_max = max;
+        self
+    }
+    
+    /// 分帧方式
+    pub fn framing(mut self, framing: FramingMode) -> Self {
+        self.config.framing = framing;
+        self
+    }
+    
+    /// 启用batch_size自动调整
+    pub fn batch_auto_tune(mut self, tune: BatchAutoTune) -> Self {
+        self.config.batch_auto_tune = Some(tune);
+        self
+    }
+    
+    /// 来源标识
+    pub fn source_id(mut self, id: &str) -> Self {
+        self.config.source_id = Some(id.to_string());
+        self
+    }
+    
+    /// 校验并完成构建
+    pub fn build(self) -> Result<NanomsgConfig> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
 /// Nanomsg连接状态
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConnectionState {
@@ -49,11 +266,24 @@ pub enum ConnectionState {
     Error,
 }
 
+/// 传输层工厂函数，客户端每次建立连接时调用
+///
+/// 监听URL为`inproc://`时始终使用进程内传输，不调用工厂
+pub type SocketFactory = Arc<dyn Fn() -> Box<dyn Transport> + Send + Sync>;
+
+/// 脚本模式下按顺序返回的帧
+struct ScriptedFrames {
+    frames: Vec<Vec<u8>>,
+    position: usize,
+    looping: bool,
+}
+
 /// 模拟的Nanomsg Socket（实际实现需要真正的nanomsg绑定）
 pub struct MockNanomsgSocket {
     url: String,
     is_connected: bool,
     message_count: u64,
+    script: Option<ScriptedFrames>,
 }
 
 impl MockNanomsgSocket {
@@ -62,7 +292,30 @@ impl MockNanomsgSocket {
             url: String::new(),
             is_connected: false,
             message_count: 0,
+            script: None,
+        }
+    }
+    
+    /// 创建按顺序返回指定帧的确定性socket，帧耗尽后返回流结束错误
+    ///
+    /// 空脚本表示一直没有消息的对端，始终返回`WouldBlock`
+    pub fn scripted(frames: Vec<Vec<u8>>) -> Self {
+        Self {
+            script: Some(ScriptedFrames {
+                frames,
+                position: 0,
+                looping: false,
+            }),
+            ..Self::new()
+        }
+    }
+    
+    /// 设置脚本帧耗尽后是否从头循环
+    pub fn with_loop(mut self, looping: bool) -> Self {
+        if let Some(ref mut script) = self.script {
+            script.looping = looping;
         }
+        self
     }
     
     pub fn bind(&mut self, url: &str) -> Result<()> {
@@ -74,15 +327,19 @@ impl MockNanomsgSocket {
     
     pub fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
         if !self.is_connected {
-            return Err(VehicleError::NanomsgError("Socket not connected".to_string()));
+            return Err(VehicleError::nanomsg(NanomsgErrorKind::NotConnected, "Socket not connected"));
+        }
+        
+        if let Some(ref mut script) = self.script {
+            return Self::recv_scripted(script, buffer);
         }
         
         // 模拟接收消息
         self.message_count += 1;
         
         // 每10个消息中有1个是空的（模拟无消息情况）
-        if self.message_count % 10 == 0 {
-            return Err(VehicleError::NanomsgError("No message available".to_string()));
+        if self.message_count.is_multiple_of(10) {
+            return Err(VehicleError::WouldBlock);
         }
         
         // 生成模拟消息
@@ -95,7 +352,7 @@ impl MockNanomsgSocket {
                     "data": {{"x": {}, "y": {}, "speed": {}}}
                 }}
             }}"#,
-            if self.message_count % 5 == 0 { "tracking" } else { "traj" },
+            if self.message_count.is_multiple_of(5) { "tracking" } else { "traj" },
             self.message_count % 3,
             chrono::Utc::now().timestamp(),
             self.message_count as f64 * 0.1,
@@ -110,48 +367,164 @@ impl MockNanomsgSocket {
         Ok(copy_len)
     }
     
+    /// 从脚本中取出下一帧
+    fn recv_scripted(script: &mut ScriptedFrames, buffer: &mut [u8]) -> Result<usize> {
+        if script.frames.is_empty() {
+            return Err(VehicleError::WouldBlock);
+        }
+        if script.position >= script.frames.len() {
+            if !script.looping {
+                return Err(VehicleError::nanomsg(NanomsgErrorKind::EndOfStream, "End of stream"));
+            }
+            script.position = 0;
+        }
+        
+        let frame = &script.frames[script.position];
+        script.position += 1;
+        
+        let copy_len = std::cmp::min(frame.len(), buffer.len());
+        buffer[..copy_len].copy_from_slice(&frame[..copy_len]);
+        Ok(copy_len)
+    }
+    
     pub fn close(&mut self) {
         self.is_connected = false;
         info!("Mock nanomsg socket closed");
     }
 }
 
+impl Transport for MockNanomsgSocket {
+    fn bind(&mut self, url: &str) -> Result<()> {
+        MockNanomsgSocket::bind(self, url)
+    }
+    
+    fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        MockNanomsgSocket::recv(self, buffer)
+    }
+    
+    fn close(&mut self) {
+        MockNanomsgSocket::close(self)
+    }
+}
+
+impl Default for MockNanomsgSocket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// 高性能Nanomsg客户端
 pub struct NanomsgClient {
-    config: NanomsgConfig,
-    socket: Arc<RwLock<Option<MockNanomsgSocket>>>,
+    config: Arc<RwLock<NanomsgConfig>>,
+    socket: Arc<RwLock<Option<Box<dyn Transport>>>>,
     message_processor: Arc<MessageProcessor>,
+    socket_factory: SocketFactory,
     connection_state: Arc<RwLock<ConnectionState>>,
     is_running: Arc<RwLock<bool>>,
     stats: Arc<RwLock<NanomsgStats>>,
+    runtime: Option<tokio::runtime::Handle>,
+    reconnect_notify: Arc<Notify>,
+    shutdown: Arc<RwLock<CancellationToken>>,
+    // 对端已正常关闭（流结束），不再自动重连
+    stream_ended: Arc<RwLock<bool>>,
+    // 接收任务是否仍在运行（stop后可能仍在提交当前批次）
+    receiving: Arc<AtomicBool>,
 }
 
 /// Nanomsg客户端统计信息
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct NanomsgStats {
     pub bytes_received: u64,
     pub messages_received: u64,
     pub connection_attempts: u32,
     pub reconnections: u32,
+    /// 因达到最长存活时间而主动重连的次数
+    pub planned_reconnections: u32,
+    /// 因空闲超时而判定连接失效的次数
+    pub idle_disconnects: u32,
+    #[serde(skip)]
     pub last_message_time: Option<Instant>,
     pub avg_batch_size: f64,
+    /// 非空批次的平均接收耗时（微秒，移动平均）
+    pub avg_batch_duration_us: f64,
+    /// 非空批次的平均填充率（消息数 / batch_size，移动平均）
+    pub avg_batch_fill_ratio: f64,
+    /// 因达到batch_size而结束的批次数
+    pub batches_size_limited: u64,
+    /// 因达到batch_timeout而结束的批次数（socket中仍可能有消息）
+    pub batches_timeout_limited: u64,
+    /// 解析或校验失败、超大而被拒绝的帧数（已向传输确认，不再重放）
+    #[serde(default)]
+    pub messages_rejected: u64,
+}
+
+/// 提交结果对应的传输确认方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameDisposition {
+    /// 已接收或有意丢弃，确认
+    Ack,
+    /// 帧本身无效，确认并计入拒绝
+    Reject,
+    /// 暂时无法接收（队列满等），不确认，留待重放
+    Retry,
+}
+
+impl FrameDisposition {
+    fn of(result: &Result<SubmitOutcome>) -> Self {
+        match result {
+            Ok(SubmitOutcome::Dropped { reason: "queue full" }) => FrameDisposition::Retry,
+            Ok(SubmitOutcome::Dropped { .. }) => FrameDisposition::Reject,
+            Ok(_) => FrameDisposition::Ack,
+            Err(VehicleError::JsonError(_) | VehicleError::InvalidMessage(_)) => FrameDisposition::Reject,
+            Err(_) => FrameDisposition::Retry,
+        }
+    }
 }
 
 impl NanomsgClient {
     /// 创建新的Nanomsg客户端
     pub fn new(config: NanomsgConfig, message_processor: Arc<MessageProcessor>) -> Self {
         Self {
-            config,
+            config: Arc::new(RwLock::new(config)),
             socket: Arc::new(RwLock::new(None)),
             message_processor,
+            socket_factory: Arc::new(|| Box::new(MockNanomsgSocket::new())),
             connection_state: Arc::new(RwLock::new(ConnectionState::Disconnected)),
             is_running: Arc::new(RwLock::new(false)),
             stats: Arc::new(RwLock::new(NanomsgStats::default())),
+            runtime: None,
+            reconnect_notify: Arc::new(Notify::new()),
+            shutdown: Arc::new(RwLock::new(CancellationToken::new())),
+            stream_ended: Arc::new(RwLock::new(false)),
+            receiving: Arc::new(AtomicBool::new(false)),
         }
     }
     
-    /// 启动客户端
+    /// 创建在指定运行时上运行后台任务的客户端，用于与应用其他部分隔离
+    pub fn with_runtime(
+        config: NanomsgConfig,
+        message_processor: Arc<MessageProcessor>,
+        runtime: tokio::runtime::Handle,
+    ) -> Self {
+        let mut client = Self::new(config, message_processor);
+        client.runtime = Some(runtime);
+        client
+    }
+    
+    /// 设置建立连接时使用的socket工厂，如返回脚本模式的模拟socket
+    pub fn set_socket_factory(&mut self, factory: SocketFactory) {
+        self.socket_factory = factory;
+    }
+    
+    /// 启动客户端，直到调用`stop`
     pub async fn start(&self) -> Result<()> {
+        self.start_with_token(CancellationToken::new()).await
+    }
+    
+    /// 启动客户端，直到调用`stop`或`token`被取消
+    ///
+    /// `stop`只取消内部派生的子token，不影响传入的token
+    pub async fn start_with_token(&self, token: CancellationToken) -> Result<()> {
         {
             let mut running = self.is_running.write();
             if *running {
@@ -159,17 +532,21 @@ impl NanomsgClient {
             }
             *running = true;
         }
+        *self.stream_ended.write() = false;
         
-        info!("Starting Nanomsg client on: {}", self.config.listen_url);
+        info!("Starting Nanomsg client on: {}", self.config.read().listen_url);
+        
+        let shutdown = token.child_token();
+        *self.shutdown.write() = shutdown.clone();
         
         // 启动连接管理任务
-        let connection_task = self.spawn_connection_manager();
+        let connection_task = self.spawn_connection_manager(shutdown.clone());
         
         // 启动消息接收任务
         let receiver_task = self.spawn_message_receiver();
         
         // 启动统计报告任务
-        let stats_task = self.spawn_stats_reporter();
+        let stats_task = self.spawn_stats_reporter(shutdown.clone());
         
         // 等待任务完成
         tokio::select! {
@@ -182,6 +559,14 @@ impl NanomsgClient {
             result = stats_task => {
                 error!("Stats reporter task ended: {:?}", result);
             }
+            _ = shutdown.cancelled() => {
+                info!("Nanomsg client shutdown requested");
+            }
+        }
+        
+        // 外部取消时各任务可能先于上面的分支结束，统一在此关闭连接
+        if shutdown.is_cancelled() && self.is_running() {
+            self.stop();
         }
         
         Ok(())
@@ -195,6 +580,7 @@ impl NanomsgClient {
             let mut running = self.is_running.write();
             *running = false;
         }
+        self.shutdown.read().cancel();
         
         // 关闭socket
         if let Some(mut socket) = self.socket.write().take() {
@@ -207,21 +593,75 @@ impl NanomsgClient {
         }
     }
     
+    /// 在线更新配置，不中断连接
+    ///
+    /// 批量大小、缓冲区、超时与退避等设置在接收循环的下一轮生效；
+    /// 仅在监听URL变化时关闭当前连接并重连。配置无效时返回错误且不做任何修改
+    pub fn reconfigure(&self, new: NanomsgConfig) -> Result<()> {
+        new.validate()?;
+        
+        let url_changed = {
+            let mut config = self.config.write();
+            let url_changed = config.listen_url != new.listen_url;
+            *config = new;
+            url_changed
+        };
+        info!("Applied nanomsg client config update");
+        
+        if url_changed && self.is_running() {
+            info!("Listen URL changed, reconnecting to: {}", self.config.read().listen_url);
+            // 先切换状态，避免接收任务把取走的socket当作连接错误
+            *self.connection_state.write() = ConnectionState::Disconnected;
+            *self.stream_ended.write() = false;
+            if let Some(mut old_socket) = self.socket.write().take() {
+                old_socket.close();
+            }
+            self.stats.write().planned_reconnections += 1;
+            self.reconnect_notify.notify_one();
+        }
+        
+        Ok(())
+    }
+    
+    /// 获取当前配置
+    pub fn config(&self) -> NanomsgConfig {
+        self.config.read().clone()
+    }
+    
+    /// 对端是否已结束数据流（如有限回放源读完或对端正常关闭）
+    ///
+    /// 流结束后客户端保持`Disconnected`且不再重连，直到切换监听URL或重新启动
+    pub fn is_stream_ended(&self) -> bool {
+        *self.stream_ended.read()
+    }
+    
     /// 生成连接管理任务
-    fn spawn_connection_manager(&self) -> tokio::task::JoinHandle<Result<()>> {
-        let config = self.config.clone();
+    fn spawn_connection_manager(&self, shutdown: CancellationToken) -> tokio::task::JoinHandle<Result<()>> {
+        let shared_config = self.config.clone();
+        let reconnect_notify = self.reconnect_notify.clone();
         let socket = self.socket.clone();
+        let socket_factory = self.socket_factory.clone();
         let connection_state = self.connection_state.clone();
         let is_running = self.is_running.clone();
         let stats = self.stats.clone();
+        let stream_ended = self.stream_ended.clone();
         
-        tokio::spawn(async move {
+        runtime::spawn(self.runtime.as_ref(), async move {
             info!("Started connection manager");
+            let mut connected_at: Option<Instant> = None;
             
             while *is_running.read() {
+                let config = shared_config.read().clone();
                 let current_state = *connection_state.read();
                 
                 match current_state {
+                    ConnectionState::Disconnected if *stream_ended.read() => {
+                        // 流已结束，等待切换URL或关闭
+                        tokio::select! {
+                            _ = reconnect_notify.notified() => {}
+                            _ = shutdown.cancelled() => break,
+                        }
+                    }
                     ConnectionState::Disconnected => {
                         // 尝试连接
                         {
@@ -229,8 +669,9 @@ impl NanomsgClient {
                             *state = ConnectionState::Connecting;
                         }
                         
-                        match Self::establish_connection(&config, &socket, &stats).await {
+                        match Self::establish_connection(&config, &socket, &socket_factory, &stats).await {
                             Ok(_) => {
+                                connected_at = Some(Instant::now());
                                 let mut state = connection_state.write();
                                 *state = ConnectionState::Connected;
                                 info!("Successfully connected to: {}", config.listen_url);
@@ -244,14 +685,63 @@ impl NanomsgClient {
                     }
                     ConnectionState::Error => {
                         // 等待重连间隔
-                        sleep(config.reconnect_interval).await;
+                        tokio::select! {
+                            _ = sleep(config.reconnect_interval) => {}
+                            _ = shutdown.cancelled() => break,
+                        }
                         
                         let mut state = connection_state.write();
                         *state = ConnectionState::Disconnected;
                     }
                     _ => {
                         // 连接正常，检查连接状态
-                        sleep(Duration::from_secs(5)).await;
+                        let mut check_interval = Duration::from_secs(5);
+                        
+                        if let (Some(max_lifetime), Some(since)) = (config.connection_max_lifetime, connected_at) {
+                            let age = since.elapsed();
+                            if age >= max_lifetime {
+                                info!(
+                                    "Recycling connection after {:.1}s (max lifetime reached)",
+                                    age.as_secs_f64()
+                                );
+                                // 先切换状态，避免接收任务把取走的socket当作连接错误
+                                *connection_state.write() = ConnectionState::Disconnected;
+                                if let Some(mut old_socket) = socket.write().take() {
+                                    old_socket.close();
+                                }
+                                stats.write().planned_reconnections += 1;
+                                connected_at = None;
+                                continue;
+                            }
+                            check_interval = check_interval.min(max_lifetime - age);
+                        }
+                        
+                        if let (Some(idle_timeout), Some(since)) = (config.idle_reconnect_timeout, connected_at) {
+                            // 以最近一条消息或建立连接的时间中较晚者作为最后活动时间
+                            let last_activity = stats
+                                .read()
+                                .last_message_time
+                                .map_or(since, |last| last.max(since));
+                            let idle = last_activity.elapsed();
+                            if idle >= idle_timeout {
+                                warn!(
+                                    "No messages for {:.1}s while connected, forcing reconnect",
+                                    idle.as_secs_f64()
+                                );
+                                stats.write().idle_disconnects += 1;
+                                connected_at = None;
+                                *connection_state.write() = ConnectionState::Error;
+                                continue;
+                            }
+                            check_interval = check_interval.min(idle_timeout - idle);
+                        }
+                        
+                        // 配置更新需要重连时提前唤醒
+                        tokio::select! {
+                            _ = sleep(check_interval) => {}
+                            _ = reconnect_notify.notified() => {}
+                            _ = shutdown.cancelled() => break,
+                        }
                     }
                 }
             }
@@ -264,7 +754,8 @@ impl NanomsgClient {
     /// 建立连接
     async fn establish_connection(
         config: &NanomsgConfig,
-        socket: &Arc<RwLock<Option<MockNanomsgSocket>>>,
+        socket: &Arc<RwLock<Option<Box<dyn Transport>>>>,
+        socket_factory: &SocketFactory,
         stats: &Arc<RwLock<NanomsgStats>>,
     ) -> Result<()> {
         let mut attempts = 0;
@@ -277,7 +768,7 @@ impl NanomsgClient {
                 stats_guard.connection_attempts += 1;
             }
             
-            match Self::try_connect(config).await {
+            match Self::try_connect(config, socket_factory).await {
                 Ok(new_socket) => {
                     let mut socket_guard = socket.write();
                     *socket_guard = Some(new_socket);
@@ -298,15 +789,25 @@ impl NanomsgClient {
             }
         }
         
-        Err(VehicleError::NanomsgError(
-            format!("Failed to connect after {} attempts", config.max_reconnect_attempts)
+        Err(VehicleError::nanomsg(
+            NanomsgErrorKind::ConnectFailed,
+            format!("Failed to connect after {} attempts", config.max_reconnect_attempts),
         ))
     }
     
     /// 尝试连接
-    async fn try_connect(config: &NanomsgConfig) -> Result<MockNanomsgSocket> {
-        let mut socket = MockNanomsgSocket::new();
-        socket.bind(&config.listen_url)?;
+    ///
+    /// 传输的`bind`可能阻塞到连接建立（如NATS、Redis），在阻塞线程池上执行，不占用运行时工作线程
+    async fn try_connect(config: &NanomsgConfig, socket_factory: &SocketFactory) -> Result<Box<dyn Transport>> {
+        let mut socket: Box<dyn Transport> = if is_inproc_url(&config.listen_url) {
+            Box::new(InprocTransport::new())
+        } else {
+            socket_factory()
+        };
+        let url = config.listen_url.clone();
+        let socket = tokio::task::spawn_blocking(move || socket.bind(&url).map(|()| socket))
+            .await
+            .map_err(|e| VehicleError::nanomsg(NanomsgErrorKind::BindFailed, format!("Bind task failed: {}", e)))??;
         
         // 模拟连接延迟
         sleep(Duration::from_millis(10)).await;
@@ -316,21 +817,36 @@ impl NanomsgClient {
     
     /// 生成消息接收任务
     fn spawn_message_receiver(&self) -> tokio::task::JoinHandle<Result<()>> {
-        let config = self.config.clone();
+        let shared_config = self.config.clone();
         let socket = self.socket.clone();
         let message_processor = self.message_processor.clone();
         let connection_state = self.connection_state.clone();
         let is_running = self.is_running.clone();
         let stats = self.stats.clone();
+        let stream_ended = self.stream_ended.clone();
+        let receiving = self.receiving.clone();
+        receiving.store(true, Ordering::SeqCst);
         
-        tokio::spawn(async move {
+        runtime::spawn(self.runtime.as_ref(), async move {
             info!("Started message receiver");
+            let mut config = shared_config.read().clone();
             let mut buffer = vec![0u8; config.buffer_size];
+            let mut idle_backoff = config.idle_backoff_min;
+            let mut decoder = LengthPrefixedDecoder::default();
+            let mut batches_since_tune = 0u32;
             
             while *is_running.read() {
+                // 应用在线更新的配置
+                config = shared_config.read().clone();
+                if buffer.len() != config.buffer_size {
+                    buffer.resize(config.buffer_size, 0);
+                }
+                
                 let current_state = *connection_state.read();
                 
                 if current_state != ConnectionState::Connected {
+                    // 断开连接时丢弃残留的半帧
+                    decoder.clear();
                     sleep(Duration::from_millis(100)).await;
                     continue;
                 }
@@ -342,15 +858,39 @@ impl NanomsgClient {
                     &message_processor,
                     &stats,
                     &mut buffer,
+                    &mut decoder,
                 ).await {
                     Ok(count) => {
                         if count == 0 {
-                            // 没有消息，短暂休眠
-                            sleep(Duration::from_micros(100)).await;
+                            // 没有消息，休眠时间随连续空接收递增
+                            sleep(idle_backoff).await;
+                            idle_backoff = Self::next_idle_backoff(&config, idle_backoff);
+                        } else {
+                            idle_backoff = config.idle_backoff_min;
+                            
+                            if let Some(ref tune) = config.batch_auto_tune {
+                                batches_since_tune += 1;
+                                if batches_since_tune >= tune.adjust_every {
+                                    batches_since_tune = 0;
+                                    Self::auto_tune_batch_size(tune, &shared_config, &stats, &message_processor);
+                                }
+                            }
+                        }
+                    }
+                    Err(VehicleError::NanomsgError { kind: NanomsgErrorKind::EndOfStream, .. }) => {
+                        info!("Stream ended by peer, stop receiving");
+                        idle_backoff = config.idle_backoff_min;
+                        
+                        // 正常结束不视为错误，置为断开且不重连
+                        *stream_ended.write() = true;
+                        *connection_state.write() = ConnectionState::Disconnected;
+                        if let Some(mut old_socket) = socket.write().take() {
+                            old_socket.close();
                         }
                     }
                     Err(e) => {
                         error!("Message receiving error: {}", e);
+                        idle_backoff = config.idle_backoff_min;
                         
                         // 连接可能断开，更新状态
                         {
@@ -363,21 +903,58 @@ impl NanomsgClient {
                 }
             }
             
+            receiving.store(false, Ordering::SeqCst);
             info!("Message receiver stopped");
             Ok(())
         })
     }
     
+    /// 按近期批次统计调整共享配置中的batch_size
+    fn auto_tune_batch_size(
+        tune: &BatchAutoTune,
+        shared_config: &RwLock<NanomsgConfig>,
+        stats: &RwLock<NanomsgStats>,
+        message_processor: &MessageProcessor,
+    ) {
+        let (fill_ratio, batch_latency) = {
+            let stats = stats.read();
+            (stats.avg_batch_fill_ratio, Duration::from_secs_f64(stats.avg_batch_duration_us / 1_000_000.0))
+        };
+        let backlog_ratio = message_processor.backlog_ratio();
+        
+        let mut config = shared_config.write();
+        let current = config.batch_size;
+        let next = tune.next_batch_size(current, fill_ratio, batch_latency, backlog_ratio);
+        if next != current {
+            info!(
+                "Auto-tuned batch_size {} -> {} (fill {:.0}%, latency {:?}, backlog {:.0}%)",
+                current,
+                next,
+                fill_ratio * 100.0,
+                batch_latency,
+                backlog_ratio * 100.0
+            );
+            config.batch_size = next;
+        }
+    }
+    
+    /// 计算下一次空接收的休眠时间：翻倍直至上限
+    fn next_idle_backoff(config: &NanomsgConfig, current: Duration) -> Duration {
+        current.saturating_mul(2).min(config.idle_backoff_max).max(config.idle_backoff_min)
+    }
+    
     /// 批量接收消息
     async fn receive_message_batch(
         config: &NanomsgConfig,
-        socket: &Arc<RwLock<Option<MockNanomsgSocket>>>,
+        socket: &Arc<RwLock<Option<Box<dyn Transport>>>>,
         message_processor: &Arc<MessageProcessor>,
         stats: &Arc<RwLock<NanomsgStats>>,
         buffer: &mut [u8],
+        decoder: &mut LengthPrefixedDecoder,
     ) -> Result<usize> {
         let batch_start = Instant::now();
         let mut message_count = 0;
+        let mut drained = false;
         
         // 在指定时间内尽可能多地接收消息
         while message_count < config.batch_size && 
@@ -388,31 +965,39 @@ impl NanomsgClient {
                 if let Some(ref mut sock) = socket_guard.as_mut() {
                     sock.recv(buffer)
                 } else {
-                    return Err(VehicleError::NanomsgError("Socket not available".to_string()));
+                    return Err(VehicleError::nanomsg(NanomsgErrorKind::NotConnected, "Socket not available"));
                 }
             };
             
             match receive_result {
-                Ok(bytes_received) => {
-                    if bytes_received > 0 {
-                        // 提交消息给处理器
-                        if let Err(e) = message_processor.submit_message(&buffer[..bytes_received]).await {
-                            warn!("Failed to submit message: {}", e);
-                        } else {
+                Ok(0) => {}
+                Ok(bytes_received) => match config.framing {
+                    FramingMode::MessageBoundary => {
+                        if Self::submit_frame(config, socket, message_processor, stats, &buffer[..bytes_received]).await {
                             message_count += 1;
-                            
-                            // 更新统计
-                            {
-                                let mut stats_guard = stats.write();
-                                stats_guard.bytes_received += bytes_received as u64;
-                                stats_guard.messages_received += 1;
-                                stats_guard.last_message_time = Some(Instant::now());
+                        }
+                    }
+                    FramingMode::LengthPrefixed => {
+                        // 一次接收可能包含多条完整帧，末尾的半帧留待下次接收
+                        decoder.extend(&buffer[..bytes_received]);
+                        while let Some(frame) = decoder.next_frame()? {
+                            if Self::submit_frame(config, socket, message_processor, stats, frame).await {
+                                message_count += 1;
                             }
                         }
                     }
+                },
+                Err(VehicleError::WouldBlock) => {
+                    // 没有消息可接收，退出批量接收；其他socket错误向上传递以触发重连
+                    drained = true;
+                    break;
                 }
-                Err(VehicleError::NanomsgError(_)) => {
-                    // 没有消息可接收，退出批量接收
+                Err(e @ VehicleError::NanomsgError { kind: NanomsgErrorKind::EndOfStream, .. }) => {
+                    // 先返回本批已收到的消息，下次接收时再报告流结束
+                    if message_count == 0 {
+                        return Err(e);
+                    }
+                    drained = true;
                     break;
                 }
                 Err(e) => {
@@ -421,45 +1006,104 @@ impl NanomsgClient {
             }
         }
         
-        // 更新平均批量大小
+        // 更新批量统计，用于判断batch_size和batch_timeout哪个是限制因素
         if message_count > 0 {
+            let batch_duration = batch_start.elapsed();
             let mut stats_guard = stats.write();
-            if stats_guard.avg_batch_size == 0.0 {
-                stats_guard.avg_batch_size = message_count as f64;
-            } else {
-                // 移动平均
-                stats_guard.avg_batch_size = 
-                    (stats_guard.avg_batch_size * 0.9) + (message_count as f64 * 0.1);
+            Self::update_batch_average(&mut stats_guard.avg_batch_size, message_count as f64);
+            Self::update_batch_average(
+                &mut stats_guard.avg_batch_duration_us,
+                batch_duration.as_secs_f64() * 1_000_000.0,
+            );
+            // 长度前缀分帧时一次接收可能超出batch_size，填充率按1计
+            let fill_ratio = (message_count as f64 / config.batch_size as f64).min(1.0);
+            Self::update_batch_average(&mut stats_guard.avg_batch_fill_ratio, fill_ratio);
+            
+            if message_count >= config.batch_size {
+                stats_guard.batches_size_limited += 1;
+            } else if !drained {
+                stats_guard.batches_timeout_limited += 1;
             }
         }
         
         Ok(message_count)
     }
     
+    /// 批量统计的移动平均，首个样本直接作为初始值
+    fn update_batch_average(average: &mut f64, sample: f64) {
+        if *average == 0.0 {
+            *average = sample;
+        } else {
+            *average = (*average * 0.9) + (sample * 0.1);
+        }
+    }
+    
+    /// 提交一帧给处理器并更新统计，返回是否提交成功
+    ///
+    /// 已接收、有意丢弃和无效的帧向传输确认；队列满等暂时失败的帧不确认，由支持重放的传输重新投递
+    async fn submit_frame(
+        config: &NanomsgConfig,
+        socket: &Arc<RwLock<Option<Box<dyn Transport>>>>,
+        message_processor: &Arc<MessageProcessor>,
+        stats: &Arc<RwLock<NanomsgStats>>,
+        frame: &[u8],
+    ) -> bool {
+        let result = match config.source_id {
+            Some(ref source) => message_processor.submit_message_detailed_from(frame, source).await,
+            None => message_processor.submit_message_detailed(frame).await,
+        };
+        let disposition = FrameDisposition::of(&result);
+        if let Some(sock) = socket.write().as_mut() {
+            match disposition {
+                FrameDisposition::Ack => sock.ack(),
+                FrameDisposition::Reject => sock.reject(),
+                FrameDisposition::Retry => {}
+            }
+        }
+        if disposition == FrameDisposition::Reject {
+            stats.write().messages_rejected += 1;
+        }
+        if let Err(e) = result {
+            warn!("Failed to submit message: {}", e);
+            return false;
+        }
+        
+        let mut stats_guard = stats.write();
+        stats_guard.bytes_received += frame.len() as u64;
+        stats_guard.messages_received += 1;
+        stats_guard.last_message_time = Some(Instant::now());
+        true
+    }
+    
     /// 生成统计报告任务
-    fn spawn_stats_reporter(&self) -> tokio::task::JoinHandle<Result<()>> {
+    fn spawn_stats_reporter(&self, shutdown: CancellationToken) -> tokio::task::JoinHandle<Result<()>> {
         let stats = self.stats.clone();
         let is_running = self.is_running.clone();
         let connection_state = self.connection_state.clone();
         
-        tokio::spawn(async move {
+        runtime::spawn(self.runtime.as_ref(), async move {
             info!("Started stats reporter");
             
             while *is_running.read() {
-                sleep(Duration::from_secs(30)).await;
+                tokio::select! {
+                    _ = sleep(Duration::from_secs(30)) => {}
+                    _ = shutdown.cancelled() => break,
+                }
                 
                 let stats_snapshot = stats.read().clone();
                 let current_state = *connection_state.read();
                 
                 info!(
                     "Nanomsg Stats - State: {:?}, Messages: {}, Bytes: {}, \
-                     Connections: {}, Reconnections: {}, Avg Batch: {:.1}",
+                     Connections: {}, Reconnections: {}, Avg Batch: {:.1} ({:.0}% full, {:.0}μs)",
                     current_state,
                     stats_snapshot.messages_received,
                     stats_snapshot.bytes_received,
                     stats_snapshot.connection_attempts,
                     stats_snapshot.reconnections,
-                    stats_snapshot.avg_batch_size
+                    stats_snapshot.avg_batch_size,
+                    stats_snapshot.avg_batch_fill_ratio * 100.0,
+                    stats_snapshot.avg_batch_duration_us
                 );
                 
                 // 检查连接健康状态
@@ -489,16 +1133,58 @@ impl NanomsgClient {
         self.stats.read().clone()
     }
     
+    /// 按间隔把处理器与客户端统计原子地写入`path`，`token`取消时写入最后一次快照后退出
+    pub fn persist_stats(
+        &self,
+        path: impl Into<PathBuf>,
+        interval: Duration,
+        token: CancellationToken,
+    ) -> tokio::task::JoinHandle<()> {
+        let processor = self.message_processor.clone();
+        let stats = self.stats.clone();
+        persistence::spawn_persist_task(self.runtime.as_ref(), path.into(), interval, token, move || {
+            StatsSnapshot::new(processor.get_stats(), Some(stats.read().clone()))
+        })
+    }
+    
+    /// 累加上次运行保存的客户端计数（如`StatsSnapshot::load`读取的快照），用于重启后延续统计
+    pub fn restore_stats(&self, previous: &NanomsgStats) {
+        let mut stats = self.stats.write();
+        stats.bytes_received += previous.bytes_received;
+        stats.messages_received += previous.messages_received;
+        stats.connection_attempts += previous.connection_attempts;
+        stats.reconnections += previous.reconnections;
+        stats.planned_reconnections += previous.planned_reconnections;
+        stats.idle_disconnects += previous.idle_disconnects;
+        stats.batches_size_limited += previous.batches_size_limited;
+        stats.batches_timeout_limited += previous.batches_timeout_limited;
+    }
+    
     /// 检查是否正在运行
     pub fn is_running(&self) -> bool {
         *self.is_running.read()
     }
+    
+    /// 接收任务是否仍在运行；`stop`之后变为false前可能仍在向处理器提交消息
+    pub fn is_receiving(&self) -> bool {
+        self.receiving.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for NanomsgClient {
+    fn drop(&mut self) {
+        if self.is_running() {
+            self.stop();
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::message_processor::MessageProcessor;
+    use crate::message_processor::{MessageProcessor, ProcessorConfig};
+    use crate::transport::InprocSender;
+    use crate::types::MessagePriority;
     
     #[tokio::test]
     async fn test_nanomsg_client_creation() {
@@ -540,4 +1226,521 @@ mod tests {
         assert!(config.buffer_size > 0);
         assert!(config.batch_size > 0);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_scripted_socket() {
+        let frames = vec![b"first".to_vec(), b"second".to_vec()];
+        let mut buffer = vec![0u8; 64];
+        let mut recv_frame = |socket: &mut MockNanomsgSocket| {
+            socket.recv(&mut buffer).map(|len| buffer[..len].to_vec())
+        };
+        
+        let mut socket = MockNanomsgSocket::scripted(frames.clone());
+        socket.bind("ipc:///tmp/test.ipc").unwrap();
+        assert_eq!(recv_frame(&mut socket).unwrap(), b"first");
+        assert_eq!(recv_frame(&mut socket).unwrap(), b"second");
+        assert!(recv_frame(&mut socket).is_err());
+        assert!(recv_frame(&mut socket).is_err());
+        
+        let mut socket = MockNanomsgSocket::scripted(frames).with_loop(true);
+        socket.bind("ipc:///tmp/test.ipc").unwrap();
+        for expected in [&b"first"[..], b"second", b"first"] {
+            assert_eq!(recv_frame(&mut socket).unwrap(), expected);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_client_with_scripted_socket() {
+        let processed_count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let count_clone = processed_count.clone();
+        let processor = MessageProcessor::new();
+        processor.set_callback(Arc::new(move |_| {
+            count_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }));
+        let processor = Arc::new(processor);
+        let runner = processor.clone();
+        let processor_handle = tokio::spawn(async move { runner.start().await });
+        
+        let frames: Vec<Vec<u8>> = (0..3)
+            .map(|i| {
+                format!(
+                    r#"{{"service": "tracking", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                    i
+                )
+                .into_bytes()
+            })
+            .collect();
+        let mut client = NanomsgClient::new(NanomsgConfig::default(), processor.clone());
+        client.set_socket_factory(Arc::new(move || Box::new(MockNanomsgSocket::scripted(frames.clone())) as Box<dyn Transport>));
+        let client = Arc::new(client);
+        let client_runner = client.clone();
+        let client_handle = tokio::spawn(async move { client_runner.start().await });
+        
+        sleep(Duration::from_millis(200)).await;
+        assert_eq!(client.get_stats().messages_received, 3);
+        assert_eq!(processed_count.load(std::sync::atomic::Ordering::SeqCst), 3);
+        
+        // 脚本读完即流结束：断开且不重连
+        assert!(client.is_stream_ended());
+        assert_eq!(client.get_connection_state(), ConnectionState::Disconnected);
+        assert_eq!(client.get_stats().connection_attempts, 1);
+        
+        client.stop();
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), processor_handle).await;
+        client_handle.abort();
+    }
+    
+    /// 统计确认与拒绝次数的脚本传输
+    struct AckCountingSocket {
+        inner: MockNanomsgSocket,
+        acks: Arc<std::sync::atomic::AtomicUsize>,
+        rejects: Arc<std::sync::atomic::AtomicUsize>,
+    }
+    
+    impl Transport for AckCountingSocket {
+        fn bind(&mut self, url: &str) -> Result<()> {
+            self.inner.bind(url)
+        }
+        
+        fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
+            self.inner.recv(buffer)
+        }
+        
+        fn ack(&mut self) {
+            self.acks.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+        
+        fn reject(&mut self) {
+            self.rejects.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+        
+        fn close(&mut self) {
+            self.inner.close()
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_frames_acked_by_submit_outcome() {
+        let processor = Arc::new(MessageProcessor::new());
+        processor.set_sampling_enabled(false);
+        let runner = processor.clone();
+        let processor_handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+        // 暂停时背景队列只进不出，超出容量的帧因队列满被丢弃
+        processor.pause();
+        
+        let traj = |i: usize| {
+            format!(
+                r#"{{"service": "traj", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{}}}}}}"#,
+                i
+            )
+            .into_bytes()
+        };
+        let capacity = MessagePriority::Background.queue_capacity();
+        let mut frames: Vec<Vec<u8>> = (0..=capacity).map(traj).collect();
+        frames.push(b"not json".to_vec());
+        frames.push(traj(0));
+        
+        let acks = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let rejects = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let (acks_clone, rejects_clone) = (acks.clone(), rejects.clone());
+        let mut client = NanomsgClient::new(NanomsgConfig::default(), processor.clone());
+        client.set_socket_factory(Arc::new(move || {
+            Box::new(AckCountingSocket {
+                inner: MockNanomsgSocket::scripted(frames.clone()),
+                acks: acks_clone.clone(),
+                rejects: rejects_clone.clone(),
+            }) as Box<dyn Transport>
+        }));
+        let client = Arc::new(client);
+        let client_runner = client.clone();
+        let client_handle = tokio::spawn(async move { client_runner.start().await });
+        
+        sleep(Duration::from_millis(300)).await;
+        // 入队与去重的帧确认；无效帧拒绝；队列满的帧不确认，留待重放
+        assert_eq!(acks.load(std::sync::atomic::Ordering::SeqCst), capacity + 1);
+        assert_eq!(rejects.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(client.get_stats().messages_rejected, 1);
+        
+        client.stop();
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), processor_handle).await;
+        client_handle.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_connection_recycled_after_max_lifetime() {
+        let processor = Arc::new(MessageProcessor::new());
+        let config = NanomsgConfig {
+            connection_max_lifetime: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let mut client = NanomsgClient::new(config, processor);
+        client.set_socket_factory(Arc::new(|| Box::new(MockNanomsgSocket::scripted(Vec::new())) as Box<dyn Transport>));
+        let client = Arc::new(client);
+        let runner = client.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        
+        sleep(Duration::from_millis(350)).await;
+        let stats = client.get_stats();
+        assert!(stats.planned_reconnections >= 2, "planned: {}", stats.planned_reconnections);
+        assert_eq!(stats.reconnections, 0);
+        
+        client.stop();
+        handle.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_idle_timeout_forces_reconnect() {
+        let processor = Arc::new(MessageProcessor::new());
+        let config = NanomsgConfig {
+            idle_reconnect_timeout: Some(Duration::from_millis(100)),
+            reconnect_interval: Duration::from_millis(20),
+            ..Default::default()
+        };
+        let mut client = NanomsgClient::new(config, processor);
+        client.set_socket_factory(Arc::new(|| Box::new(MockNanomsgSocket::scripted(Vec::new())) as Box<dyn Transport>));
+        let client = Arc::new(client);
+        let runner = client.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        
+        sleep(Duration::from_millis(350)).await;
+        let stats = client.get_stats();
+        assert!(stats.idle_disconnects >= 1);
+        assert!(stats.connection_attempts >= 2);
+        
+        client.stop();
+        handle.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_socket_error_propagates_from_batch() {
+        let processor = Arc::new(MessageProcessor::new());
+        let config = NanomsgConfig::default();
+        let stats = Arc::new(RwLock::new(NanomsgStats::default()));
+        let mut buffer = vec![0u8; 64];
+        
+        // 未连接的socket是真实错误，不应被当作暂无消息
+        let socket: Arc<RwLock<Option<Box<dyn Transport>>>> = Arc::new(RwLock::new(Some(Box::new(MockNanomsgSocket::new()))));
+        let result = NanomsgClient::receive_message_batch(&config, &socket, &processor, &stats, &mut buffer, &mut LengthPrefixedDecoder::default()).await;
+        assert!(matches!(
+            result,
+            Err(VehicleError::NanomsgError { kind: NanomsgErrorKind::NotConnected, .. })
+        ));
+        
+        let mut connected = MockNanomsgSocket::scripted(Vec::new());
+        connected.bind("ipc:///tmp/test.ipc").unwrap();
+        let socket: Arc<RwLock<Option<Box<dyn Transport>>>> = Arc::new(RwLock::new(Some(Box::new(connected))));
+        let result = NanomsgClient::receive_message_batch(&config, &socket, &processor, &stats, &mut buffer, &mut LengthPrefixedDecoder::default()).await;
+        assert_eq!(result.unwrap(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_length_prefixed_frames_across_receives() {
+        let processor = Arc::new(MessageProcessor::new());
+        let config = NanomsgConfig::builder().framing(FramingMode::LengthPrefixed).build().unwrap();
+        let stats = Arc::new(RwLock::new(NanomsgStats::default()));
+        let mut buffer = vec![0u8; 1024];
+        
+        let mut stream = Vec::new();
+        for i in 0..3 {
+            let payload = format!(
+                r#"{{"service": "tracking", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{"x": 1.0}}}}}}"#,
+                i
+            );
+            stream.extend(crate::framing::encode_length_prefixed(payload.as_bytes()));
+        }
+        // 第二帧跨越两次接收
+        let split = stream.len() / 2;
+        let mut socket = MockNanomsgSocket::scripted(vec![stream[..split].to_vec(), stream[split..].to_vec()]);
+        socket.bind("ipc:///tmp/test.ipc").unwrap();
+        let socket: Arc<RwLock<Option<Box<dyn Transport>>>> = Arc::new(RwLock::new(Some(Box::new(socket))));
+        
+        let mut decoder = LengthPrefixedDecoder::default();
+        let result = NanomsgClient::receive_message_batch(&config, &socket, &processor, &stats, &mut buffer, &mut decoder).await;
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(stats.read().messages_received, 3);
+        assert_eq!(decoder.pending_len(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_batch_timing_stats() {
+        let processor = Arc::new(MessageProcessor::new());
+        let config = NanomsgConfig::builder().batch_size(2).build().unwrap();
+        let stats = Arc::new(RwLock::new(NanomsgStats::default()));
+        let mut buffer = vec![0u8; 1024];
+        
+        let frames = (0..3)
+            .map(|i| {
+                format!(
+                    r#"{{"service": "tracking", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{"x": 1.0}}}}}}"#,
+                    i
+                )
+                .into_bytes()
+            })
+            .collect();
+        let mut socket = MockNanomsgSocket::scripted(frames);
+        socket.bind("ipc:///tmp/test.ipc").unwrap();
+        let socket: Arc<RwLock<Option<Box<dyn Transport>>>> = Arc::new(RwLock::new(Some(Box::new(socket))));
+        let mut decoder = LengthPrefixedDecoder::default();
+        
+        // 第一批达到batch_size，第二批取完剩余消息后因socket为空结束
+        for expected in [2, 1] {
+            let result = NanomsgClient::receive_message_batch(&config, &socket, &processor, &stats, &mut buffer, &mut decoder).await;
+            assert_eq!(result.unwrap(), expected);
+        }
+        let result = NanomsgClient::receive_message_batch(&config, &socket, &processor, &stats, &mut buffer, &mut decoder).await;
+        assert!(matches!(
+            result,
+            Err(VehicleError::NanomsgError { kind: NanomsgErrorKind::EndOfStream, .. })
+        ));
+        
+        let stats = stats.read();
+        assert_eq!(stats.batches_size_limited, 1);
+        assert_eq!(stats.batches_timeout_limited, 0);
+        assert!((stats.avg_batch_fill_ratio - 0.95).abs() < 1e-9);
+        assert!(stats.avg_batch_duration_us > 0.0);
+    }
+    
+    #[test]
+    fn test_batch_auto_tune_bounds() {
+        let tune = BatchAutoTune {
+            min_batch_size: 8,
+            max_batch_size: 64,
+            ..Default::default()
+        };
+        let fast = Duration::from_millis(1);
+        
+        // 持续填满且无积压时增大，直至上限
+        assert_eq!(tune.next_batch_size(40, 0.95, fast, 0.0), 51);
+        assert_eq!(tune.next_batch_size(60, 0.95, fast, 0.0), 64);
+        // 未填满时保持
+        assert_eq!(tune.next_batch_size(40, 0.5, fast, 0.0), 40);
+        // 批次耗时过长或处理器积压时减小，直至下限
+        assert_eq!(tune.next_batch_size(40, 0.95, Duration::from_millis(20), 0.0), 30);
+        assert_eq!(tune.next_batch_size(40, 0.95, fast, 0.8), 30);
+        assert_eq!(tune.next_batch_size(9, 0.5, fast, 0.8), 8);
+        
+        let invalid = NanomsgConfig::builder()
+            .batch_auto_tune(BatchAutoTune { min_batch_size: 100, max_batch_size: 10, ..Default::default() })
+            .build();
+        assert!(matches!(invalid, Err(VehicleError::ConfigError(_))));
+    }
+    
+    #[test]
+    fn test_idle_backoff_grows_to_cap() {
+        let config = NanomsgConfig {
+            idle_backoff_min: Duration::from_micros(100),
+            idle_backoff_max: Duration::from_micros(350),
+            ..Default::default()
+        };
+        
+        let mut backoff = config.idle_backoff_min;
+        let mut sequence = Vec::new();
+        for _ in 0..4 {
+            backoff = NanomsgClient::next_idle_backoff(&config, backoff);
+            sequence.push(backoff.as_micros());
+        }
+        assert_eq!(sequence, vec![200, 350, 350, 350]);
+    }
+    
+    #[tokio::test]
+    async fn test_drop_stops_client() {
+        let processor = Arc::new(MessageProcessor::new());
+        let mut client = NanomsgClient::new(NanomsgConfig::default(), processor.clone());
+        client.set_socket_factory(Arc::new(|| Box::new(MockNanomsgSocket::scripted(Vec::new())) as Box<dyn Transport>));
+        let client = Arc::new(client);
+        let runner = client.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(50)).await;
+        
+        handle.abort();
+        let _ = handle.await;
+        let is_running = client.is_running.clone();
+        drop(client);
+        assert!(!*is_running.read());
+        
+        // 接收任务退出后释放对处理器的引用
+        sleep(Duration::from_millis(200)).await;
+        assert_eq!(Arc::strong_count(&processor), 1);
+    }
+    
+    #[test]
+    fn test_config_builder_validation() {
+        let config = NanomsgConfig::builder()
+            .listen_url("ipc:///tmp/builder.ipc")
+            .batch_size(50)
+            .idle_reconnect_timeout(Duration::from_secs(30))
+            .build()
+            .unwrap();
+        assert_eq!(config.listen_url, "ipc:///tmp/builder.ipc");
+        assert_eq!(config.batch_size, 50);
+        assert_eq!(config.idle_reconnect_timeout, Some(Duration::from_secs(30)));
+        
+        assert!(matches!(
+            NanomsgConfig::builder().batch_size(0).build(),
+            Err(VehicleError::ConfigError(_))
+        ));
+        assert!(NanomsgConfig::builder().buffer_size(0).build().is_err());
+        assert!(NanomsgConfig::builder()
+            .idle_backoff(Duration::from_millis(10), Duration::from_millis(1))
+            .build()
+            .is_err());
+        assert!(NanomsgConfig::default().validate().is_ok());
+    }
+    
+    #[test]
+    fn test_tasks_run_on_dedicated_runtime() {
+        let dedicated = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("vehicle-processing")
+            .enable_all()
+            .build()
+            .unwrap();
+        let app = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        
+        let processor = MessageProcessor::with_config(ProcessorConfig {
+            runtime: Some(dedicated.handle().clone()),
+            ..Default::default()
+        });
+        let (tx, rx) = std::sync::mpsc::channel();
+        processor.set_callback(Arc::new(move |_| {
+            let _ = tx.send(std::thread::current().name().map(|name| name.to_string()));
+            Ok(())
+        }));
+        let processor = Arc::new(processor);
+        let mut client = NanomsgClient::with_runtime(
+            NanomsgConfig::default(),
+            processor.clone(),
+            dedicated.handle().clone(),
+        );
+        client.set_socket_factory(Arc::new(|| Box::new(MockNanomsgSocket::scripted(vec![
+            br#"{"service": "tracking", "params": {"vin": "VIN_1", "timestamp": 1234567890.0, "data": {"x": 1.0}}}"#.to_vec(),
+        ])) as Box<dyn Transport>));
+        let client = Arc::new(client);
+        
+        app.block_on(async {
+            let runner = processor.clone();
+            let processor_handle = tokio::spawn(async move { runner.start().await });
+            let client_runner = client.clone();
+            let client_handle = tokio::spawn(async move { client_runner.start().await });
+            
+            let thread_name = tokio::task::spawn_blocking(move || rx.recv_timeout(Duration::from_secs(2)))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(thread_name.as_deref(), Some("vehicle-processing"));
+            
+            client.stop();
+            processor.stop();
+            let _ = tokio::time::timeout(Duration::from_secs(1), client_handle).await;
+            let _ = tokio::time::timeout(Duration::from_secs(1), processor_handle).await;
+        });
+    }
+    
+    #[tokio::test]
+    async fn test_reconfigure_in_place() {
+        let processor = Arc::new(MessageProcessor::new());
+        let mut client = NanomsgClient::new(NanomsgConfig::default(), processor);
+        client.set_socket_factory(Arc::new(|| Box::new(MockNanomsgSocket::scripted(Vec::new()).with_loop(true)) as Box<dyn Transport>));
+        let client = Arc::new(client);
+        let runner = client.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(client.get_connection_state(), ConnectionState::Connected);
+        
+        // 无效配置被拒绝，原配置保持不变
+        let invalid = NanomsgConfig { batch_size: 0, ..client.config() };
+        assert!(matches!(client.reconfigure(invalid), Err(VehicleError::ConfigError(_))));
+        assert_eq!(client.config().batch_size, 100);
+        
+        // 批量参数在线生效，不触发重连
+        let tuned = NanomsgConfig { batch_size: 10, buffer_size: 4096, ..client.config() };
+        client.reconfigure(tuned).unwrap();
+        assert_eq!(client.config().batch_size, 10);
+        assert_eq!(client.get_stats().planned_reconnections, 0);
+        
+        // URL变化时重连
+        let moved = NanomsgConfig { listen_url: "ipc:///tmp/moved.ipc".to_string(), ..client.config() };
+        client.reconfigure(moved).unwrap();
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(client.get_stats().planned_reconnections, 1);
+        assert_eq!(client.get_connection_state(), ConnectionState::Connected);
+        assert_eq!(client.get_stats().connection_attempts, 2);
+        
+        client.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
+    }
+    
+    #[tokio::test]
+    async fn test_start_with_token_cancellation() {
+        let processor = Arc::new(MessageProcessor::new());
+        let client = Arc::new(NanomsgClient::new(NanomsgConfig::default(), processor.clone()));
+        let token = CancellationToken::new();
+        
+        let processor_runner = processor.clone();
+        let processor_token = token.clone();
+        let processor_handle = tokio::spawn(async move { processor_runner.start_with_token(processor_token).await });
+        let client_runner = client.clone();
+        let client_token = token.clone();
+        let client_handle = tokio::spawn(async move { client_runner.start_with_token(client_token).await });
+        sleep(Duration::from_millis(50)).await;
+        assert!(processor.is_running());
+        assert!(client.is_running());
+        
+        token.cancel();
+        let processor_result = tokio::time::timeout(Duration::from_secs(1), processor_handle).await;
+        let client_result = tokio::time::timeout(Duration::from_secs(1), client_handle).await;
+        assert!(processor_result.unwrap().unwrap().is_ok());
+        assert!(client_result.unwrap().unwrap().is_ok());
+        assert!(!processor.is_running());
+        assert!(!client.is_running());
+        assert_eq!(client.get_connection_state(), ConnectionState::Disconnected);
+    }
+    
+    #[tokio::test]
+    async fn test_inproc_end_to_end() {
+        let url = "inproc://client-end-to-end";
+        let sender = InprocSender::connect(url).unwrap();
+        
+        let processor = MessageProcessor::new();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        processor.set_callback(Arc::new(move |message| {
+            let _ = tx.send(message.vin);
+            Ok(())
+        }));
+        let processor = Arc::new(processor);
+        let config = NanomsgConfig::builder().listen_url(url).build().unwrap();
+        let client = Arc::new(NanomsgClient::new(config, processor.clone()));
+        
+        let runner = processor.clone();
+        let processor_handle = tokio::spawn(async move { runner.start().await });
+        let client_runner = client.clone();
+        let client_handle = tokio::spawn(async move { client_runner.start().await });
+        
+        for i in 0..3 {
+            let frame = format!(
+                r#"{{"service": "tracking", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{"x": 1.0}}}}}}"#,
+                i
+            );
+            sender.send(frame.as_bytes()).unwrap();
+        }
+        
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            let vin = tokio::time::timeout(Duration::from_secs(2), rx.recv()).await.unwrap().unwrap();
+            received.push(vin);
+        }
+        assert_eq!(received, ["VIN_0", "VIN_1", "VIN_2"]);
+        
+        client.stop();
+        processor.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), client_handle).await;
+        let _ = tokio::time::timeout(Duration::from_secs(1), processor_handle).await;
+    }
+}
@@ -0,0 +1,209 @@
+//! NATS传输
+//!
+//! 订阅NATS主题，把收到的消息作为原始帧交给客户端的接收循环，
+//! 使同一处理核心无需改动即可消费NATS上发布的车辆消息。
+
+use crate::error::{NanomsgErrorKind, Result, VehicleError};
+use crate::nanomsg_client::SocketFactory;
+use crate::transport::Transport;
+
+use crossbeam::channel::{Receiver, TryRecvError, TrySendError};
+use futures::StreamExt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// 订阅线程与接收端之间缓存的最大帧数
+const NATS_CHANNEL_CAPACITY: usize = 4096;
+
+/// 连接NATS服务器的超时时间
+const NATS_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// NATS传输配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NatsConfig {
+    /// NATS服务器地址，如`nats://127.0.0.1:4222`
+    pub url: String,
+    /// 订阅的主题，支持NATS通配符（如`vehicle.*.tracking`）
+    pub subject: String,
+}
+
+impl NatsConfig {
+    /// 创建配置
+    pub fn new(url: impl Into<String>, subject: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            subject: subject.into(),
+        }
+    }
+
+    /// 创建NATS传输的socket工厂，用于`NanomsgClient::set_socket_factory`
+    pub fn socket_factory(&self) -> SocketFactory {
+        let config = self.clone();
+        Arc::new(move || Box::new(NatsTransport::new(config.clone())) as Box<dyn Transport>)
+    }
+}
+
+/// 运行中的订阅：后台线程持有NATS连接，通过通道转交消息
+struct NatsSubscription {
+    rx: Receiver<Vec<u8>>,
+    shutdown: CancellationToken,
+}
+
+/// NATS传输：在独立线程上订阅主题，`recv`非阻塞地取出收到的消息
+///
+/// `bind`的URL参数仅用于日志，实际连接`NatsConfig::url`；
+/// 订阅线程缓存已满时新消息被丢弃并计入`dropped`
+pub struct NatsTransport {
+    config: NatsConfig,
+    subscription: Option<NatsSubscription>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl NatsTransport {
+    /// 创建未连接的传输
+    pub fn new(config: NatsConfig) -> Self {
+        Self {
+            config,
+            subscription: None,
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// 因缓存已满丢弃的消息数
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// 在后台线程上连接并订阅，阻塞直到订阅建立或失败
+    fn subscribe(&self) -> Result<NatsSubscription> {
+        let (frame_tx, frame_rx) = crossbeam::channel::bounded(NATS_CHANNEL_CAPACITY);
+        let (ready_tx, ready_rx) = crossbeam::channel::bounded(1);
+        let shutdown = CancellationToken::new();
+        let config = self.config.clone();
+        let token = shutdown.clone();
+        let dropped = self.dropped.clone();
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        thread::Builder::new().name("nats-transport".to_string()).spawn(move || {
+            runtime.block_on(async move {
+                let connect = async_nats::ConnectOptions::new()
+                    .connection_timeout(NATS_CONNECT_TIMEOUT)
+                    .connect(config.url.as_str())
+                    .await;
+                let client = match connect {
+                    Ok(client) => client,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(format!("Failed to connect to {}: {}", config.url, e)));
+                        return;
+                    }
+                };
+                let mut subscriber = match client.subscribe(config.subject.clone()).await {
+                    Ok(subscriber) => subscriber,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(format!("Failed to subscribe to {}: {}", config.subject, e)));
+                        return;
+                    }
+                };
+                let _ = ready_tx.send(Ok(()));
+
+                loop {
+                    tokio::select! {
+                        _ = token.cancelled() => break,
+                        message = subscriber.next() => {
+                            let Some(message) = message else {
+                                warn!("NATS subscription to {} ended", config.subject);
+                                break;
+                            };
+                            match frame_tx.try_send(message.payload.to_vec()) {
+                                Ok(()) => {}
+                                Err(TrySendError::Full(_)) => {
+                                    dropped.fetch_add(1, Ordering::Relaxed);
+                                }
+                                Err(TrySendError::Disconnected(_)) => break,
+                            }
+                        }
+                    }
+                }
+                let _ = subscriber.unsubscribe().await;
+            });
+        })?;
+
+        match ready_rx.recv_timeout(NATS_CONNECT_TIMEOUT * 2) {
+            Ok(Ok(())) => Ok(NatsSubscription { rx: frame_rx, shutdown }),
+            Ok(Err(msg)) => Err(VehicleError::nanomsg(NanomsgErrorKind::NotConnected, msg)),
+            Err(_) => {
+                shutdown.cancel();
+                Err(VehicleError::nanomsg(
+                    NanomsgErrorKind::NotConnected,
+                    format!("Timed out connecting to {}", self.config.url),
+                ))
+            }
+        }
+    }
+}
+
+impl Transport for NatsTransport {
+    fn bind(&mut self, url: &str) -> Result<()> {
+        self.close();
+        self.subscription = Some(self.subscribe()?);
+        info!(
+            "NATS transport subscribed to {} on {} (endpoint {})",
+            self.config.subject, self.config.url, url
+        );
+        Ok(())
+    }
+
+    fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        let Some(ref subscription) = self.subscription else {
+            return Err(VehicleError::nanomsg(NanomsgErrorKind::NotConnected, "NATS transport not subscribed"));
+        };
+
+        match subscription.rx.try_recv() {
+            Ok(frame) => {
+                let copy_len = frame.len().min(buffer.len());
+                buffer[..copy_len].copy_from_slice(&frame[..copy_len]);
+                Ok(copy_len)
+            }
+            Err(TryRecvError::Empty) => Err(VehicleError::WouldBlock),
+            Err(TryRecvError::Disconnected) => Err(VehicleError::nanomsg(
+                NanomsgErrorKind::ConnectionReset,
+                "NATS subscription closed",
+            )),
+        }
+    }
+
+    fn close(&mut self) {
+        if let Some(subscription) = self.subscription.take() {
+            subscription.shutdown.cancel();
+        }
+    }
+}
+
+impl Drop for NatsTransport {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nats_transport_requires_subscription() {
+        // 端口1上没有NATS服务器，连接应立即失败
+        let mut transport = NatsTransport::new(NatsConfig::new("nats://127.0.0.1:1", "vehicle.>"));
+        let mut buffer = [0u8; 16];
+        assert!(matches!(
+            transport.recv(&mut buffer),
+            Err(VehicleError::NanomsgError { kind: NanomsgErrorKind::NotConnected, .. })
+        ));
+        assert!(transport.bind("nats://127.0.0.1:1").is_err());
+        assert!(transport.recv(&mut buffer).is_err());
+        assert_eq!(transport.dropped(), 0);
+    }
+}
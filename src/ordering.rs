@@ -0,0 +1,140 @@
+use crate::clock::{Clock, SystemClock};
+use crate::sequence::series_key;
+
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// 时间戳乱序统计
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OrderStats {
+    /// 跟踪中的序列数（VIN+服务）
+    pub series_tracked: usize,
+    /// 时间戳早于该序列已见最新时间戳的消息数
+    pub out_of_order: u64,
+    /// 最大回退幅度（秒）
+    pub max_regression_secs: f64,
+}
+
+/// 按VIN+服务跟踪消息时间戳，统计乱序到达，用于诊断上游时钟问题或网络重排
+#[derive(Debug)]
+pub struct TimestampOrderTracker {
+    // 序列键 -> (最新时间戳, 最近出现时间)
+    latest: DashMap<u64, (f64, Instant)>,
+    out_of_order: AtomicU64,
+    // 非负f64的位模式与数值顺序一致，可直接用fetch_max
+    max_regression_bits: AtomicU64,
+    clock: Arc<dyn Clock>,
+}
+
+impl TimestampOrderTracker {
+    /// 创建时间戳乱序跟踪器
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// 使用指定时间源创建
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            latest: DashMap::new(),
+            out_of_order: AtomicU64::new(0),
+            max_regression_bits: AtomicU64::new(0),
+            clock,
+        }
+    }
+
+    /// 记录时间戳，早于已见最新时间戳时返回回退幅度（秒）
+    ///
+    /// 乱序消息不更新最新时间戳，后续消息仍与最新时间戳比较
+    pub fn observe(&self, vin: &str, service: &str, timestamp: f64) -> Option<f64> {
+        let now = self.clock.now();
+        let mut entry = self.latest.entry(series_key(vin, service)).or_insert((timestamp, now));
+        let (latest, seen) = entry.value_mut();
+        *seen = now;
+
+        if timestamp >= *latest {
+            *latest = timestamp;
+            return None;
+        }
+
+        let regression = *latest - timestamp;
+        self.out_of_order.fetch_add(1, Ordering::Relaxed);
+        self.max_regression_bits.fetch_max(regression.to_bits(), Ordering::Relaxed);
+        debug!(
+            "Out-of-order timestamp for {} {}: {} is {:.3}s behind latest",
+            vin, service, timestamp, regression
+        );
+        Some(regression)
+    }
+
+    /// 移除超过`expiry`未出现的序列，返回移除数量
+    pub fn evict_expired(&self, expiry: Duration) -> usize {
+        let now = self.clock.now();
+        let before = self.latest.len();
+        self.latest
+            .retain(|_, (_, seen)| now.saturating_duration_since(*seen) <= expiry);
+        before.saturating_sub(self.latest.len())
+    }
+
+    /// 获取乱序统计
+    pub fn stats(&self) -> OrderStats {
+        OrderStats {
+            series_tracked: self.latest.len(),
+            out_of_order: self.out_of_order.load(Ordering::Relaxed),
+            max_regression_secs: f64::from_bits(self.max_regression_bits.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl Default for TimestampOrderTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn test_counts_out_of_order_per_series() {
+        let tracker = TimestampOrderTracker::new();
+        assert_eq!(tracker.observe("VIN_1", "tracking", 100.0), None);
+        assert_eq!(tracker.observe("VIN_1", "tracking", 101.0), None);
+        assert_eq!(tracker.observe("VIN_1", "tracking", 99.5), Some(1.5));
+        // 乱序消息不改变最新时间戳
+        assert_eq!(tracker.observe("VIN_1", "tracking", 100.5), Some(0.5));
+        // 相同时间戳和其他序列互不影响
+        assert_eq!(tracker.observe("VIN_1", "tracking", 101.0), None);
+        assert_eq!(tracker.observe("VIN_1", "traj", 50.0), None);
+        assert_eq!(tracker.observe("VIN_2", "tracking", 10.0), None);
+
+        assert_eq!(
+            tracker.stats(),
+            OrderStats {
+                series_tracked: 3,
+                out_of_order: 2,
+                max_regression_secs: 1.5,
+            }
+        );
+    }
+
+    #[test]
+    fn test_evicts_inactive_series() {
+        let clock = Arc::new(MockClock::new());
+        let tracker = TimestampOrderTracker::with_clock(clock.clone());
+        tracker.observe("VIN_1", "tracking", 100.0);
+        clock.advance(Duration::from_secs(30));
+        tracker.observe("VIN_2", "tracking", 100.0);
+
+        clock.advance(Duration::from_secs(40));
+        assert_eq!(tracker.evict_expired(Duration::from_secs(60)), 1);
+        assert_eq!(tracker.stats().series_tracked, 1);
+        // 过期序列重新出现时从新时间戳开始跟踪
+        assert_eq!(tracker.observe("VIN_1", "tracking", 50.0), None);
+        assert_eq!(tracker.observe("VIN_2", "tracking", 99.0), Some(1.0));
+    }
+}
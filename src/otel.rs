@@ -0,0 +1,112 @@
+//! OpenTelemetry上下文传播
+//!
+//! 从消息`params`中的W3C `traceparent`/`tracestate`提取上游trace上下文，
+//! 并在转发消息时将当前span的上下文注入回`params`。
+
+use crate::types::VehicleMessage;
+
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+use opentelemetry::Context;
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// W3C trace上下文字段名
+pub const TRACEPARENT_KEY: &str = "traceparent";
+/// W3C trace状态字段名
+pub const TRACESTATE_KEY: &str = "tracestate";
+
+/// 以消息参数作为载体的读取适配器
+struct ParamsExtractor<'a>(&'a VehicleMessage);
+
+impl Extractor for ParamsExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.params.get(key).and_then(|v| v.as_str())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        [TRACEPARENT_KEY, TRACESTATE_KEY]
+            .into_iter()
+            .filter(|key| self.0.params.contains_key(*key))
+            .collect()
+    }
+}
+
+/// 以消息参数作为载体的写入适配器
+struct ParamsInjector<'a>(&'a mut VehicleMessage);
+
+impl Injector for ParamsInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        self.0.params.insert(key.to_string(), serde_json::Value::String(value));
+    }
+}
+
+/// 从消息中提取上游trace上下文，消息未携带有效`traceparent`时返回None
+pub fn extract_context(message: &VehicleMessage) -> Option<Context> {
+    if !message.params.contains_key(TRACEPARENT_KEY) {
+        return None;
+    }
+
+    let context = TraceContextPropagator::new().extract(&ParamsExtractor(message));
+    if context.span().span_context().is_valid() {
+        Some(context)
+    } else {
+        None
+    }
+}
+
+/// 将消息携带的上游上下文设置为span的父上下文
+pub fn set_parent_from_message(span: &Span, message: &VehicleMessage) {
+    if let Some(context) = extract_context(message) {
+        let _ = span.set_parent(context);
+    }
+}
+
+/// 将当前span的trace上下文注入消息参数，用于转发
+pub fn inject_context(message: &mut VehicleMessage) {
+    let context = Span::current().context();
+    TraceContextPropagator::new().inject_context(&context, &mut ParamsInjector(message));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message_with_traceparent(traceparent: &str) -> VehicleMessage {
+        let mut message = VehicleMessage::new(
+            "tracking".to_string(),
+            "TEST_VIN_123".to_string(),
+            1234567890.0,
+        );
+        message.params.insert(
+            TRACEPARENT_KEY.to_string(),
+            serde_json::json!(traceparent),
+        );
+        message
+    }
+
+    #[test]
+    fn test_extract_valid_traceparent() {
+        let message = message_with_traceparent(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        );
+
+        let context = extract_context(&message).unwrap();
+        let span_context = context.span().span_context().clone();
+        assert_eq!(
+            span_context.trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        assert!(span_context.is_remote());
+    }
+
+    #[test]
+    fn test_extract_invalid_traceparent() {
+        let message = message_with_traceparent("not-a-traceparent");
+        assert!(extract_context(&message).is_none());
+
+        let message = VehicleMessage::new("tracking".to_string(), "VIN".to_string(), 1.0);
+        assert!(extract_context(&message).is_none());
+    }
+}
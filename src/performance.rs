@@ -1,14 +1,309 @@
-use crate::types::ProcessingStats;
+use crate::types::{MessagePriority, PipelineStage, ProcessingStats, StageStat, StageTimings};
+use crate::clock::{Clock, SystemClock};
+use crate::metrics::{self, MetricsSink, NoopMetricsSink};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use parking_lot::RwLock;
-use tracing::{info, warn};
+use parking_lot::{Mutex, RwLock};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// 性能报告回调，每个报告周期以统计快照调用一次
+pub type ReportCallback = Arc<dyn Fn(&ProcessingStats) + Send + Sync>;
+
+/// 性能警告类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningKind {
+    /// 单条消息处理超过10ms
+    SlowProcessing,
+    /// 服务处理超出配置的延迟预算
+    LatencyBudgetExceeded,
+    /// 更新队列大小时超过800
+    LargeQueue,
+    /// 报告周期内丢弃率超过5%
+    HighDropRate,
+    /// 平均处理时间超过5ms
+    HighProcessingTime,
+    /// 报告周期内队列积压超过500
+    QueueBacklog,
+    /// 处理速率过低
+    LowProcessingRate,
+    /// 健康状态变差
+    HealthDegraded,
+}
+
+/// 性能警告，通过`PerformanceMonitor::set_warning_sender`推送给程序化消费者（如值班告警集成）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    /// 警告类别
+    pub kind: WarningKind,
+    /// 警告详情，与日志内容一致
+    pub detail: String,
+    /// 产生时间（Unix秒）
+    pub timestamp: f64,
+}
+
+/// 固定分桶直方图
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    /// 各桶的上界（含），最后一个桶之后为溢出桶
+    bounds: Vec<u64>,
+    /// 各桶计数，长度为bounds.len() + 1
+    counts: Vec<u64>,
+    total: u64,
+    sum: u64,
+    max: u64,
+}
+
+impl Histogram {
+    /// 使用指定的桶上界创建直方图（需递增）
+    pub fn new(bounds: Vec<u64>) -> Self {
+        let counts = vec![0; bounds.len() + 1];
+        Self {
+            bounds,
+            counts,
+            total: 0,
+            sum: 0,
+            max: 0,
+        }
+    }
+    
+    /// 创建指数分桶直方图：start, start*factor, ... 共count个桶
+    pub fn exponential(start: u64, factor: u64, count: usize) -> Self {
+        let bounds = std::iter::successors(Some(start), |b| b.checked_mul(factor))
+            .take(count)
+            .collect();
+        Self::new(bounds)
+    }
+    
+    /// 记录一个值
+    pub fn record(&mut self, value: u64) {
+        let index = self.bounds.partition_point(|&bound| bound < value);
+        self.counts[index] += 1;
+        self.total += 1;
+        self.sum = self.sum.saturating_add(value);
+        self.max = self.max.max(value);
+    }
+    
+    /// 获取各桶的(上界, 计数)，溢出桶的上界为None
+    pub fn buckets(&self) -> Vec<(Option<u64>, u64)> {
+        self.bounds
+            .iter()
+            .map(|&bound| Some(bound))
+            .chain(std::iter::once(None))
+            .zip(self.counts.iter().copied())
+            .collect()
+    }
+    
+    /// 记录总数
+    pub fn count(&self) -> u64 {
+        self.total
+    }
+    
+    /// 平均值
+    pub fn mean(&self) -> f64 {
+        if self.total > 0 {
+            self.sum as f64 / self.total as f64
+        } else {
+            0.0
+        }
+    }
+    
+    /// 最大值
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+    
+    /// 渲染为ASCII柱状图，每行一个桶，柱长按最大桶计数缩放到`width`以内
+    ///
+    /// 只输出首个到最后一个非空桶之间的范围，`unit`附加在桶上界之后
+    pub fn render_ascii(&self, width: usize, unit: &str) -> String {
+        use std::fmt::Write;
+        
+        let width = width.clamp(1, MAX_ASCII_BAR_WIDTH);
+        let (Some(first), Some(last)) = (
+            self.counts.iter().position(|&c| c > 0),
+            self.counts.iter().rposition(|&c| c > 0),
+        ) else {
+            return String::from("(no samples)\n");
+        };
+        let peak = self.counts[first..=last].iter().copied().max().unwrap_or(1);
+        
+        let mut output = String::with_capacity((last - first + 1) * (width + 32));
+        for index in first..=last {
+            let count = self.counts[index];
+            let bar = ((count as u128 * width as u128).div_ceil(peak as u128)) as usize;
+            let _ = match self.bounds.get(index) {
+                Some(bound) => write!(output, "<= {:>8}{:<3}|", bound, unit),
+                None => write!(output, " > {:>8}{:<3}|", self.bounds.last().copied().unwrap_or(0), unit),
+            };
+            output.extend(std::iter::repeat_n('#', bar));
+            let _ = writeln!(output, " {}", count);
+        }
+        output
+    }
+    
+    /// 近似百分位（返回所在桶的上界，溢出桶返回最大值）
+    pub fn percentile(&self, p: f64) -> Option<u64> {
+        if self.total == 0 {
+            return None;
+        }
+        
+        let target = ((p.clamp(0.0, 1.0) * self.total as f64).ceil() as u64).max(1);
+        let mut cumulative = 0;
+        for (bound, count) in self.buckets() {
+            cumulative += count;
+            if cumulative >= target {
+                return Some(bound.unwrap_or(self.max));
+            }
+        }
+        Some(self.max)
+    }
+}
+
+/// ASCII直方图柱的最大宽度
+const MAX_ASCII_BAR_WIDTH: usize = 80;
+
+/// 吞吐量计量的事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThroughputEvent {
+    /// 接收
+    Received,
+    /// 处理完成
+    Processed,
+    /// 丢弃
+    Dropped,
+}
+
+/// 滑动窗口内的吞吐量（条/秒）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ThroughputRates {
+    /// 每秒接收数
+    pub received_per_sec: f64,
+    /// 每秒处理数
+    pub processed_per_sec: f64,
+    /// 每秒丢弃数
+    pub dropped_per_sec: f64,
+}
+
+/// 单秒分桶
+#[derive(Debug, Clone, Copy, Default)]
+struct ThroughputBucket {
+    second: u64,
+    counts: [u64; 3],
+}
+
+/// 滑动窗口吞吐量计量，按秒分桶，只统计最近`window_secs`秒
+#[derive(Debug)]
+pub struct ThroughputMeter {
+    base: Instant,
+    buckets: Mutex<Vec<ThroughputBucket>>,
+}
+
+impl ThroughputMeter {
+    /// 创建窗口为`window_secs`秒的计量器（至少1秒）
+    pub fn new(window_secs: usize, now: Instant) -> Self {
+        Self {
+            base: now,
+            buckets: Mutex::new(vec![ThroughputBucket::default(); window_secs.max(1)]),
+        }
+    }
+    
+    /// 记录一次事件
+    pub fn record(&self, event: ThroughputEvent, now: Instant) {
+        let second = now.saturating_duration_since(self.base).as_secs();
+        let mut buckets = self.buckets.lock();
+        let len = buckets.len() as u64;
+        let bucket = &mut buckets[(second % len) as usize];
+        // 分桶属于更早的轮次时先清零再复用
+        if bucket.second != second {
+            *bucket = ThroughputBucket { second, counts: [0; 3] };
+        }
+        bucket.counts[event as usize] += 1;
+    }
+    
+    /// 计算截至`now`的窗口内平均吞吐量；运行不足一个窗口时按实际时长计算
+    pub fn rates(&self, now: Instant) -> ThroughputRates {
+        let elapsed = now.saturating_duration_since(self.base);
+        let current = elapsed.as_secs();
+        let buckets = self.buckets.lock();
+        let window = buckets.len() as u64;
+        let oldest = (current + 1).saturating_sub(window);
+        
+        let mut totals = [0u64; 3];
+        for bucket in buckets.iter().filter(|b| b.second >= oldest && b.second <= current) {
+            for (total, count) in totals.iter_mut().zip(bucket.counts) {
+                *total += count;
+            }
+        }
+        
+        // 窗口从最早分桶的起点到当前时刻，不足1秒按1秒计，避免刚启动时速率虚高
+        let span = (elapsed.as_secs_f64() - oldest as f64).max(1.0);
+        ThroughputRates {
+            received_per_sec: totals[ThroughputEvent::Received as usize] as f64 / span,
+            processed_per_sec: totals[ThroughputEvent::Processed as usize] as f64 / span,
+            dropped_per_sec: totals[ThroughputEvent::Dropped as usize] as f64 / span,
+        }
+    }
+}
+
+/// 吞吐量滑动窗口长度（秒）
+const THROUGHPUT_WINDOW_SECS: usize = 10;
+
+/// 单个流水线阶段的耗时计数，各提交方和处理任务记录时不加锁
+#[derive(Default)]
+struct AtomicStageStat {
+    count: AtomicU64,
+    total_nanos: AtomicU64,
+    max_nanos: AtomicU64,
+}
+
+impl AtomicStageStat {
+    fn record(&self, elapsed: Duration) {
+        let nanos = elapsed.as_nanos().min(u64::MAX as u128) as u64;
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.max_nanos.fetch_max(nanos, Ordering::Relaxed);
+    }
+    
+    /// 读取当前统计，三个计数分别读取，并发记录时可能相差一次
+    fn snapshot(&self) -> StageStat {
+        StageStat {
+            count: self.count.load(Ordering::Relaxed),
+            total: Duration::from_nanos(self.total_nanos.load(Ordering::Relaxed)),
+            max: Duration::from_nanos(self.max_nanos.load(Ordering::Relaxed)),
+        }
+    }
+    
+    fn reset(&self) {
+        self.count.store(0, Ordering::Relaxed);
+        self.total_nanos.store(0, Ordering::Relaxed);
+        self.max_nanos.store(0, Ordering::Relaxed);
+    }
+}
 
 /// 性能监控器
 pub struct PerformanceMonitor {
     stats: Arc<RwLock<ProcessingStats>>,
     last_report_time: Arc<RwLock<Instant>>,
     report_interval: Duration,
+    size_histogram: RwLock<Histogram>,
+    latency_histogram: RwLock<Histogram>,
+    clock: Arc<dyn Clock>,
+    latency_budgets: RwLock<HashMap<String, Duration>>,
+    slow_counts: RwLock<HashMap<String, u64>>,
+    stage_timings: [AtomicStageStat; PipelineStage::COUNT],
+    report_callback: RwLock<Option<ReportCallback>>,
+    ewma_alpha: f64,
+    throughput: ThroughputMeter,
+    health_hysteresis: HealthHysteresis,
+    health: Mutex<HealthTracker>,
+    metrics_sink: RwLock<Arc<dyn MetricsSink>>,
+    warmup: Duration,
+    warmup_until: RwLock<Option<Instant>>,
+    warning_sender: RwLock<Option<mpsc::Sender<Warning>>>,
+    log_warnings: AtomicBool,
 }
 
 impl PerformanceMonitor {
@@ -18,6 +313,123 @@ impl PerformanceMonitor {
             stats: Arc::new(RwLock::new(ProcessingStats::new())),
             last_report_time: Arc::new(RwLock::new(Instant::now())),
             report_interval,
+            // 64B ~ 1MB
+            size_histogram: RwLock::new(Histogram::exponential(64, 2, 15)),
+            // 50μs ~ 102ms
+            latency_histogram: RwLock::new(Histogram::exponential(50, 2, 12)),
+            clock: Arc::new(SystemClock),
+            latency_budgets: RwLock::new(HashMap::new()),
+            slow_counts: RwLock::new(HashMap::new()),
+            stage_timings: Default::default(),
+            report_callback: RwLock::new(None),
+            ewma_alpha: ProcessingStats::DEFAULT_EWMA_ALPHA,
+            throughput: ThroughputMeter::new(THROUGHPUT_WINDOW_SECS, Instant::now()),
+            health_hysteresis: HealthHysteresis::default(),
+            health: Mutex::new(HealthTracker::new(Instant::now())),
+            metrics_sink: RwLock::new(Arc::new(NoopMetricsSink)),
+            warmup: Duration::ZERO,
+            warmup_until: RwLock::new(None),
+            warning_sender: RwLock::new(None),
+            log_warnings: AtomicBool::new(true),
+        }
+    }
+    
+    /// 使用指定时间源
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.last_report_time = Arc::new(RwLock::new(clock.now()));
+        self.throughput = ThroughputMeter::new(THROUGHPUT_WINDOW_SECS, clock.now());
+        self.health = Mutex::new(HealthTracker::new(clock.now()));
+        self.clock = clock;
+        self
+    }
+    
+    /// 设置健康状态的滞回参数
+    pub fn with_health_hysteresis(mut self, hysteresis: HealthHysteresis) -> Self {
+        self.health_hysteresis = hysteresis;
+        self
+    }
+    
+    /// 设置预热时长：`begin_warmup`后的这段时间内不输出性能警告，健康状态视为`Healthy`
+    pub fn with_warmup(mut self, warmup: Duration) -> Self {
+        self.warmup = warmup;
+        self
+    }
+    
+    /// 开始预热期（处理器启动时调用），未设置预热时长时不生效
+    pub fn begin_warmup(&self) {
+        if !self.warmup.is_zero() {
+            *self.warmup_until.write() = Some(self.clock.now() + self.warmup);
+        }
+    }
+    
+    /// 是否处于预热期
+    pub fn is_warming_up(&self) -> bool {
+        self.warmup_until.read().is_some_and(|until| self.clock.now() < until)
+    }
+    
+    /// 设置平均处理时间的平滑系数（0~1]，越大越敏感，默认0.1
+    pub fn with_ewma_alpha(mut self, alpha: f64) -> Self {
+        self.ewma_alpha = if alpha > 0.0 { alpha.min(1.0) } else { ProcessingStats::DEFAULT_EWMA_ALPHA };
+        self
+    }
+    
+    /// 获取最近10秒的滑动窗口吞吐量
+    pub fn get_throughput(&self) -> ThroughputRates {
+        self.throughput.rates(self.clock.now())
+    }
+    
+    /// 设置性能报告回调，用于推送到statsd、CloudWatch等外部系统
+    ///
+    /// 在tokio运行时中回调以阻塞任务执行，不阻塞统计记录路径；运行时之外同步调用
+    pub fn set_report_callback(&self, callback: ReportCallback) {
+        *self.report_callback.write() = Some(callback);
+    }
+    
+    /// 清除性能报告回调
+    pub fn clear_report_callback(&self) {
+        *self.report_callback.write() = None;
+    }
+    
+    /// 设置指标输出端，之后记录的统计同时输出到该后端
+    pub fn set_metrics_sink(&self, sink: Arc<dyn MetricsSink>) {
+        *self.metrics_sink.write() = sink;
+    }
+    
+    /// 恢复为不输出指标
+    pub fn clear_metrics_sink(&self) {
+        *self.metrics_sink.write() = Arc::new(NoopMetricsSink);
+    }
+    
+    /// 设置警告通道，之后的性能警告同时发送到该通道；通道满时丢弃警告，不阻塞记录路径
+    pub fn set_warning_sender(&self, sender: mpsc::Sender<Warning>) {
+        *self.warning_sender.write() = Some(sender);
+    }
+    
+    /// 清除警告通道
+    pub fn clear_warning_sender(&self) {
+        *self.warning_sender.write() = None;
+    }
+    
+    /// 设置是否将性能警告写入日志，关闭后警告只发送到警告通道
+    pub fn set_warning_logs(&self, enabled: bool) {
+        self.log_warnings.store(enabled, Ordering::Relaxed);
+    }
+    
+    /// 输出性能警告：按设置写入日志，并发送到警告通道；预热期内流量尚未稳定，不输出
+    fn emit_warning(&self, kind: WarningKind, detail: String) {
+        if self.is_warming_up() {
+            return;
+        }
+        if self.log_warnings.load(Ordering::Relaxed) {
+            warn!("{}", detail);
+        }
+        self.send_warning(kind, detail);
+    }
+    
+    /// 只发送到警告通道
+    fn send_warning(&self, kind: WarningKind, detail: String) {
+        if let Some(sender) = self.warning_sender.read().as_ref() {
+            let _ = sender.try_send(Warning { kind, detail, timestamp: self.clock.unix_timestamp() });
         }
     }
     
@@ -28,51 +440,193 @@ impl PerformanceMonitor {
     
     /// 记录接收到的消息
     pub fn record_received(&self) {
-        let mut stats = self.stats.write();
-        stats.increment_received();
+        let in_flight = {
+            let mut stats = self.stats.write();
+            stats.increment_received();
+            stats.in_flight()
+        };
+        self.throughput.record(ThroughputEvent::Received, self.clock.now());
+        let sink = self.metrics_sink.read();
+        sink.counter(metrics::MESSAGES_RECEIVED, 1);
+        sink.gauge(metrics::IN_FLIGHT, in_flight as f64);
+        drop(sink);
         
-        // 检查是否需要报告
+        // 检查是否需要报告（需先释放写锁，报告时会再次读取统计）
         self.check_and_report();
     }
     
     /// 记录处理完成的消息
     pub fn record_processed(&self, processing_time: Duration) {
-        let mut stats = self.stats.write();
-        stats.increment_processed();
-        stats.update_processing_time(processing_time);
+        self.update_processed(processing_time);
         
         // 如果处理时间过长，记录警告
         if processing_time > Duration::from_millis(10) {
-            warn!(
-                "Slow message processing detected: {:.2}ms", 
-                processing_time.as_secs_f64() * 1000.0
+            self.emit_warning(
+                WarningKind::SlowProcessing,
+                format!("Slow message processing detected: {:.2}ms", processing_time.as_secs_f64() * 1000.0),
+            );
+        }
+    }
+    
+    /// 记录试运行模式下完成处理（未调用回调）的消息
+    pub fn record_dry_run(&self, processing_time: Duration) {
+        self.stats.write().increment_dry_run();
+        self.update_processed(processing_time);
+    }
+    
+    /// 按优先级记录处理完成的消息
+    pub fn record_priority_processed(&self, priority: MessagePriority) {
+        self.stats.write().increment_priority_processed(priority);
+    }
+    
+    /// 记录某服务处理完成的消息，配置了延迟预算的服务按预算判断慢处理
+    pub fn record_service_processed(&self, service: &str, processing_time: Duration) {
+        let budget = self.latency_budgets.read().get(service).copied();
+        let Some(budget) = budget else {
+            self.record_processed(processing_time);
+            return;
+        };
+        
+        self.update_processed(processing_time);
+        
+        if processing_time > budget {
+            *self.slow_counts.write().entry(service.to_string()).or_insert(0) += 1;
+            self.metrics_sink.read().counter(metrics::SLOW_MESSAGES, 1);
+            self.emit_warning(
+                WarningKind::LatencyBudgetExceeded,
+                format!(
+                    "Service {} exceeded latency budget: {:.2}ms > {:.2}ms",
+                    service,
+                    processing_time.as_secs_f64() * 1000.0,
+                    budget.as_secs_f64() * 1000.0
+                ),
             );
         }
     }
     
+    /// 更新处理计数、平均耗时和延迟直方图
+    fn update_processed(&self, processing_time: Duration) {
+        let in_flight = {
+            let mut stats = self.stats.write();
+            stats.increment_processed();
+            stats.update_processing_time_with_alpha(processing_time, self.ewma_alpha);
+            stats.in_flight()
+        };
+        self.throughput.record(ThroughputEvent::Processed, self.clock.now());
+        self.latency_histogram.write().record(processing_time.as_micros() as u64);
+        
+        let sink = self.metrics_sink.read();
+        sink.counter(metrics::MESSAGES_PROCESSED, 1);
+        sink.gauge(metrics::IN_FLIGHT, in_flight as f64);
+        sink.histogram(metrics::PROCESSING_TIME_US, processing_time.as_secs_f64() * 1_000_000.0);
+    }
+    
+    /// 获取处理延迟直方图（微秒）
+    pub fn get_latency_histogram(&self) -> Histogram {
+        self.latency_histogram.read().clone()
+    }
+    
+    /// 将处理延迟直方图渲染为ASCII柱状图，便于命令行诊断
+    pub fn render_latency_histogram(&self) -> String {
+        self.latency_histogram.read().render_ascii(40, "us")
+    }
+    
+    /// 设置服务的处理延迟预算
+    pub fn set_latency_budget(&self, service: &str, budget: Duration) {
+        self.latency_budgets.write().insert(service.to_string(), budget);
+    }
+    
+    /// 获取各服务的处理延迟预算
+    pub fn latency_budgets(&self) -> HashMap<String, Duration> {
+        self.latency_budgets.read().clone()
+    }
+    
+    /// 获取各服务超出延迟预算的次数
+    pub fn get_slow_counts(&self) -> HashMap<String, u64> {
+        self.slow_counts.read().clone()
+    }
+    
+    /// 记录流水线阶段耗时
+    pub fn record_stage(&self, stage: PipelineStage, elapsed: Duration) {
+        self.stage_timings[stage as usize].record(elapsed);
+    }
+    
+    /// 获取各流水线阶段耗时汇总
+    pub fn get_stage_timings(&self) -> StageTimings {
+        PipelineStage::all()
+            .into_iter()
+            .map(|stage| (stage, self.stage_timings[stage as usize].snapshot()))
+            .collect()
+    }
+    
     /// 记录丢弃的消息
     pub fn record_dropped(&self, reason: &str) {
-        let mut stats = self.stats.write();
-        stats.increment_dropped();
+        let in_flight = {
+            let mut stats = self.stats.write();
+            stats.increment_dropped();
+            stats.in_flight()
+        };
+        self.throughput.record(ThroughputEvent::Dropped, self.clock.now());
+        let sink = self.metrics_sink.read();
+        sink.counter(metrics::MESSAGES_DROPPED, 1);
+        sink.gauge(metrics::IN_FLIGHT, in_flight as f64);
         
         warn!("Message dropped: {}", reason);
     }
     
+    /// 当前已接收但尚未处理完成或丢弃的消息数
+    ///
+    /// 不依赖队列深度采样，可作为实时积压指标
+    pub fn in_flight(&self) -> u64 {
+        self.stats.read().in_flight()
+    }
+    
+    /// 记录JSON解析失败的消息，同时计为丢弃
+    pub fn record_parse_error(&self) {
+        self.stats.write().increment_parse_errors();
+        self.metrics_sink.read().counter(metrics::PARSE_ERRORS, 1);
+        self.record_dropped("parse error");
+    }
+    
+    /// 记录解析成功但字段缺失或校验失败的消息，同时计为丢弃
+    pub fn record_invalid(&self, reason: &str) {
+        self.stats.write().increment_validation_errors();
+        self.metrics_sink.read().counter(metrics::VALIDATION_ERRORS, 1);
+        self.record_dropped(&format!("invalid message ({})", reason));
+    }
+    
+    /// 记录使用到达时间补全时间戳的消息
+    pub fn record_backfilled(&self) {
+        self.stats.write().increment_backfilled();
+        self.metrics_sink.read().counter(metrics::MESSAGES_BACKFILLED, 1);
+    }
+    
+    /// 记录消息大小（字节）
+    pub fn record_message_size(&self, bytes: usize) {
+        self.size_histogram.write().record(bytes as u64);
+        self.metrics_sink.read().histogram(metrics::MESSAGE_SIZE_BYTES, bytes as f64);
+    }
+    
+    /// 获取消息大小直方图
+    pub fn get_size_histogram(&self) -> Histogram {
+        self.size_histogram.read().clone()
+    }
+    
     /// 更新队列大小
     pub fn update_queue_size(&self, size: usize) {
-        let mut stats = self.stats.write();
-        stats.update_queue_size(size);
+        self.stats.write().update_queue_size(size);
+        self.metrics_sink.read().gauge(metrics::QUEUE_SIZE, size as f64);
         
         // 如果队列过大，记录警告
         if size > 800 {
-            warn!("Large queue size detected: {}", size);
+            self.emit_warning(WarningKind::LargeQueue, format!("Large queue size detected: {}", size));
         }
     }
     
     /// 检查并报告性能统计
     fn check_and_report(&self) {
         let mut last_report = self.last_report_time.write();
-        let now = Instant::now();
+        let now = self.clock.now();
         
         if now.duration_since(*last_report) >= self.report_interval {
             let stats = self.stats.read();
@@ -93,6 +647,20 @@ impl PerformanceMonitor {
             // 检查性能警告
             self.check_performance_warnings(&stats);
             
+            if let Some(callback) = self.report_callback.read().clone() {
+                let snapshot = stats.clone();
+                match tokio::runtime::Handle::try_current() {
+                    Ok(handle) => {
+                        handle.spawn_blocking(move || callback(&snapshot));
+                    }
+                    Err(_) => callback(&snapshot),
+                }
+            }
+            
+            // 每个报告周期记录一次健康读数
+            drop(stats);
+            self.observe_health();
+            
             *last_report = now;
         }
     }
@@ -101,66 +669,133 @@ impl PerformanceMonitor {
     fn check_performance_warnings(&self, stats: &ProcessingStats) {
         // 检查丢弃率
         if stats.get_drop_rate() > 0.05 {  // 5%
-            warn!(
-                "High drop rate detected: {:.2}%", 
-                stats.get_drop_rate() * 100.0
+            self.emit_warning(
+                WarningKind::HighDropRate,
+                format!("High drop rate detected: {:.2}%", stats.get_drop_rate() * 100.0),
             );
         }
         
         // 检查平均处理时间
         if stats.avg_processing_time_us > 5000 {  // 5ms
-            warn!(
-                "High average processing time: {}μs", 
-                stats.avg_processing_time_us
+            self.emit_warning(
+                WarningKind::HighProcessingTime,
+                format!("High average processing time: {}μs", stats.avg_processing_time_us),
             );
         }
         
         // 检查队列积压
         if stats.queue_size > 500 {
-            warn!("Large queue backlog: {}", stats.queue_size);
+            self.emit_warning(WarningKind::QueueBacklog, format!("Large queue backlog: {}", stats.queue_size));
         }
         
         // 检查处理速率
         let processing_rate = stats.get_processing_rate();
         if processing_rate < 100.0 && stats.messages_received > 1000 {
-            warn!(
-                "Low processing rate: {:.1} msg/s", 
-                processing_rate
+            self.emit_warning(
+                WarningKind::LowProcessingRate,
+                format!("Low processing rate: {:.1} msg/s", processing_rate),
             );
         }
     }
     
+    /// 累加上次运行保存的计数
+    pub fn restore_stats(&self, previous: &ProcessingStats) {
+        self.stats.write().restore_from(previous);
+        info!(
+            "Restored stats from previous run: {} received, {} processed",
+            previous.messages_received,
+            previous.messages_processed
+        );
+    }
+    
     /// 重置统计信息
     pub fn reset_stats(&self) {
         let mut stats = self.stats.write();
         *stats = ProcessingStats::new();
+        self.slow_counts.write().clear();
+        self.stage_timings.iter().for_each(AtomicStageStat::reset);
         
         let mut last_report = self.last_report_time.write();
-        *last_report = Instant::now();
+        *last_report = self.clock.now();
         
         info!("Performance statistics reset");
     }
     
-    /// 获取性能健康状态
+    /// 原子地获取统计快照并重置，快照与重置在同一把写锁内完成
+    pub fn snapshot_and_reset(&self) -> ProcessingStats {
+        let snapshot = {
+            let mut stats = self.stats.write();
+            std::mem::replace(&mut *stats, ProcessingStats::new())
+        };
+        
+        *self.last_report_time.write() = self.clock.now();
+        
+        debug!("Performance statistics snapshotted and reset");
+        snapshot
+    }
+    
+    /// 获取当前统计对应的瞬时健康状态（不经滞回处理），预热期内为`Healthy`
     pub fn get_health_status(&self) -> HealthStatus {
-        let stats = self.stats.read();
+        if self.is_warming_up() {
+            return HealthStatus::Healthy;
+        }
+        HealthStatus::classify(&self.stats.read(), 1.0)
+    }
+    
+    /// 记录一次健康读数并返回经滞回处理的健康状态
+    ///
+    /// 连续`escalate_after`次读数更差才切换到更差的状态；读数低于按`recovery_ratio`缩放的阈值才恢复
+    pub fn observe_health(&self) -> HealthState {
+        // 预热期内的读数不参与状态切换
+        if self.is_warming_up() {
+            return self.get_health_state();
+        }
         
-        let drop_rate = stats.get_drop_rate();
-        let avg_time_ms = stats.avg_processing_time_us as f64 / 1000.0;
-        let queue_size = stats.queue_size;
+        let (reading, recovered) = {
+            let stats = self.stats.read();
+            (
+                HealthStatus::classify(&stats, 1.0),
+                HealthStatus::classify(&stats, self.health_hysteresis.recovery_ratio),
+            )
+        };
         
-        if drop_rate > 0.1 || avg_time_ms > 10.0 || queue_size > 800 {
-            HealthStatus::Critical
-        } else if drop_rate > 0.05 || avg_time_ms > 5.0 || queue_size > 500 {
-            HealthStatus::Warning
+        let mut tracker = self.health.lock();
+        let current = tracker.state.status;
+        let next = if reading > current {
+            tracker.pending_worse += 1;
+            (tracker.pending_worse >= self.health_hysteresis.escalate_after).then_some(reading)
         } else {
-            HealthStatus::Healthy
+            tracker.pending_worse = 0;
+            // 恢复阈值更低，recovered不会好于reading
+            (recovered < current).then_some(recovered)
+        };
+        
+        if let Some(next) = next {
+            tracker.transition(next, self.clock.now());
+            // 状态切换已由tracker写入日志，这里只发送到警告通道
+            if next > current {
+                self.send_warning(
+                    WarningKind::HealthDegraded,
+                    format!("Health status changed: {} -> {}", current.as_str(), next.as_str()),
+                );
+            }
         }
+        tracker.state
+    }
+    
+    /// 获取经滞回处理的健康状态及其开始时间
+    pub fn get_health_state(&self) -> HealthState {
+        self.health.lock().state
+    }
+    
+    /// 获取最近的健康状态切换记录（按时间顺序）
+    pub fn get_health_transitions(&self) -> Vec<HealthTransition> {
+        self.health.lock().transitions.iter().copied().collect()
     }
 }
 
-/// 健康状态枚举
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// 健康状态枚举，按严重程度递增排序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum HealthStatus {
     Healthy,
     Warning,
@@ -176,11 +811,99 @@ impl HealthStatus {
             HealthStatus::Critical => "critical",
         }
     }
+    
+    /// 按阈值判定健康状态，`scale`用于缩放阈值（恢复判定使用更低的阈值）
+    fn classify(stats: &ProcessingStats, scale: f64) -> Self {
+        let drop_rate = stats.get_drop_rate();
+        let avg_time_ms = stats.avg_processing_time_us as f64 / 1000.0;
+        let queue_size = stats.queue_size as f64;
+        
+        if drop_rate > 0.1 * scale || avg_time_ms > 10.0 * scale || queue_size > 800.0 * scale {
+            HealthStatus::Critical
+        } else if drop_rate > 0.05 * scale || avg_time_ms > 5.0 * scale || queue_size > 500.0 * scale {
+            HealthStatus::Warning
+        } else {
+            HealthStatus::Healthy
+        }
+    }
+}
+
+/// 健康状态滞回配置，避免读数在阈值附近时状态反复切换
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthHysteresis {
+    /// 连续多少次读数更差才切换到更差的状态
+    pub escalate_after: u32,
+    /// 恢复阈值相对进入阈值的比例（0~1]
+    pub recovery_ratio: f64,
+}
+
+impl Default for HealthHysteresis {
+    fn default() -> Self {
+        Self {
+            escalate_after: 3,
+            recovery_ratio: 0.8,
+        }
+    }
+}
+
+/// 经滞回处理的健康状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthState {
+    pub status: HealthStatus,
+    /// 进入该状态的时间
+    pub since: Instant,
+}
+
+/// 健康状态切换记录
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthTransition {
+    pub from: HealthStatus,
+    pub to: HealthStatus,
+    pub at: Instant,
+}
+
+/// 保留的健康状态切换记录数
+const MAX_HEALTH_TRANSITIONS: usize = 32;
+
+/// 健康状态滞回跟踪
+#[derive(Debug)]
+struct HealthTracker {
+    state: HealthState,
+    // 连续更差读数的次数
+    pending_worse: u32,
+    transitions: VecDeque<HealthTransition>,
+}
+
+impl HealthTracker {
+    fn new(now: Instant) -> Self {
+        Self {
+            state: HealthState { status: HealthStatus::Healthy, since: now },
+            pending_worse: 0,
+            transitions: VecDeque::with_capacity(MAX_HEALTH_TRANSITIONS),
+        }
+    }
+    
+    fn transition(&mut self, to: HealthStatus, now: Instant) {
+        let from = self.state.status;
+        if to > from {
+            warn!("Health status changed: {} -> {}", from.as_str(), to.as_str());
+        } else {
+            info!("Health status changed: {} -> {}", from.as_str(), to.as_str());
+        }
+        
+        self.state = HealthState { status: to, since: now };
+        self.pending_worse = 0;
+        if self.transitions.len() == MAX_HEALTH_TRANSITIONS {
+            self.transitions.pop_front();
+        }
+        self.transitions.push_back(HealthTransition { from, to, at: now });
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::MockClock;
     use std::time::Duration;
     
     #[test]
@@ -199,6 +922,53 @@ mod tests {
         assert!(stats.avg_processing_time_us > 0);
     }
     
+    #[derive(Debug, Default)]
+    struct RecordingSink {
+        counters: Mutex<HashMap<String, u64>>,
+        gauges: Mutex<HashMap<String, f64>>,
+        histograms: Mutex<Vec<(String, f64)>>,
+    }
+    
+    impl MetricsSink for RecordingSink {
+        fn counter(&self, name: &str, value: u64) {
+            *self.counters.lock().entry(name.to_string()).or_insert(0) += value;
+        }
+        
+        fn gauge(&self, name: &str, value: f64) {
+            self.gauges.lock().insert(name.to_string(), value);
+        }
+        
+        fn histogram(&self, name: &str, value: f64) {
+            self.histograms.lock().push((name.to_string(), value));
+        }
+    }
+    
+    #[test]
+    fn test_metrics_sink_receives_records() {
+        let monitor = PerformanceMonitor::new(Duration::from_secs(60));
+        let sink = Arc::new(RecordingSink::default());
+        monitor.set_metrics_sink(sink.clone());
+        
+        monitor.record_received();
+        monitor.record_received();
+        monitor.record_processed(Duration::from_micros(250));
+        monitor.record_dropped("test");
+        monitor.update_queue_size(7);
+        
+        let counters = sink.counters.lock().clone();
+        assert_eq!(counters.get(metrics::MESSAGES_RECEIVED), Some(&2));
+        assert_eq!(counters.get(metrics::MESSAGES_PROCESSED), Some(&1));
+        assert_eq!(counters.get(metrics::MESSAGES_DROPPED), Some(&1));
+        assert_eq!(sink.gauges.lock().get(metrics::QUEUE_SIZE), Some(&7.0));
+        assert_eq!(sink.gauges.lock().get(metrics::IN_FLIGHT), Some(&0.0));
+        assert_eq!(monitor.in_flight(), 0);
+        assert_eq!(*sink.histograms.lock(), vec![(metrics::PROCESSING_TIME_US.to_string(), 250.0)]);
+        
+        monitor.clear_metrics_sink();
+        monitor.record_received();
+        assert_eq!(sink.counters.lock().get(metrics::MESSAGES_RECEIVED), Some(&2));
+    }
+    
     #[test]
     fn test_health_status() {
         let monitor = PerformanceMonitor::new(Duration::from_secs(1));
@@ -214,4 +984,261 @@ mod tests {
         // 应该变为Critical状态
         assert_eq!(monitor.get_health_status(), HealthStatus::Critical);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_warmup_reports_healthy() {
+        let clock = Arc::new(MockClock::new());
+        let monitor = PerformanceMonitor::new(Duration::from_secs(1))
+            .with_clock(clock.clone())
+            .with_warmup(Duration::from_secs(5));
+        monitor.begin_warmup();
+        monitor.update_queue_size(900);
+        
+        assert!(monitor.is_warming_up());
+        assert_eq!(monitor.get_health_status(), HealthStatus::Healthy);
+        for _ in 0..5 {
+            monitor.observe_health();
+        }
+        assert_eq!(monitor.get_health_state().status, HealthStatus::Healthy);
+        
+        clock.advance(Duration::from_secs(5));
+        assert!(!monitor.is_warming_up());
+        assert_eq!(monitor.get_health_status(), HealthStatus::Critical);
+    }
+    
+    #[test]
+    fn test_health_hysteresis() {
+        let clock = Arc::new(MockClock::new());
+        let monitor = PerformanceMonitor::new(Duration::from_secs(1)).with_clock(clock.clone());
+        let observe = |queue_size| {
+            monitor.update_queue_size(queue_size);
+            monitor.observe_health().status
+        };
+        
+        // 连续3次超过阈值才升级
+        assert_eq!(observe(900), HealthStatus::Healthy);
+        assert_eq!(observe(900), HealthStatus::Healthy);
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(observe(900), HealthStatus::Critical);
+        assert_eq!(monitor.get_health_state().since, clock.now());
+        
+        // 低于进入阈值但高于恢复阈值时保持
+        assert_eq!(observe(700), HealthStatus::Critical);
+        assert_eq!(observe(600), HealthStatus::Warning);
+        assert_eq!(observe(450), HealthStatus::Warning);
+        assert_eq!(observe(100), HealthStatus::Healthy);
+        
+        let path: Vec<_> = monitor.get_health_transitions().iter().map(|t| (t.from, t.to)).collect();
+        assert_eq!(
+            path,
+            [
+                (HealthStatus::Healthy, HealthStatus::Critical),
+                (HealthStatus::Critical, HealthStatus::Warning),
+                (HealthStatus::Warning, HealthStatus::Healthy),
+            ]
+        );
+    }
+    
+    #[test]
+    fn test_snapshot_and_reset() {
+        let monitor = PerformanceMonitor::new(Duration::from_secs(1));
+        
+        monitor.record_received();
+        monitor.record_received();
+        monitor.record_processed(Duration::from_micros(500));
+        monitor.record_dropped("test");
+        
+        let snapshot = monitor.snapshot_and_reset();
+        assert_eq!(snapshot.messages_received, 2);
+        assert_eq!(snapshot.messages_processed, 1);
+        assert_eq!(snapshot.messages_dropped, 1);
+        
+        let stats = monitor.get_stats();
+        assert_eq!(stats.messages_received, 0);
+        assert_eq!(stats.messages_processed, 0);
+        assert_eq!(stats.messages_dropped, 0);
+    }
+    
+    #[test]
+    fn test_record_received_reports_without_deadlock() {
+        let monitor = PerformanceMonitor::new(Duration::ZERO);
+        
+        // 报告间隔为0时每次都会触发报告
+        monitor.record_received();
+        monitor.record_received();
+        assert_eq!(monitor.get_stats().messages_received, 2);
+    }
+    
+    #[test]
+    fn test_histogram() {
+        let mut histogram = Histogram::new(vec![10, 100, 1000]);
+        for value in [5, 10, 50, 500, 5000] {
+            histogram.record(value);
+        }
+        
+        assert_eq!(
+            histogram.buckets(),
+            vec![(Some(10), 2), (Some(100), 1), (Some(1000), 1), (None, 1)]
+        );
+        assert_eq!(histogram.count(), 5);
+        assert_eq!(histogram.max(), 5000);
+        assert_eq!(histogram.mean(), 1113.0);
+        assert_eq!(histogram.percentile(0.5), Some(100));
+        assert_eq!(histogram.percentile(1.0), Some(5000));
+    }
+    
+    #[test]
+    fn test_message_size_histogram() {
+        let monitor = PerformanceMonitor::new(Duration::from_secs(1));
+        monitor.record_message_size(100);
+        monitor.record_message_size(100_000);
+        
+        let histogram = monitor.get_size_histogram();
+        assert_eq!(histogram.count(), 2);
+        assert_eq!(histogram.max(), 100_000);
+    }
+    
+    #[test]
+    fn test_service_latency_budget() {
+        let monitor = PerformanceMonitor::new(Duration::from_secs(60));
+        monitor.set_latency_budget("traj", Duration::from_micros(200));
+        monitor.set_latency_budget("route", Duration::from_millis(50));
+        
+        monitor.record_service_processed("traj", Duration::from_micros(500));
+        monitor.record_service_processed("route", Duration::from_millis(20));
+        monitor.record_service_processed("tracking", Duration::from_millis(20));
+        
+        assert_eq!(monitor.get_stats().messages_processed, 3);
+        assert_eq!(monitor.get_slow_counts(), HashMap::from([("traj".to_string(), 1)]));
+    }
+    
+    #[test]
+    fn test_warnings_sent_to_channel() {
+        let monitor = PerformanceMonitor::new(Duration::from_secs(60));
+        let (tx, mut rx) = mpsc::channel(8);
+        monitor.set_warning_sender(tx);
+        monitor.set_warning_logs(false);
+        monitor.set_latency_budget("traj", Duration::from_micros(200));
+        
+        monitor.record_service_processed("traj", Duration::from_micros(500));
+        monitor.record_processed(Duration::from_millis(20));
+        monitor.update_queue_size(10);
+        
+        let warning = rx.try_recv().unwrap();
+        assert_eq!(warning.kind, WarningKind::LatencyBudgetExceeded);
+        assert_eq!(warning.detail, "Service traj exceeded latency budget: 0.50ms > 0.20ms");
+        assert!(warning.timestamp > 0.0);
+        assert_eq!(rx.try_recv().unwrap().kind, WarningKind::SlowProcessing);
+        assert!(rx.try_recv().is_err());
+    }
+    
+    #[test]
+    fn test_no_per_message_warnings_during_warmup() {
+        let clock = Arc::new(MockClock::new());
+        let monitor = PerformanceMonitor::new(Duration::from_secs(60))
+            .with_clock(clock.clone())
+            .with_warmup(Duration::from_secs(5));
+        let (tx, mut rx) = mpsc::channel(8);
+        monitor.set_warning_sender(tx);
+        monitor.set_warning_logs(false);
+        monitor.set_latency_budget("traj", Duration::from_micros(200));
+        monitor.begin_warmup();
+        
+        monitor.record_service_processed("traj", Duration::from_micros(500));
+        monitor.record_processed(Duration::from_millis(20));
+        monitor.update_queue_size(900);
+        assert!(rx.try_recv().is_err());
+        // 预热期内仍计入慢处理统计
+        assert_eq!(monitor.get_slow_counts(), HashMap::from([("traj".to_string(), 1)]));
+        
+        clock.advance(Duration::from_secs(5));
+        monitor.update_queue_size(900);
+        assert_eq!(rx.try_recv().unwrap().kind, WarningKind::LargeQueue);
+    }
+    
+    #[test]
+    fn test_render_latency_histogram() {
+        let monitor = PerformanceMonitor::new(Duration::from_secs(60));
+        assert_eq!(monitor.render_latency_histogram(), "(no samples)\n");
+        
+        for _ in 0..4 {
+            monitor.record_processed(Duration::from_micros(80));
+        }
+        monitor.record_processed(Duration::from_micros(300));
+        
+        let rendered = monitor.render_latency_histogram();
+        let lines: Vec<&str> = rendered.lines().collect();
+        // 100us、200us（空）、400us三个桶
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("<=      100us |"));
+        assert!(lines[0].ends_with(&format!("{} 4", "#".repeat(40))));
+        assert!(lines[1].ends_with("| 0"));
+        assert!(lines[2].ends_with(&format!("{} 1", "#".repeat(10))));
+    }
+    
+    #[test]
+    fn test_report_callback() {
+        let clock = Arc::new(MockClock::new());
+        let monitor = PerformanceMonitor::new(Duration::from_secs(10)).with_clock(clock.clone());
+        let reports = Arc::new(RwLock::new(Vec::new()));
+        let sink = reports.clone();
+        monitor.set_report_callback(Arc::new(move |stats| {
+            sink.write().push(stats.messages_received);
+        }));
+        
+        monitor.record_received();
+        assert!(reports.read().is_empty());
+        
+        clock.advance(Duration::from_secs(10));
+        monitor.record_received();
+        assert_eq!(*reports.read(), vec![2]);
+    }
+    
+    #[test]
+    fn test_ewma_alpha_converges() {
+        let monitor = PerformanceMonitor::new(Duration::from_secs(60)).with_ewma_alpha(0.5);
+        monitor.record_processed(Duration::from_micros(100));
+        monitor.record_processed(Duration::from_micros(300));
+        // 100 + 0.5 * (300 - 100)
+        assert_eq!(monitor.get_stats().avg_processing_time_us, 200);
+        
+        // 稳定输入下收敛到输入值，不因取整停在下方
+        for _ in 0..50 {
+            monitor.record_processed(Duration::from_micros(1009));
+        }
+        assert_eq!(monitor.get_stats().avg_processing_time_us, 1009);
+        
+        let mut stats = ProcessingStats::new();
+        stats.update_processing_time(Duration::from_micros(10));
+        for _ in 0..200 {
+            stats.update_processing_time(Duration::from_micros(19));
+        }
+        // 旧实现 (avg * 9 + 19) / 10 会停在10
+        assert_eq!(stats.avg_processing_time_us, 19);
+    }
+    
+    #[test]
+    fn test_throughput_sliding_window() {
+        let clock = Arc::new(MockClock::new());
+        let monitor = PerformanceMonitor::new(Duration::from_secs(60)).with_clock(clock.clone());
+        
+        // 前2秒每秒接收100条
+        for _ in 0..2 {
+            for _ in 0..100 {
+                monitor.record_received();
+            }
+            clock.advance(Duration::from_secs(1));
+        }
+        let rates = monitor.get_throughput();
+        assert!((rates.received_per_sec - 100.0).abs() < 1e-9);
+        assert_eq!(rates.dropped_per_sec, 0.0);
+        
+        // 超出窗口后早期的计数不再计入
+        clock.advance(Duration::from_secs(20));
+        monitor.record_processed(Duration::from_micros(10));
+        let rates = monitor.get_throughput();
+        assert_eq!(rates.received_per_sec, 0.0);
+        // 窗口覆盖第13~22秒的分桶，起点到当前时刻共9秒
+        assert!((rates.processed_per_sec - 1.0 / 9.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::error::Result;
+use crate::nanomsg_client::NanomsgStats;
+use crate::runtime;
+use crate::types::ProcessingStats;
+
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+/// 落盘的统计快照，用于进程异常退出后的事后分析
+///
+/// 时间点字段（如`last_update`）不落盘
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatsSnapshot {
+    /// 写入时的Unix时间戳（秒）
+    pub saved_at: f64,
+    /// 处理器统计
+    pub processing: ProcessingStats,
+    /// 客户端统计（只持久化处理器时为None）
+    pub nanomsg: Option<NanomsgStats>,
+}
+
+impl StatsSnapshot {
+    /// 以当前时间创建快照
+    pub fn new(processing: ProcessingStats, nanomsg: Option<NanomsgStats>) -> Self {
+        Self {
+            saved_at: chrono::Utc::now().timestamp_micros() as f64 / 1_000_000.0,
+            processing,
+            nanomsg,
+        }
+    }
+
+    /// 原子地写入文件：先写临时文件再重命名，崩溃时不会留下写了一半的快照
+    pub fn write_atomic(&self, path: &Path) -> Result<()> {
+        let mut tmp_path = OsString::from(path.as_os_str());
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&serde_json::to_vec_pretty(self)?)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// 读取上次保存的快照，文件不存在时返回None
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        match fs::read(path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// 按间隔把`collect`返回的快照写入`path`，`token`取消时写入最后一次快照后退出
+///
+/// 只持久化处理器统计时可直接使用；同时持久化客户端统计见`NanomsgClient::persist_stats`
+pub fn persist_stats<F>(
+    path: impl Into<PathBuf>,
+    interval: Duration,
+    token: CancellationToken,
+    collect: F,
+) -> tokio::task::JoinHandle<()>
+where
+    F: Fn() -> StatsSnapshot + Send + 'static,
+{
+    spawn_persist_task(None, path.into(), interval, token, collect)
+}
+
+pub(crate) fn spawn_persist_task<F>(
+    runtime: Option<&tokio::runtime::Handle>,
+    path: PathBuf,
+    interval: Duration,
+    token: CancellationToken,
+    collect: F,
+) -> tokio::task::JoinHandle<()>
+where
+    F: Fn() -> StatsSnapshot + Send + 'static,
+{
+    runtime::spawn(runtime, async move {
+        info!("Persisting stats to {} every {:?}", path.display(), interval);
+
+        loop {
+            let stopping = tokio::select! {
+                _ = sleep(interval) => false,
+                _ = token.cancelled() => true,
+            };
+
+            match collect().write_atomic(&path) {
+                Ok(()) => debug!("Persisted stats snapshot to {}", path.display()),
+                Err(e) => warn!("Failed to persist stats to {}: {}", path.display(), e),
+            }
+            if stopping {
+                break;
+            }
+        }
+
+        info!("Stats persistence stopped");
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_persist_and_load_snapshot() {
+        let path = std::env::temp_dir().join(format!("vehicle_stats_{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert!(StatsSnapshot::load(&path).unwrap().is_none());
+
+        let token = CancellationToken::new();
+        let handle = persist_stats(&path, Duration::from_secs(3600), token.clone(), || {
+            let mut processing = ProcessingStats::new();
+            processing.messages_received = 42;
+            let nanomsg = NanomsgStats { bytes_received: 1024, ..Default::default() };
+            StatsSnapshot::new(processing, Some(nanomsg))
+        });
+        // 取消时写入最后一次快照
+        token.cancel();
+        handle.await.unwrap();
+
+        let snapshot = StatsSnapshot::load(&path).unwrap().unwrap();
+        assert_eq!(snapshot.processing.messages_received, 42);
+        assert_eq!(snapshot.nanomsg.unwrap().bytes_received, 1024);
+        assert!(snapshot.processing.last_update.is_none());
+        let _ = fs::remove_file(&path);
+    }
+}
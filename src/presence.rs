@@ -0,0 +1,92 @@
+use crate::clock::Clock;
+
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 按VIN记录最近一次收到消息的时间，用于判断车队中当前在线的车辆
+#[derive(Debug)]
+pub struct VinRegistry {
+    last_seen: DashMap<String, Instant>,
+    clock: Arc<dyn Clock>,
+}
+
+impl VinRegistry {
+    /// 创建使用指定时间源的VIN登记表
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            last_seen: DashMap::new(),
+            clock,
+        }
+    }
+
+    /// 记录VIN在当前时间出现
+    pub fn touch(&self, vin: &str) {
+        let now = self.clock.now();
+        // 已登记的VIN只更新时间，避免每条消息都分配key
+        match self.last_seen.get_mut(vin) {
+            Some(mut seen) => *seen = now,
+            None => {
+                self.last_seen.insert(vin.to_string(), now);
+            }
+        }
+    }
+
+    /// 最近`within`时间内出现过的VIN
+    pub fn active_vins(&self, within: Duration) -> Vec<String> {
+        let now = self.clock.now();
+        self.last_seen
+            .iter()
+            .filter(|entry| now.saturating_duration_since(*entry.value()) <= within)
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    /// VIN最近一次出现的时间
+    pub fn last_seen(&self, vin: &str) -> Option<Instant> {
+        self.last_seen.get(vin).map(|seen| *seen)
+    }
+
+    /// 登记表中（尚未过期）的VIN数量
+    pub fn len(&self) -> usize {
+        self.last_seen.len()
+    }
+
+    /// 登记表是否为空
+    pub fn is_empty(&self) -> bool {
+        self.last_seen.is_empty()
+    }
+
+    /// 移除超过`expiry`未出现的VIN，返回移除数量
+    pub fn evict_expired(&self, expiry: Duration) -> usize {
+        let now = self.clock.now();
+        let before = self.last_seen.len();
+        self.last_seen
+            .retain(|_, seen| now.saturating_duration_since(*seen) <= expiry);
+        before.saturating_sub(self.last_seen.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn test_active_vins_and_expiry() {
+        let clock = Arc::new(MockClock::new());
+        let registry = VinRegistry::new(clock.clone());
+        registry.touch("VIN_1");
+        clock.advance(Duration::from_secs(30));
+        registry.touch("VIN_2");
+        assert_eq!(registry.len(), 2);
+
+        assert_eq!(registry.active_vins(Duration::from_secs(10)), vec!["VIN_2".to_string()]);
+
+        clock.advance(Duration::from_secs(40));
+        registry.touch("VIN_2");
+        assert_eq!(registry.evict_expired(Duration::from_secs(60)), 1);
+        assert!(registry.last_seen("VIN_1").is_none());
+        assert_eq!(registry.len(), 1);
+    }
+}
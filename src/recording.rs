@@ -0,0 +1,195 @@
+use crate::error::Result;
+use crate::framing::{encode_length_prefixed, LengthPrefixedDecoder};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+
+/// gzip文件头的魔数
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// 录制文件的压缩方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// 不压缩，直接写入长度前缀帧
+    #[default]
+    None,
+    /// gzip压缩，适合高频`traj`等大流量录制
+    Gzip,
+}
+
+/// 录制文件写入端
+enum RecordingWriter {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+/// 接入帧录制器：以长度前缀格式顺序写入原始帧，可选gzip压缩
+pub struct Recorder {
+    writer: RecordingWriter,
+    frames: u64,
+}
+
+impl Recorder {
+    /// 创建录制文件，已存在时覆盖
+    pub fn create(path: impl AsRef<Path>, compression: Compression) -> Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        let writer = match compression {
+            Compression::None => RecordingWriter::Plain(file),
+            Compression::Gzip => RecordingWriter::Gzip(GzEncoder::new(file, flate2::Compression::default())),
+        };
+        Ok(Self { writer, frames: 0 })
+    }
+
+    /// 追加一帧
+    pub fn record(&mut self, frame: &[u8]) -> Result<()> {
+        let record = encode_length_prefixed(frame);
+        match self.writer {
+            RecordingWriter::Plain(ref mut writer) => writer.write_all(&record)?,
+            RecordingWriter::Gzip(ref mut writer) => writer.write_all(&record)?,
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// 已录制的帧数
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// 结束录制：写出gzip尾部并刷新到磁盘
+    pub fn finish(self) -> Result<()> {
+        let mut file = match self.writer {
+            RecordingWriter::Plain(writer) => writer,
+            RecordingWriter::Gzip(writer) => writer.finish()?,
+        };
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// 每次从录制文件读取的字节数
+const READ_CHUNK_BYTES: usize = 64 * 1024;
+
+/// 录制文件读取端：按文件头魔数自动识别gzip压缩，逐帧流式解码
+///
+/// 录制中途退出时，未压缩文件末尾不完整的帧和gzip文件缺失的尾部都会被忽略，只返回已完整写入的帧
+pub struct RecordingReader {
+    source: Box<dyn Read + Send>,
+    decoder: LengthPrefixedDecoder,
+    chunk: Vec<u8>,
+    exhausted: bool,
+}
+
+impl RecordingReader {
+    /// 打开录制文件
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let source: Box<dyn Read + Send> = if file.fill_buf()?.starts_with(&GZIP_MAGIC) {
+            Box::new(GzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+        Ok(Self {
+            source,
+            decoder: LengthPrefixedDecoder::default(),
+            chunk: vec![0; READ_CHUNK_BYTES],
+            exhausted: false,
+        })
+    }
+}
+
+impl Iterator for RecordingReader {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.decoder.next_frame() {
+                Ok(Some(frame)) => return Some(Ok(frame.to_vec())),
+                Ok(None) if self.exhausted => return None,
+                Ok(None) => {}
+                Err(e) => {
+                    self.exhausted = true;
+                    return Some(Err(e));
+                }
+            }
+            match self.source.read(&mut self.chunk) {
+                Ok(0) => self.exhausted = true,
+                Ok(n) => self.decoder.extend(&self.chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                // gzip流被截断（未调用finish），已解压的帧仍然有效
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => self.exhausted = true,
+                Err(e) => {
+                    self.exhausted = true;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+    }
+}
+
+/// 读取录制文件中的全部帧，按文件头魔数自动识别gzip压缩
+///
+/// 大文件建议使用[`RecordingReader`]逐帧读取
+pub fn read_recording(path: impl AsRef<Path>) -> Result<Vec<Vec<u8>>> {
+    RecordingReader::open(path)?.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("vehicle_recording_{}_{}.bin", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_recording_round_trip_plain_and_gzip() {
+        let frames: Vec<Vec<u8>> = (0..100)
+            .map(|i| format!(r#"{{"service": "traj", "params": {{"vin": "VIN_{}", "data": {{}}}}}}"#, i).into_bytes())
+            .collect();
+
+        let mut sizes = Vec::new();
+        for (name, compression) in [("plain", Compression::None), ("gzip", Compression::Gzip)] {
+            let path = recording_path(name);
+            let mut recorder = Recorder::create(&path, compression).unwrap();
+            for frame in &frames {
+                recorder.record(frame).unwrap();
+            }
+            assert_eq!(recorder.frames(), 100);
+            recorder.finish().unwrap();
+
+            assert_eq!(read_recording(&path).unwrap(), frames);
+            sizes.push(std::fs::metadata(&path).unwrap().len());
+            let _ = std::fs::remove_file(&path);
+        }
+        assert!(sizes[1] < sizes[0]);
+    }
+
+    #[test]
+    fn test_truncated_recordings_keep_complete_frames() {
+        let frames: Vec<Vec<u8>> = (0..1000)
+            .map(|i| format!(r#"{{"service": "traj", "params": {{"vin": "VIN_{}", "data": {{}}}}}}"#, i).into_bytes())
+            .collect();
+
+        for (name, compression) in [("plain_truncated", Compression::None), ("gzip_truncated", Compression::Gzip)] {
+            let path = recording_path(name);
+            let mut recorder = Recorder::create(&path, compression).unwrap();
+            for frame in &frames {
+                recorder.record(frame).unwrap();
+            }
+            recorder.finish().unwrap();
+
+            // 截掉文件末尾（gzip尾部及部分压缩数据，或最后一帧的一部分）
+            let len = std::fs::metadata(&path).unwrap().len();
+            std::fs::OpenOptions::new().write(true).open(&path).unwrap().set_len(len - 16).unwrap();
+
+            let read = read_recording(&path).unwrap();
+            assert!(!read.is_empty() && read.len() < frames.len());
+            assert_eq!(read, frames[..read.len()]);
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+}
@@ -0,0 +1,385 @@
+//! Redis Streams传输
+//!
+//! 从Redis Stream读取条目作为原始帧交给客户端的接收循环。使用消费者组时条目在处理器接收、有意丢弃
+//! （过滤、采样、去重）或拒绝（无效帧）后确认（XACK）；因队列满被丢弃的条目不确认，
+//! 重新连接时先重放本消费者未确认的条目，实现至少一次的可重放接入。
+
+use crate::error::{NanomsgErrorKind, Result, VehicleError};
+use crate::nanomsg_client::SocketFactory;
+use crate::transport::Transport;
+
+use crossbeam::channel::{Receiver, Sender, TryRecvError};
+use parking_lot::RwLock;
+use redis::streams::{StreamReadOptions, StreamReadReply};
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// 读取线程与接收端之间缓存的最大条目数，缓存满时读取线程等待（背压）
+const REDIS_CHANNEL_CAPACITY: usize = 1024;
+
+/// 连接Redis服务器的超时时间
+const REDIS_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 消费者组配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedisConsumerGroup {
+    /// 组名，不存在时从流末尾创建
+    pub group: String,
+    /// 本实例的消费者名，同一组内各实例应不同
+    pub consumer: String,
+}
+
+/// Redis Streams传输配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedisStreamConfig {
+    /// Redis服务器地址，如`redis://127.0.0.1:6379`
+    pub url: String,
+    /// 读取的流
+    pub stream: String,
+    /// 条目中保存原始帧的字段名
+    pub field: String,
+    /// 消费者组，None时使用XREAD直接读取且不确认
+    pub group: Option<RedisConsumerGroup>,
+    /// 不使用消费者组且没有已交付记录时的起始ID，`$`表示只读取新条目，`0`表示从头重放
+    pub start_id: String,
+    /// 每次读取的最大条目数
+    pub batch_size: usize,
+    /// 每次读取的最长阻塞时间，同时决定关闭时读取线程的退出延迟
+    pub block: Duration,
+}
+
+impl RedisStreamConfig {
+    /// 创建读取指定流的配置，帧字段默认为`payload`
+    pub fn new(url: impl Into<String>, stream: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            stream: stream.into(),
+            field: "payload".to_string(),
+            group: None,
+            start_id: "$".to_string(),
+            batch_size: 100,
+            block: Duration::from_millis(100),
+        }
+    }
+
+    /// 使用消费者组读取
+    pub fn with_group(mut self, group: impl Into<String>, consumer: impl Into<String>) -> Self {
+        self.group = Some(RedisConsumerGroup {
+            group: group.into(),
+            consumer: consumer.into(),
+        });
+        self
+    }
+
+    /// 设置保存原始帧的字段名
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.field = field.into();
+        self
+    }
+
+    /// 设置不使用消费者组时的起始ID
+    pub fn with_start_id(mut self, start_id: impl Into<String>) -> Self {
+        self.start_id = start_id.into();
+        self
+    }
+
+    /// 创建Redis Streams传输的socket工厂，用于`NanomsgClient::set_socket_factory`
+    ///
+    /// 重连创建的各传输共享同一份统计，不使用消费者组时从最后交付的ID继续读取
+    pub fn socket_factory(&self, stats: Arc<RwLock<RedisStreamStats>>) -> SocketFactory {
+        let config = self.clone();
+        Arc::new(move || {
+            Box::new(RedisStreamTransport::with_stats(config.clone(), stats.clone())) as Box<dyn Transport>
+        })
+    }
+}
+
+/// Redis Streams传输统计信息
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RedisStreamStats {
+    pub entries_received: u64,
+    pub bytes_received: u64,
+    /// 已确认（XACK）的条目数
+    pub entries_acked: u64,
+    /// 缺少帧字段或被处理器拒绝（解析、校验失败）而跳过的条目数（使用消费者组时仍会确认）
+    pub entries_invalid: u64,
+    /// 从未确认列表中重放的条目数
+    pub entries_replayed: u64,
+    /// 最后交给客户端的条目ID
+    pub last_delivered_id: Option<String>,
+}
+
+/// 读取线程交给接收端的条目
+struct StreamEntry {
+    id: String,
+    payload: Vec<u8>,
+}
+
+/// 运行中的读取线程
+struct StreamReader {
+    rx: Receiver<StreamEntry>,
+    ack_tx: Sender<String>,
+    shutdown: Arc<AtomicBool>,
+}
+
+/// Redis Streams传输：在独立线程上读取流，`recv`非阻塞地取出条目
+///
+/// `bind`的URL参数仅用于日志，实际连接`RedisStreamConfig::url`
+pub struct RedisStreamTransport {
+    config: RedisStreamConfig,
+    reader: Option<StreamReader>,
+    stats: Arc<RwLock<RedisStreamStats>>,
+    // 最近一次recv返回、尚未确认的条目ID
+    unacked: Option<String>,
+}
+
+impl RedisStreamTransport {
+    /// 创建未连接的传输
+    pub fn new(config: RedisStreamConfig) -> Self {
+        Self::with_stats(config, Arc::new(RwLock::new(RedisStreamStats::default())))
+    }
+
+    /// 创建使用共享统计的传输
+    pub fn with_stats(config: RedisStreamConfig, stats: Arc<RwLock<RedisStreamStats>>) -> Self {
+        Self {
+            config,
+            reader: None,
+            stats,
+            unacked: None,
+        }
+    }
+
+    /// 获取统计信息
+    pub fn stats(&self) -> RedisStreamStats {
+        self.stats.read().clone()
+    }
+
+    /// 连接Redis并按需创建消费者组
+    fn connect(&self) -> Result<Connection> {
+        let client = redis::Client::open(self.config.url.as_str())
+            .map_err(|e| VehicleError::ConfigError(format!("Invalid redis url {}: {}", self.config.url, e)))?;
+        let mut connection = client
+            .get_connection_with_timeout(REDIS_CONNECT_TIMEOUT)
+            .map_err(|e| redis_error("Failed to connect to redis", e))?;
+
+        if let Some(ref group) = self.config.group {
+            let created: redis::RedisResult<()> =
+                connection.xgroup_create_mkstream(&self.config.stream, &group.group, "$");
+            match created {
+                Ok(()) => info!("Created consumer group {} on {}", group.group, self.config.stream),
+                Err(e) if e.code() == Some("BUSYGROUP") => {}
+                Err(e) => return Err(redis_error("Failed to create consumer group", e)),
+            }
+        }
+        Ok(connection)
+    }
+
+    /// 启动读取线程
+    fn spawn_reader(&self, mut connection: Connection) -> Result<StreamReader> {
+        let (tx, rx) = crossbeam::channel::bounded(REDIS_CHANNEL_CAPACITY);
+        let (ack_tx, ack_rx) = crossbeam::channel::unbounded();
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let config = self.config.clone();
+        let stats = self.stats.clone();
+        let stop = shutdown.clone();
+
+        // 消费者组先从"0"重放本消费者未确认的条目，重放完后改读新条目">"
+        let mut cursor = match config.group {
+            Some(_) => "0".to_string(),
+            None => stats.read().last_delivered_id.clone().unwrap_or_else(|| config.start_id.clone()),
+        };
+        let mut replaying = config.group.is_some();
+
+        thread::Builder::new().name("redis-stream-transport".to_string()).spawn(move || {
+            let mut options = StreamReadOptions::default()
+                .count(config.batch_size.max(1))
+                .block(config.block.as_millis() as usize);
+            if let Some(ref group) = config.group {
+                options = options.group(&group.group, &group.consumer);
+            }
+
+            'read: while !stop.load(Ordering::SeqCst) {
+                if let Err(e) = flush_acks(&config, &mut connection, &ack_rx, &stats) {
+                    warn!("Failed to ack stream entries: {}", e);
+                    return;
+                }
+
+                let read_id = if config.group.is_some() && !replaying { ">" } else { cursor.as_str() };
+                let reply: Option<StreamReadReply> =
+                    match connection.xread_options(&[&config.stream], &[read_id], &options) {
+                        Ok(reply) => reply,
+                        Err(e) => {
+                            warn!("Failed to read stream {}: {}", config.stream, e);
+                            break;
+                        }
+                    };
+                let entries: Vec<_> = reply.into_iter().flat_map(|reply| reply.keys).flat_map(|key| key.ids).collect();
+                if replaying && entries.is_empty() {
+                    replaying = false;
+                    continue;
+                }
+
+                for entry in entries {
+                    cursor = entry.id.clone();
+                    let payload = entry
+                        .map
+                        .get(&config.field)
+                        .and_then(|value| redis::from_redis_value::<Vec<u8>>(value).ok());
+                    let Some(payload) = payload else {
+                        stats.write().entries_invalid += 1;
+                        if let Err(e) = ack_invalid(&config, &mut connection, &entry.id) {
+                            warn!("Failed to ack invalid stream entry {}: {}", entry.id, e);
+                        }
+                        continue;
+                    };
+                    if replaying {
+                        stats.write().entries_replayed += 1;
+                    }
+                    // 接收端关闭后退出；缓存满时阻塞，把背压传回Redis
+                    if tx.send(StreamEntry { id: entry.id, payload }).is_err() {
+                        break 'read;
+                    }
+                }
+            }
+
+            // 退出前提交关闭前已确认的条目，其余未确认条目在下次连接时重放
+            if let Err(e) = flush_acks(&config, &mut connection, &ack_rx, &stats) {
+                warn!("Failed to ack stream entries: {}", e);
+            }
+        })?;
+
+        Ok(StreamReader { rx, ack_tx, shutdown })
+    }
+}
+
+/// 批量确认接收端已提交的条目
+fn flush_acks(
+    config: &RedisStreamConfig,
+    connection: &mut Connection,
+    ack_rx: &Receiver<String>,
+    stats: &RwLock<RedisStreamStats>,
+) -> redis::RedisResult<()> {
+    let Some(ref group) = config.group else {
+        return Ok(());
+    };
+    let ids: Vec<String> = ack_rx.try_iter().collect();
+    if !ids.is_empty() {
+        let acked: usize = connection.xack(&config.stream, &group.group, &ids)?;
+        stats.write().entries_acked += acked as u64;
+    }
+    Ok(())
+}
+
+/// 确认缺少帧字段的条目，避免其在未确认列表中反复重放
+fn ack_invalid(config: &RedisStreamConfig, connection: &mut Connection, id: &str) -> redis::RedisResult<()> {
+    if let Some(ref group) = config.group {
+        connection.xack::<_, _, _, usize>(&config.stream, &group.group, &[id])?;
+    }
+    Ok(())
+}
+
+fn redis_error(context: &str, e: redis::RedisError) -> VehicleError {
+    VehicleError::nanomsg(NanomsgErrorKind::NotConnected, format!("{}: {}", context, e))
+}
+
+impl Transport for RedisStreamTransport {
+    fn bind(&mut self, url: &str) -> Result<()> {
+        self.close();
+        let connection = self.connect()?;
+        self.reader = Some(self.spawn_reader(connection)?);
+        info!(
+            "Redis stream transport reading {} on {} (endpoint {})",
+            self.config.stream, self.config.url, url
+        );
+        Ok(())
+    }
+
+    fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        let Some(ref reader) = self.reader else {
+            return Err(VehicleError::nanomsg(NanomsgErrorKind::NotConnected, "Redis stream transport not bound"));
+        };
+
+        match reader.rx.try_recv() {
+            Ok(entry) => {
+                let copy_len = entry.payload.len().min(buffer.len());
+                buffer[..copy_len].copy_from_slice(&entry.payload[..copy_len]);
+                {
+                    let mut stats = self.stats.write();
+                    stats.entries_received += 1;
+                    stats.bytes_received += copy_len as u64;
+                    stats.last_delivered_id = Some(entry.id.clone());
+                }
+                // 客户端成功提交后通过`ack`确认，未确认的条目在重新连接时重放
+                self.unacked = self.config.group.as_ref().map(|_| entry.id);
+                Ok(copy_len)
+            }
+            Err(TryRecvError::Empty) => Err(VehicleError::WouldBlock),
+            Err(TryRecvError::Disconnected) => Err(VehicleError::nanomsg(
+                NanomsgErrorKind::ConnectionReset,
+                "Redis stream reader stopped",
+            )),
+        }
+    }
+
+    fn ack(&mut self) {
+        // 读取线程在下次读取前批量发送XACK
+        if let (Some(id), Some(reader)) = (self.unacked.take(), self.reader.as_ref()) {
+            let _ = reader.ack_tx.send(id);
+        }
+    }
+
+    fn reject(&mut self) {
+        if self.unacked.is_some() {
+            self.stats.write().entries_invalid += 1;
+        }
+        self.ack();
+    }
+
+    fn close(&mut self) {
+        self.unacked = None;
+        if let Some(reader) = self.reader.take() {
+            reader.shutdown.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+impl Drop for RedisStreamTransport {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redis_stream_transport_requires_connection() {
+        let config = RedisStreamConfig::new("redis://127.0.0.1:1", "vehicle").with_group("core", "node-1");
+        assert_eq!(config.field, "payload");
+        assert_eq!(config.group.as_ref().unwrap().consumer, "node-1");
+
+        let mut transport = RedisStreamTransport::new(config);
+        let mut buffer = [0u8; 16];
+        assert!(matches!(
+            transport.recv(&mut buffer),
+            Err(VehicleError::NanomsgError { kind: NanomsgErrorKind::NotConnected, .. })
+        ));
+        // 端口1上没有Redis服务器，连接应立即失败
+        assert!(transport.bind("redis://127.0.0.1:1").is_err());
+        assert!(matches!(
+            RedisStreamTransport::new(RedisStreamConfig::new("not a url", "vehicle")).bind(""),
+            Err(VehicleError::ConfigError(_))
+        ));
+        assert_eq!(transport.stats().entries_received, 0);
+        assert!(transport.stats().last_delivered_id.is_none());
+        // 未接收任何条目时确认不做任何事
+        transport.ack();
+        assert_eq!(transport.stats().entries_acked, 0);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::error::{Result, VehicleError};
+use crate::message_processor::MessageProcessor;
+use crate::types::{ProcessingStats, ProcessorState, SubmitOutcome};
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+
+/// 按VIN哈希将消息分发到多个处理器，每个处理器负责互不相交的车辆集合，保证单车消息有序
+pub struct ProcessorRouter {
+    shards: Vec<Arc<MessageProcessor>>,
+}
+
+impl ProcessorRouter {
+    /// 使用已创建的处理器分片创建路由器
+    pub fn new(shards: Vec<Arc<MessageProcessor>>) -> Result<Self> {
+        if shards.is_empty() {
+            return Err(VehicleError::ConfigError("Processor router requires at least one shard".to_string()));
+        }
+        Ok(Self { shards })
+    }
+
+    /// 分片数量
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// 获取所有分片
+    pub fn shards(&self) -> &[Arc<MessageProcessor>] {
+        &self.shards
+    }
+
+    /// 计算VIN所属的分片；空白VIN与处理器一致按"UNKNOWN"路由
+    pub fn shard_for_vin(&self, vin: &str) -> usize {
+        let vin = if vin.trim().is_empty() { "UNKNOWN" } else { vin };
+        let mut hasher = DefaultHasher::new();
+        vin.hash(&mut hasher);
+        (hasher.finish() % self.shards.len() as u64) as usize
+    }
+
+    /// 按VIN路由提交消息
+    pub async fn submit_message(&self, raw_data: &[u8]) -> Result<()> {
+        self.route(raw_data)?.submit_message(raw_data).await
+    }
+
+    /// 按VIN路由提交消息，并返回入队或丢弃的决策
+    pub async fn submit_message_detailed(&self, raw_data: &[u8]) -> Result<SubmitOutcome> {
+        self.route(raw_data)?.submit_message_detailed(raw_data).await
+    }
+
+    /// 选择消息所属的分片，VIN位置取自分片配置的`field_paths`（各分片应使用相同的字段位置）
+    ///
+    /// 非字符串VIN与处理器一致按"UNKNOWN"路由；无法解析的消息计入分片0的解析错误
+    fn route(&self, raw_data: &[u8]) -> Result<&Arc<MessageProcessor>> {
+        let frame: serde_json::Value = match serde_json::from_slice(raw_data) {
+            Ok(frame) => frame,
+            Err(e) => {
+                self.shards[0].performance_monitor().record_parse_error();
+                return Err(e.into());
+            }
+        };
+        let vin_path = self.shards[0].field_paths().vin();
+        let vin = frame.pointer(vin_path).and_then(|v| v.as_str()).unwrap_or_default();
+        Ok(&self.shards[self.shard_for_vin(vin)])
+    }
+
+    /// 启动所有分片，直到全部分片结束
+    pub async fn start(&self) -> Result<()> {
+        info!("Starting processor router with {} shards", self.shards.len());
+
+        let mut tasks = JoinSet::new();
+        for shard in &self.shards {
+            let shard = shard.clone();
+            tasks.spawn(async move { shard.start().await });
+        }
+
+        let mut first_error = None;
+        while let Some(result) = tasks.join_next().await {
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    warn!("Processor shard failed: {}", e);
+                    first_error.get_or_insert(e);
+                }
+                Err(e) => warn!("Processor shard task panicked: {}", e),
+            }
+        }
+
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// 停止所有分片
+    pub fn stop(&self) {
+        for shard in &self.shards {
+            shard.stop();
+        }
+    }
+
+    /// 汇总所有分片的统计；任一分片运行中即视为运行中
+    pub fn get_stats(&self) -> ProcessingStats {
+        let mut total = ProcessingStats::default();
+        for shard in &self.shards {
+            let stats = shard.get_stats();
+            total.merge(&stats);
+            if total.processor_state != ProcessorState::Running {
+                total.processor_state = stats.processor_state;
+            }
+        }
+        total
+    }
+
+    /// 各分片的统计
+    pub fn get_shard_stats(&self) -> Vec<ProcessingStats> {
+        self.shards.iter().map(|shard| shard.get_stats()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message_processor::ProcessorConfig;
+    use crate::types::FieldPaths;
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    #[tokio::test]
+    async fn test_routes_by_vin() {
+        let shards = (0..4).map(|_| Arc::new(MessageProcessor::new())).collect();
+        let router = Arc::new(ProcessorRouter::new(shards).unwrap());
+        let runner = router.clone();
+        let handle = tokio::spawn(async move { runner.start().await });
+        sleep(Duration::from_millis(20)).await;
+
+        for i in 0..8 {
+            let frame = format!(
+                r#"{{"service": "tracking", "params": {{"vin": "VIN_{}", "timestamp": 1234567890.0, "data": {{"x": {}}}}}}}"#,
+                i % 2, i
+            );
+            router.submit_message(frame.as_bytes()).await.unwrap();
+        }
+
+        // 同一VIN的消息全部进入同一分片
+        let per_shard: Vec<u64> = router.get_shard_stats().iter().map(|s| s.messages_received).collect();
+        for vin in ["VIN_0", "VIN_1"] {
+            assert!(per_shard[router.shard_for_vin(vin)] >= 4);
+        }
+        assert_eq!(router.get_stats().messages_received, 8);
+        assert!(router.submit_message(b"not json").await.is_err());
+        assert_eq!(router.get_stats().parse_errors, 1);
+
+        router.stop();
+        let _ = tokio::time::timeout(Duration::from_secs(1), handle).await;
+    }
+
+    #[tokio::test]
+    async fn test_routes_by_configured_vin_path() {
+        let config = || ProcessorConfig {
+            field_paths: FieldPaths::default().with_vin("/header/vehicle").unwrap(),
+            ..ProcessorConfig::default()
+        };
+        let shards = (0..4).map(|_| Arc::new(MessageProcessor::with_config(config()))).collect();
+        let router = ProcessorRouter::new(shards).unwrap();
+
+        let frame = br#"{"service": "tracking", "header": {"vehicle": "VIN_7"}, "params": {"vin": "OTHER", "timestamp": 1.0, "data": {}}}"#;
+        assert!(std::ptr::eq(router.route(frame).unwrap(), &router.shards()[router.shard_for_vin("VIN_7")]));
+    }
+
+    #[test]
+    fn test_routes_escaped_and_non_string_vins() {
+        let shards = (0..4).map(|_| Arc::new(MessageProcessor::new())).collect();
+        let router = ProcessorRouter::new(shards).unwrap();
+        let shard_of = |frame: &[u8]| {
+            let shard = router.route(frame).unwrap();
+            router.shards().iter().position(|s| Arc::ptr_eq(s, shard)).unwrap()
+        };
+
+        // 转义的VIN按解码后的值路由，非字符串VIN按"UNKNOWN"路由
+        assert_eq!(shard_of(br#"{"params": {"vin": "VIN\u005f1"}}"#), router.shard_for_vin("VIN_1"));
+        assert_eq!(shard_of(br#"{"params": {"vin": 12345}}"#), router.shard_for_vin("UNKNOWN"));
+        assert_eq!(shard_of(br#"{"params": {}}"#), router.shard_for_vin("UNKNOWN"));
+    }
+
+    #[test]
+    fn test_requires_shards() {
+        assert!(ProcessorRouter::new(Vec::new()).is_err());
+    }
+}
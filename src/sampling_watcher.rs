@@ -0,0 +1,272 @@
+use crate::error::{Result, VehicleError};
+use crate::types::SamplingConfig;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::RwLock;
+use serde::Deserialize;
+use tracing::{error, info};
+
+/// 采样配置文件内容（TOML或JSON，按扩展名识别）
+///
+/// ```toml
+/// default_rate = 0.5
+///
+/// [rates]
+/// traj = 0.1
+///
+/// [globs]
+/// "sensor_*" = 0.2
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SamplingConfigFile {
+    /// 未配置服务的默认采样率
+    #[serde(default)]
+    pub default_rate: Option<f32>,
+    /// 按服务名精确设置的采样率
+    #[serde(default)]
+    pub rates: HashMap<String, f32>,
+    /// 按glob模式设置的采样率
+    #[serde(default)]
+    pub globs: HashMap<String, f32>,
+}
+
+impl SamplingConfigFile {
+    /// 读取并解析配置文件
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+
+        let file: SamplingConfigFile = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&content)?,
+            _ => toml::from_str(&content).map_err(|e| {
+                VehicleError::ConfigError(format!("Invalid sampling config {}: {}", path.display(), e))
+            })?,
+        };
+
+        file.validate()?;
+        Ok(file)
+    }
+
+    /// 校验所有采样率在[0.0, 1.0]范围内
+    pub fn validate(&self) -> Result<()> {
+        if let Some(rate) = self.default_rate {
+            if !rate.is_finite() || !(0.0..=1.0).contains(&rate) {
+                return Err(VehicleError::ConfigError(format!(
+                    "Default sampling rate out of range: {}",
+                    rate
+                )));
+            }
+        }
+        for (name, rate) in self.rates.iter().chain(self.globs.iter()) {
+            if !rate.is_finite() || !(0.0..=1.0).contains(rate) {
+                return Err(VehicleError::ConfigError(format!(
+                    "Sampling rate for {} out of range: {}",
+                    name, rate
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// 将文件内容应用到采样配置，返回变化的条目描述
+    ///
+    /// 在写锁内先于副本上应用，全部成功后再替换，避免无效的模式覆盖运行中的配置，
+    /// 也不会丢失其他线程在读取与替换之间所做的修改
+    ///
+    /// 应用是增量的：文件中删除的条目不会从运行中的配置移除，保持上次设置的采样率
+    pub fn apply_to(&self, config: &RwLock<SamplingConfig>) -> Result<Vec<String>> {
+        let mut current = config.write();
+        let mut updated = current.clone();
+        let mut changes = Vec::new();
+
+        if let Some(rate) = self.default_rate {
+            if updated.default_rate != rate {
+                changes.push(describe_change("(default)", Some(updated.default_rate), rate));
+                updated.set_default_rate(rate);
+            }
+        }
+
+        for (service, rate) in &self.rates {
+            let old = updated.rates.get(service).copied();
+            if old != Some(*rate) {
+                changes.push(describe_change(service, old, *rate));
+                updated.set_rate(service, *rate);
+            }
+        }
+
+        for (glob, rate) in &self.globs {
+            let old = updated
+                .patterns
+                .iter()
+                .find(|p| &p.pattern == glob)
+                .map(|p| p.rate);
+            if old != Some(*rate) {
+                changes.push(describe_change(glob, old, *rate));
+                updated.set_glob_rate(glob, *rate)?;
+            }
+        }
+
+        *current = updated;
+        Ok(changes)
+    }
+}
+
+fn describe_change(name: &str, old: Option<f32>, new: f32) -> String {
+    match old {
+        Some(old) => format!("{}: {:.2} -> {:.2}", name, old, new),
+        None => format!("{}: (unset) -> {:.2}", name, new),
+    }
+}
+
+/// 采样配置文件监听器，drop后停止监听
+pub struct SamplingConfigWatcher {
+    path: PathBuf,
+    _watcher: RecommendedWatcher,
+}
+
+impl SamplingConfigWatcher {
+    /// 加载配置文件并开始监听，文件变化时重新加载
+    ///
+    /// 初次加载失败时返回错误；之后的无效文件会被拒绝，运行中的配置保持不变。
+    /// 重新加载只添加或更新条目，从文件中删除的条目需通过`update_sampling_config`等接口显式修改
+    pub(crate) fn start(path: &Path, config: Arc<RwLock<SamplingConfig>>) -> Result<Self> {
+        let path = path.to_path_buf();
+        reload(&path, &config)?;
+
+        let watched_path = path.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            match event {
+                Ok(event) => {
+                    let relevant = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                        && event.paths.iter().any(|p| p.file_name() == watched_path.file_name());
+                    if relevant {
+                        if let Err(e) = reload(&watched_path, &config) {
+                            error!("Rejected sampling config {}: {}", watched_path.display(), e);
+                        }
+                    }
+                }
+                Err(e) => error!("Sampling config watch error: {}", e),
+            }
+        })
+        .map_err(|e| VehicleError::ConfigError(format!("Failed to create file watcher: {}", e)))?;
+
+        // 监听所在目录，兼容编辑器以重命名方式替换文件
+        let dir = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|e| VehicleError::ConfigError(format!("Failed to watch {}: {}", dir.display(), e)))?;
+
+        info!("Watching sampling config: {}", path.display());
+        Ok(Self {
+            path,
+            _watcher: watcher,
+        })
+    }
+
+    /// 获取监听的文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// 重新加载配置文件并记录变化
+fn reload(path: &Path, config: &RwLock<SamplingConfig>) -> Result<()> {
+    let file = SamplingConfigFile::load(path)?;
+    let changes = file.apply_to(config)?;
+
+    if changes.is_empty() {
+        info!("Reloaded sampling config {}: no changes", path.display());
+    } else {
+        info!("Reloaded sampling config {}: {}", path.display(), changes.join(", "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_config_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "vehicle_nn_sampling_{}_{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("sampling.toml")
+    }
+
+    #[test]
+    fn test_apply_config_file() {
+        let config = RwLock::new(SamplingConfig::default());
+        let file: SamplingConfigFile = toml::from_str(
+            r#"
+            default_rate = 0.5
+
+            [rates]
+            traj = 0.5
+            tracking = 1.0
+
+            [globs]
+            "sensor_*" = 0.2
+            "#,
+        )
+        .unwrap();
+
+        let changes = file.apply_to(&config).unwrap();
+        assert_eq!(changes.len(), 3); // tracking未变化
+        assert_eq!(config.read().get_rate("traj"), 0.5);
+        assert_eq!(config.read().get_rate("sensor_gps"), 0.2);
+        assert_eq!(config.read().get_rate("unknown_service"), 0.5);
+
+        // 增量应用：文件中不再出现的条目保持原值
+        let reduced: SamplingConfigFile = toml::from_str("[rates]\ntracking = 0.8\n").unwrap();
+        assert_eq!(reduced.apply_to(&config).unwrap().len(), 1);
+        assert_eq!(config.read().get_rate("traj"), 0.5);
+        assert_eq!(config.read().get_rate("sensor_gps"), 0.2);
+
+        let invalid = SamplingConfigFile {
+            rates: HashMap::from([("traj".to_string(), 1.5)]),
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_watch_reloads_and_rejects_invalid() {
+        let path = temp_config_path("watch");
+        std::fs::write(&path, "[rates]\ntraj = 0.5\n").unwrap();
+
+        let config = Arc::new(RwLock::new(SamplingConfig::default()));
+        let watcher = SamplingConfigWatcher::start(&path, config.clone()).unwrap();
+        assert_eq!(watcher.path(), path.as_path());
+        assert_eq!(config.read().get_rate("traj"), 0.5);
+
+        let wait_for_rate = |expected: f32| {
+            for _ in 0..100 {
+                if config.read().get_rate("traj") == expected {
+                    return true;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            false
+        };
+
+        std::fs::write(&path, "[rates]\ntraj = 0.25\n").unwrap();
+        assert!(wait_for_rate(0.25));
+
+        // 无效文件不覆盖运行中的配置
+        std::fs::write(&path, "[rates]\ntraj = \"oops\"\n").unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(config.read().get_rate("traj"), 0.25);
+
+        drop(watcher);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
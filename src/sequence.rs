@@ -0,0 +1,169 @@
+use crate::clock::{Clock, SystemClock};
+
+use dashmap::DashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// VIN+服务序列的哈希键，避免每条消息分配字符串；哈希碰撞只影响诊断统计
+pub(crate) fn series_key(vin: &str, service: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    vin.hash(&mut hasher);
+    service.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 序列号缺口统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GapStats {
+    /// 跟踪中的序列数（VIN+服务）
+    pub sequences_tracked: usize,
+    /// 检测到的缺口次数
+    pub gaps_detected: u64,
+    /// 缺口中丢失的帧总数
+    pub frames_missing: u64,
+    /// 序列号回退次数（视为上游重启）
+    pub resets: u64,
+}
+
+/// 按VIN+服务跟踪`params.seq`，检测上游丢帧
+#[derive(Debug)]
+pub struct SequenceTracker {
+    // 序列键 -> (最新序列号, 最近出现时间)
+    last_seq: DashMap<u64, (u64, Instant)>,
+    gaps_detected: AtomicU64,
+    frames_missing: AtomicU64,
+    resets: AtomicU64,
+    clock: Arc<dyn Clock>,
+}
+
+impl SequenceTracker {
+    /// 创建序列号跟踪器
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// 使用指定时间源创建
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            last_seq: DashMap::new(),
+            gaps_detected: AtomicU64::new(0),
+            frames_missing: AtomicU64::new(0),
+            resets: AtomicU64::new(0),
+            clock,
+        }
+    }
+
+    /// 记录序列号，返回检测到的缺口大小（丢失的帧数）
+    ///
+    /// 相同序列号视为重复，不计缺口；序列号回退视为上游重启，从新序列号重新跟踪
+    pub fn observe(&self, vin: &str, service: &str, seq: u64) -> Option<u64> {
+        let now = self.clock.now();
+        let mut entry = self.last_seq.entry(series_key(vin, service)).or_insert((seq, now));
+        let (last, seen) = entry.value_mut();
+        *seen = now;
+
+        let previous = *last;
+        if seq < previous {
+            self.resets.fetch_add(1, Ordering::Relaxed);
+            *last = seq;
+            return None;
+        }
+        if seq == previous {
+            return None;
+        }
+
+        *last = seq;
+        let missing = seq - previous - 1;
+        if missing == 0 {
+            return None;
+        }
+
+        self.gaps_detected.fetch_add(1, Ordering::Relaxed);
+        self.frames_missing.fetch_add(missing, Ordering::Relaxed);
+        warn!(
+            "Sequence gap for {} {}: expected {}, got {} ({} missing)",
+            vin,
+            service,
+            previous + 1,
+            seq,
+            missing
+        );
+        Some(missing)
+    }
+
+    /// 移除超过`expiry`未出现的序列，返回移除数量
+    pub fn evict_expired(&self, expiry: Duration) -> usize {
+        let now = self.clock.now();
+        let before = self.last_seq.len();
+        self.last_seq
+            .retain(|_, (_, seen)| now.saturating_duration_since(*seen) <= expiry);
+        before.saturating_sub(self.last_seq.len())
+    }
+
+    /// 获取缺口统计
+    pub fn stats(&self) -> GapStats {
+        GapStats {
+            sequences_tracked: self.last_seq.len(),
+            gaps_detected: self.gaps_detected.load(Ordering::Relaxed),
+            frames_missing: self.frames_missing.load(Ordering::Relaxed),
+            resets: self.resets.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for SequenceTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn test_detects_gaps_per_sequence() {
+        let tracker = SequenceTracker::new();
+        assert_eq!(tracker.observe("VIN_1", "tracking", 1), None);
+        assert_eq!(tracker.observe("VIN_1", "tracking", 2), None);
+        assert_eq!(tracker.observe("VIN_1", "tracking", 5), Some(2));
+        // 重复和其他序列互不影响
+        assert_eq!(tracker.observe("VIN_1", "tracking", 5), None);
+        assert_eq!(tracker.observe("VIN_1", "traj", 10), None);
+        assert_eq!(tracker.observe("VIN_2", "tracking", 1), None);
+        // 回退视为重启
+        assert_eq!(tracker.observe("VIN_1", "tracking", 0), None);
+        assert_eq!(tracker.observe("VIN_1", "tracking", 1), None);
+
+        assert_eq!(
+            tracker.stats(),
+            GapStats {
+                sequences_tracked: 3,
+                gaps_detected: 1,
+                frames_missing: 2,
+                resets: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_evicts_inactive_sequences() {
+        let clock = Arc::new(MockClock::new());
+        let tracker = SequenceTracker::with_clock(clock.clone());
+        tracker.observe("VIN_1", "tracking", 1);
+        clock.advance(Duration::from_secs(30));
+        tracker.observe("VIN_2", "tracking", 1);
+
+        clock.advance(Duration::from_secs(40));
+        assert_eq!(tracker.evict_expired(Duration::from_secs(60)), 1);
+        assert_eq!(tracker.stats().sequences_tracked, 1);
+        // 过期序列重新出现时从新序列号开始跟踪，不计缺口
+        assert_eq!(tracker.observe("VIN_1", "tracking", 10), None);
+        assert_eq!(tracker.observe("VIN_2", "tracking", 3), Some(1));
+    }
+}
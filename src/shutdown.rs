@@ -0,0 +1,53 @@
+use crate::error::{Result, VehicleError};
+use crate::message_processor::MessageProcessor;
+use crate::nanomsg_client::NanomsgClient;
+use crate::types::MessagePriority;
+
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+/// 关闭过程中检查接收任务和队列状态的间隔
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// 按顺序关闭客户端和处理器，避免关闭期间向已停止的处理器提交消息
+///
+/// 依次：停止客户端接收、等待正在提交的批次完成、等待处理器队列和回调清空、停止处理器。
+/// `timeout`为整个过程的时限，超时后仍会停止处理器并返回`Timeout`，未处理的消息被丢弃
+pub async fn shutdown(client: &NanomsgClient, processor: &MessageProcessor, timeout: Duration) -> Result<()> {
+    let deadline = Instant::now() + timeout;
+
+    info!("Shutting down: stopping client");
+    client.stop();
+    while client.is_receiving() && Instant::now() < deadline {
+        sleep(SHUTDOWN_POLL_INTERVAL).await;
+    }
+
+    info!("Shutting down: draining message processor");
+    while processor.is_running() && pending_messages(processor) > 0 && Instant::now() < deadline {
+        sleep(SHUTDOWN_POLL_INTERVAL).await;
+    }
+
+    let pending = if processor.is_running() { pending_messages(processor) } else { 0 };
+    let receiving = client.is_receiving();
+    processor.stop();
+
+    if pending > 0 || receiving {
+        warn!(
+            "Shutdown timed out after {:?}: {} messages not processed, client receiving: {}",
+            timeout, pending, receiving
+        );
+        return Err(VehicleError::Timeout);
+    }
+    info!("Shutdown complete");
+    Ok(())
+}
+
+/// 处理器中尚未处理完成的消息数：各队列中的消息加正在执行的回调
+fn pending_messages(processor: &MessageProcessor) -> usize {
+    MessagePriority::all()
+        .into_iter()
+        .map(|priority| processor.queue_depth(priority))
+        .sum::<usize>()
+        + processor.callbacks_in_flight()
+}
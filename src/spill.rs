@@ -0,0 +1,341 @@
+use crate::error::{Result, VehicleError};
+use crate::framing::encode_length_prefixed;
+use crate::types::VehicleMessage;
+
+use crossbeam::channel::{Sender, TrySendError};
+use parking_lot::Mutex;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use tracing::{error, info, warn};
+
+/// 长度前缀的字节数（与长度前缀分帧一致，4字节大端）
+const LENGTH_PREFIX_BYTES: u64 = 4;
+
+/// 默认溢写文件大小上限
+pub const DEFAULT_MAX_SPILL_BYTES: u64 = 64 * 1024 * 1024;
+
+/// 等待写入线程写盘的最大消息数
+const SPILL_WRITE_CAPACITY: usize = 1024;
+
+/// 关键消息溢写配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpillConfig {
+    /// 溢写文件路径，启动时文件中残留的消息会被重新接入
+    pub path: PathBuf,
+    /// 溢写文件大小上限，超出时新的溢出消息仍被丢弃
+    pub max_bytes: u64,
+}
+
+impl SpillConfig {
+    /// 使用默认大小上限创建
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            max_bytes: DEFAULT_MAX_SPILL_BYTES,
+        }
+    }
+
+    /// 设置溢写文件大小上限
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+}
+
+/// 溢写统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpillStats {
+    /// 写入溢写文件的消息数
+    pub spilled: u64,
+    /// 从溢写文件重新接入的消息数
+    pub recovered: u64,
+    /// 因超出大小上限而丢弃的消息数
+    pub rejected: u64,
+    /// 溢写文件中待接入的消息数
+    pub pending_messages: u64,
+    /// 溢写文件中待接入的字节数
+    pub pending_bytes: u64,
+}
+
+/// 磁盘溢写缓冲区：长度前缀 + MessagePack记录，先进先出
+///
+/// 读取位置之前的空间在取空时整体回收，文件即将超出上限时把未读部分搬到文件头部
+#[derive(Debug)]
+pub struct SpillBuffer {
+    file: File,
+    read_pos: u64,
+    write_pos: u64,
+    max_bytes: u64,
+    stats: SpillStats,
+}
+
+impl SpillBuffer {
+    /// 打开溢写文件，保留上次运行残留的完整记录，丢弃末尾不完整的记录
+    pub fn open(config: &SpillConfig) -> Result<Self> {
+        if config.max_bytes <= LENGTH_PREFIX_BYTES {
+            return Err(VehicleError::ConfigError(format!(
+                "Spill max_bytes must exceed {} bytes",
+                LENGTH_PREFIX_BYTES
+            )));
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&config.path)?;
+
+        let file_len = file.metadata()?.len();
+        let mut write_pos = 0;
+        let mut pending_messages = 0;
+        let mut prefix = [0u8; LENGTH_PREFIX_BYTES as usize];
+        while write_pos + LENGTH_PREFIX_BYTES <= file_len {
+            file.seek(SeekFrom::Start(write_pos))?;
+            file.read_exact(&mut prefix)?;
+            let record_len = LENGTH_PREFIX_BYTES + u32::from_be_bytes(prefix) as u64;
+            if write_pos + record_len > file_len {
+                break;
+            }
+            write_pos += record_len;
+            pending_messages += 1;
+        }
+        if write_pos < file_len {
+            warn!("Discarding {} bytes of incomplete spill record", file_len - write_pos);
+            file.set_len(write_pos)?;
+        }
+        if pending_messages > 0 {
+            info!("Found {} spilled messages in {}", pending_messages, config.path.display());
+        }
+
+        Ok(Self {
+            file,
+            read_pos: 0,
+            write_pos,
+            max_bytes: config.max_bytes,
+            stats: SpillStats {
+                pending_messages,
+                pending_bytes: write_pos,
+                ..Default::default()
+            },
+        })
+    }
+
+    /// 追加一条消息，超出大小上限时返回`false`
+    pub fn push(&mut self, message: &VehicleMessage) -> Result<bool> {
+        let record = encode_length_prefixed(&message.to_msgpack()?);
+        let record_len = record.len() as u64;
+        if self.stats.pending_bytes + record_len > self.max_bytes {
+            self.stats.rejected += 1;
+            return Ok(false);
+        }
+        if self.write_pos + record_len > self.max_bytes {
+            self.compact()?;
+        }
+
+        self.file.seek(SeekFrom::Start(self.write_pos))?;
+        self.file.write_all(&record)?;
+        self.write_pos += record_len;
+        self.stats.spilled += 1;
+        self.stats.pending_messages += 1;
+        self.stats.pending_bytes += record_len;
+        Ok(true)
+    }
+
+    /// 取出最早的一条消息
+    ///
+    /// 记录无法解码时跳过该记录并返回错误；长度前缀损坏时无法定位后续记录，丢弃全部未读部分并返回错误；
+    /// 读取文件失败时不移动读取位置，可稍后重试
+    pub fn pop(&mut self) -> Result<Option<VehicleMessage>> {
+        if self.read_pos >= self.write_pos {
+            return Ok(None);
+        }
+
+        let mut prefix = [0u8; LENGTH_PREFIX_BYTES as usize];
+        self.file.seek(SeekFrom::Start(self.read_pos))?;
+        self.file.read_exact(&mut prefix)?;
+        let record_len = LENGTH_PREFIX_BYTES + u32::from_be_bytes(prefix) as u64;
+        if self.read_pos + record_len > self.write_pos {
+            let offset = self.read_pos;
+            let discarded = self.stats.pending_messages;
+            self.reset()?;
+            return Err(VehicleError::InvalidMessage(format!(
+                "Corrupt spill record length at offset {}, discarded {} pending messages",
+                offset, discarded
+            )));
+        }
+        let mut body = vec![0u8; (record_len - LENGTH_PREFIX_BYTES) as usize];
+        self.file.read_exact(&mut body)?;
+
+        self.read_pos += record_len;
+        self.stats.pending_messages -= 1;
+        self.stats.pending_bytes -= record_len;
+        if self.read_pos >= self.write_pos {
+            // 已取空，回收整个文件
+            self.reset()?;
+        }
+
+        let message = VehicleMessage::from_msgpack(&body)?;
+        self.stats.recovered += 1;
+        Ok(Some(message))
+    }
+
+    /// 是否没有待接入的消息
+    pub fn is_empty(&self) -> bool {
+        self.stats.pending_messages == 0
+    }
+
+    /// 获取溢写统计
+    pub fn stats(&self) -> SpillStats {
+        self.stats
+    }
+
+    /// 清空文件及待接入统计
+    fn reset(&mut self) -> Result<()> {
+        self.file.set_len(0)?;
+        self.read_pos = 0;
+        self.write_pos = 0;
+        self.stats.pending_messages = 0;
+        self.stats.pending_bytes = 0;
+        Ok(())
+    }
+
+    /// 把未读部分搬到文件头部，回收已读空间
+    fn compact(&mut self) -> Result<()> {
+        let mut live = vec![0u8; (self.write_pos - self.read_pos) as usize];
+        self.file.seek(SeekFrom::Start(self.read_pos))?;
+        self.file.read_exact(&mut live)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&live)?;
+        self.file.set_len(live.len() as u64)?;
+        self.read_pos = 0;
+        self.write_pos = live.len() as u64;
+        Ok(())
+    }
+}
+
+/// 溢写写入端：消息交给独立的写入线程编码并写盘（含文件压缩），调用方不执行文件I/O
+pub struct SpillWriter {
+    tx: Sender<VehicleMessage>,
+}
+
+impl SpillWriter {
+    /// 启动写入线程，写入失败或超出大小上限时以丢弃原因调用`on_reject`
+    ///
+    /// 所有写入端被丢弃后写入线程在写完剩余消息后退出
+    pub fn spawn(
+        buffer: Arc<Mutex<Option<SpillBuffer>>>,
+        on_reject: impl Fn(&'static str) + Send + 'static,
+    ) -> Result<Self> {
+        let (tx, rx) = crossbeam::channel::bounded::<VehicleMessage>(SPILL_WRITE_CAPACITY);
+        thread::Builder::new().name("spill-writer".to_string()).spawn(move || {
+            for message in rx {
+                let pushed = buffer.lock().as_mut().map(|buffer| buffer.push(&message));
+                match pushed {
+                    Some(Ok(true)) | None => {}
+                    Some(Ok(false)) => {
+                        warn!("Spill file full, dropping critical message");
+                        on_reject("spill full");
+                    }
+                    Some(Err(e)) => {
+                        error!("Failed to spill critical message: {}", e);
+                        on_reject("spill error");
+                    }
+                }
+            }
+        })?;
+        Ok(Self { tx })
+    }
+
+    /// 把消息交给写入线程，写入线程积压已满时返回`false`
+    pub fn try_write(&self, message: VehicleMessage) -> bool {
+        match self.tx.try_send(message) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                warn!("Spill writer backlog full, dropping critical message");
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spill_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("vehicle_spill_{}_{}.bin", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn message(vin: &str) -> VehicleMessage {
+        VehicleMessage::new("tracking".to_string(), vin.to_string(), 1234567890.0)
+    }
+
+    #[test]
+    fn test_spill_fifo_and_size_cap() {
+        let path = spill_path("fifo");
+        let record_len = encode_length_prefixed(&message("VIN_0").to_msgpack().unwrap()).len() as u64;
+        let config = SpillConfig::new(&path).with_max_bytes(record_len * 2);
+        let mut spill = SpillBuffer::open(&config).unwrap();
+
+        assert!(spill.push(&message("VIN_0")).unwrap());
+        assert!(spill.push(&message("VIN_1")).unwrap());
+        assert!(!spill.push(&message("VIN_2")).unwrap());
+        assert_eq!(spill.pop().unwrap().unwrap().vin, "VIN_0");
+
+        // 读出一条后腾出空间，写入时压缩文件而不超出上限
+        assert!(spill.push(&message("VIN_3")).unwrap());
+        assert!(std::fs::metadata(&path).unwrap().len() <= record_len * 2);
+        assert_eq!(spill.pop().unwrap().unwrap().vin, "VIN_1");
+        assert_eq!(spill.pop().unwrap().unwrap().vin, "VIN_3");
+        assert!(spill.pop().unwrap().is_none());
+
+        let stats = spill.stats();
+        assert_eq!((stats.spilled, stats.recovered, stats.rejected), (3, 3, 1));
+        assert_eq!(stats.pending_bytes, 0);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_spill_survives_reopen() {
+        let path = spill_path("reopen");
+        let config = SpillConfig::new(&path);
+        {
+            let mut spill = SpillBuffer::open(&config).unwrap();
+            spill.push(&message("VIN_1")).unwrap();
+        }
+        // 模拟写入中途退出留下的半条记录
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[0, 0, 1, 0, 42]).unwrap();
+
+        let mut spill = SpillBuffer::open(&config).unwrap();
+        assert_eq!(spill.stats().pending_messages, 1);
+        assert_eq!(spill.pop().unwrap().unwrap().vin, "VIN_1");
+        assert!(spill.is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_spill_corrupt_length_discards_remainder() {
+        let path = spill_path("corrupt");
+        let mut spill = SpillBuffer::open(&SpillConfig::new(&path)).unwrap();
+        spill.push(&message("VIN_1")).unwrap();
+        spill.push(&message("VIN_2")).unwrap();
+
+        // 损坏第一条记录的长度前缀，使其越过已写入的范围
+        let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.write_all(&[0xff, 0xff, 0xff, 0xff]).unwrap();
+
+        assert!(spill.pop().is_err());
+        assert!(spill.pop().unwrap().is_none());
+        assert!(spill.is_empty());
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,92 +1,491 @@
-#[cfg(test)]
-mod tests {
-    // use super::*;
-    use crate::types::*;
-    // use std::collections::HashMap;
-    
-    #[test]
-    fn test_vehicle_message_creation() {
-        let mut msg = VehicleMessage::new(
-            "tracking".to_string(),
-            "TEST_VIN_123".to_string(),
-            1234567890.0
-        );
-        
-        msg.channel = "tracking".to_string();
-        msg.params.insert("data".to_string(), serde_json::json!({"x": 1.0, "y": 2.0}));
-        
-        assert!(msg.is_valid());
-        assert_eq!(msg.service, "tracking");
-        assert_eq!(msg.vin, "TEST_VIN_123");
-        
-        let hash1 = msg.get_hash();
-        let hash2 = msg.get_hash();
-        assert_eq!(hash1, hash2); // 相同消息应该有相同hash
-    }
+// use super::*;
+use crate::types::*;
+// use std::collections::HashMap;
+
+#[test]
+fn test_vehicle_message_creation() {
+    let mut msg = VehicleMessage::new(
+        "tracking".to_string(),
+        "TEST_VIN_123".to_string(),
+        1234567890.0
+    );
     
-    #[test]
-    fn test_message_priority() {
-        assert_eq!(MessagePriority::from_service("tracking"), MessagePriority::Critical);
-        assert_eq!(MessagePriority::from_service("traj"), MessagePriority::Background);
-        assert_eq!(MessagePriority::from_service("vcc"), MessagePriority::Normal);
-        
-        assert!(MessagePriority::Critical.queue_capacity() > 0);
-        assert!(MessagePriority::Critical.processing_interval().as_micros() > 0);
-    }
+    msg.channel = "tracking".to_string();
+    msg.params.insert("data".to_string(), serde_json::json!({"x": 1.0, "y": 2.0}));
     
-    #[test]
-    fn test_sampling_config() {
-        let mut config = SamplingConfig::default();
-        
-        assert_eq!(config.get_rate("tracking"), 1.0);
-        assert_eq!(config.get_rate("traj"), 0.1);
-        
-        config.set_rate("custom_service", 0.5);
-        assert_eq!(config.get_rate("custom_service"), 0.5);
-        
-        // 测试边界值
-        config.set_rate("test", 1.5); // 应该被限制为1.0
-        assert_eq!(config.get_rate("test"), 1.0);
-        
-        config.set_rate("test", -0.5); // 应该被限制为0.0
-        assert_eq!(config.get_rate("test"), 0.0);
-    }
+    assert!(msg.is_valid());
+    assert_eq!(msg.service, "tracking");
+    assert_eq!(msg.vin, "TEST_VIN_123");
     
-    #[test]
-    fn test_processing_stats() {
-        let mut stats = ProcessingStats::new();
-        
-        stats.increment_received();
-        stats.increment_processed();
-        stats.update_processing_time(std::time::Duration::from_micros(1000));
-        stats.update_queue_size(50);
-        
-        assert_eq!(stats.messages_received, 1);
-        assert_eq!(stats.messages_processed, 1);
-        assert_eq!(stats.avg_processing_time_us, 1000);
-        assert_eq!(stats.queue_size, 50);
-        assert_eq!(stats.get_drop_rate(), 0.0);
-    }
+    let hash1 = msg.get_hash();
+    let hash2 = msg.get_hash();
+    assert_eq!(hash1, hash2); // 相同消息应该有相同hash
+}
+
+#[test]
+fn test_message_priority() {
+    assert_eq!(MessagePriority::from_service("tracking"), MessagePriority::Critical);
+    assert_eq!(MessagePriority::from_service("traj"), MessagePriority::Background);
+    assert_eq!(MessagePriority::from_service("vcc"), MessagePriority::Normal);
+    
+    assert!(MessagePriority::Critical.queue_capacity() > 0);
+    assert!(MessagePriority::Critical.processing_interval().as_micros() > 0);
+}
+
+#[test]
+fn test_sampling_config() {
+    let mut config = SamplingConfig::default();
+    
+    assert_eq!(config.get_rate("tracking"), 1.0);
+    assert_eq!(config.get_rate("traj"), 0.1);
+    
+    config.set_rate("custom_service", 0.5);
+    assert_eq!(config.get_rate("custom_service"), 0.5);
+    
+    // 测试边界值
+    config.set_rate("test", 1.5); // 应该被限制为1.0
+    assert_eq!(config.get_rate("test"), 1.0);
+    
+    config.set_rate("test", -0.5); // 应该被限制为0.0
+    assert_eq!(config.get_rate("test"), 0.0);
+}
+
+#[test]
+fn test_processing_stats() {
+    let mut stats = ProcessingStats::new();
+    
+    stats.increment_received();
+    stats.increment_processed();
+    stats.update_processing_time(std::time::Duration::from_micros(1000));
+    stats.update_queue_size(50);
+    
+    assert_eq!(stats.messages_received, 1);
+    assert_eq!(stats.messages_processed, 1);
+    assert_eq!(stats.avg_processing_time_us, 1000);
+    assert_eq!(stats.queue_size, 50);
+    assert_eq!(stats.get_drop_rate(), 0.0);
+}
+
+#[test]
+fn test_json_serialization() {
+    let mut msg = VehicleMessage::new(
+        "test".to_string(),
+        "VIN123".to_string(),
+        1234567890.0
+    );
+    
+    msg.params.insert("test_param".to_string(), serde_json::json!({"value": 42}));
+    
+    // 测试序列化
+    let json_str = serde_json::to_string(&msg).unwrap();
+    assert!(json_str.contains("test"));
+    assert!(json_str.contains("VIN123"));
+    
+    // 测试反序列化
+    let deserialized: VehicleMessage = serde_json::from_str(&json_str).unwrap();
+    assert_eq!(deserialized.service, msg.service);
+    assert_eq!(deserialized.vin, msg.vin);
+    assert_eq!(deserialized.timestamp, msg.timestamp);
+}
+
+#[test]
+fn test_priority_vin_bypasses_sampling() {
+    let mut config = SamplingConfig::default();
+    config.set_rate("traj", 0.0);
+    
+    assert!(!config.should_process_vin("traj", "DEBUG_VIN"));
+    
+    config.add_priority_vin("DEBUG_VIN");
+    assert!(config.is_priority_vin("DEBUG_VIN"));
+    assert!(config.should_process_vin("traj", "DEBUG_VIN"));
+    assert!(!config.should_process_vin("traj", "OTHER_VIN"));
     
-    #[test]
-    fn test_json_serialization() {
-        let mut msg = VehicleMessage::new(
-            "test".to_string(),
-            "VIN123".to_string(),
-            1234567890.0
-        );
-        
-        msg.params.insert("test_param".to_string(), serde_json::json!({"value": 42}));
-        
-        // 测试序列化
-        let json_str = serde_json::to_string(&msg).unwrap();
-        assert!(json_str.contains("test"));
-        assert!(json_str.contains("VIN123"));
-        
-        // 测试反序列化
-        let deserialized: VehicleMessage = serde_json::from_str(&json_str).unwrap();
-        assert_eq!(deserialized.service, msg.service);
-        assert_eq!(deserialized.vin, msg.vin);
-        assert_eq!(deserialized.timestamp, msg.timestamp);
+    assert!(config.remove_priority_vin("DEBUG_VIN"));
+    assert!(!config.remove_priority_vin("DEBUG_VIN"));
+    assert!(!config.should_process_vin("traj", "DEBUG_VIN"));
+}
+
+#[test]
+fn test_geo_filter() {
+    let filter = GeoFilter::new(0.0, 10.0, 0.0, 10.0);
+    
+    let mut inside = VehicleMessage::new("tracking".to_string(), "VIN1".to_string(), 1.0);
+    inside.params.insert("data".to_string(), serde_json::json!({"x": 5.0, "y": 5.0}));
+    assert!(filter.allows(&inside));
+    
+    let mut outside = VehicleMessage::new("traj".to_string(), "VIN1".to_string(), 1.0);
+    outside.params.insert("data".to_string(), serde_json::json!({"x": 50.0, "y": 5.0}));
+    assert!(!filter.allows(&outside));
+    
+    // 缺少坐标不过滤
+    let mut no_coords = VehicleMessage::new("tracking".to_string(), "VIN1".to_string(), 1.0);
+    no_coords.params.insert("data".to_string(), serde_json::json!({"speed": 30.0}));
+    assert!(filter.allows(&no_coords));
+    
+    // 非位置服务不过滤
+    let mut vcc = VehicleMessage::new("vcc".to_string(), "VIN1".to_string(), 1.0);
+    vcc.params.insert("data".to_string(), serde_json::json!({"x": 50.0, "y": 50.0}));
+    assert!(filter.allows(&vcc));
+}
+
+#[test]
+fn test_params_preserve_insertion_order() {
+    let mut msg = VehicleMessage::new("tracking".to_string(), "VIN123".to_string(), 1.0);
+    msg.params.insert("zeta".to_string(), serde_json::json!(1));
+    msg.params.insert("alpha".to_string(), serde_json::json!(2));
+    msg.params.insert("data".to_string(), serde_json::json!({"y": 2.0, "x": 1.0}));
+    
+    let json_str = serde_json::to_string(&msg).unwrap();
+    assert!(json_str.contains(r#""params":{"zeta":1,"alpha":2,"data":{"y":2.0,"x":1.0}}"#));
+    
+    // 往返后字段顺序和序列化结果保持不变
+    let round_tripped: VehicleMessage = serde_json::from_str(&json_str).unwrap();
+    let keys: Vec<&str> = round_tripped.params.keys().map(|k| k.as_str()).collect();
+    assert_eq!(keys, vec!["zeta", "alpha", "data"]);
+    assert_eq!(serde_json::to_string(&round_tripped).unwrap(), json_str);
+    assert_eq!(round_tripped.get_hash(), msg.get_hash());
+}
+
+#[test]
+fn test_hash_independent_of_key_order() {
+    let mut msg1 = VehicleMessage::new("tracking".to_string(), "VIN123".to_string(), 1.0);
+    msg1.params.insert("data".to_string(), serde_json::json!({"x": 1, "y": {"a": 1, "b": 2}}));
+    let mut msg2 = VehicleMessage::new("tracking".to_string(), "VIN123".to_string(), 1.0);
+    msg2.params.insert("data".to_string(), serde_json::json!({"y": {"b": 2, "a": 1}, "x": 1}));
+    
+    // 保留插入顺序后，去重hash仍与键顺序无关
+    assert_eq!(msg1.get_hash(), msg2.get_hash());
+    let fields = DedupKeyConfig::builder().data_field("y").build();
+    assert_eq!(msg1.hash_with(&fields), msg2.hash_with(&fields));
+}
+
+#[test]
+fn test_dedup_key_config() {
+    let mut msg1 = VehicleMessage::new("tracking".to_string(), "VIN123".to_string(), 100.0);
+    msg1.params.insert("data".to_string(), serde_json::json!({"id": 7, "x": 1.0}));
+    let mut msg2 = msg1.clone();
+    msg2.timestamp = 200.0;
+    msg2.params.insert("data".to_string(), serde_json::json!({"id": 7, "x": 2.0}));
+    
+    // 默认配置与get_hash一致
+    assert_eq!(msg1.hash_with(&DedupKeyConfig::default()), msg1.get_hash());
+    assert_ne!(msg1.get_hash(), msg2.get_hash());
+    
+    // 忽略时间戳，只按data.id去重
+    let config = DedupKeyConfig::builder()
+        .timestamp(false)
+        .data_field("id")
+        .build();
+    assert_eq!(config.data, DedupDataKey::Fields(vec!["id".to_string()]));
+    assert_eq!(msg1.hash_with(&config), msg2.hash_with(&config));
+    
+    // VIN不同则不视为重复
+    let mut msg3 = msg2.clone();
+    msg3.vin = "VIN456".to_string();
+    assert_ne!(msg1.hash_with(&config), msg3.hash_with(&config));
+}
+
+#[test]
+fn test_stats_delta() {
+    let prev = ProcessingStats {
+        messages_received: 100,
+        messages_processed: 90,
+        messages_dropped: 10,
+        ..Default::default()
+    };
+    let current = ProcessingStats {
+        messages_received: 300,
+        messages_processed: 270,
+        messages_dropped: 30,
+        ..Default::default()
+    };
+    
+    let delta = current.delta(&prev, std::time::Duration::from_secs(2));
+    assert_eq!(delta.received, 200);
+    assert_eq!(delta.processed, 180);
+    assert_eq!(delta.dropped, 20);
+    assert_eq!(delta.received_per_sec, 100.0);
+    assert_eq!(delta.processed_per_sec, 90.0);
+    assert_eq!(delta.dropped_per_sec, 10.0);
+    assert_eq!(delta.drop_rate, 0.1);
+    
+    // 计数被重置时不下溢
+    let reset = current.delta(&ProcessingStats { messages_received: 500, ..Default::default() }, std::time::Duration::ZERO);
+    assert_eq!(reset.received, 0);
+    assert_eq!(reset.received_per_sec, 0.0);
+}
+
+#[test]
+fn test_sampling_patterns() {
+    let mut config = SamplingConfig::default();
+    config.set_glob_rate("sensor_*", 0.2).unwrap();
+    config.set_glob_rate("sensor_lidar_*", 0.05).unwrap();
+    config.set_regex_rate(r"^cam\d+$", 0.5).unwrap();
+    
+    assert_eq!(config.get_rate("sensor_radar_front"), 0.2);
+    // 更具体的模式优先
+    assert_eq!(config.get_rate("sensor_lidar_top"), 0.05);
+    assert_eq!(config.get_rate("cam12"), 0.5);
+    assert_eq!(config.get_rate("camera"), 1.0);
+    
+    // 精确匹配优先于模式
+    config.set_rate("sensor_lidar_top", 0.9);
+    assert_eq!(config.get_rate("sensor_lidar_top"), 0.9);
+    
+    // 替换已有模式
+    config.set_glob_rate("sensor_*", 0.3).unwrap();
+    assert_eq!(config.patterns.len(), 3);
+    assert_eq!(config.get_rate("sensor_radar_front"), 0.3);
+    
+    assert!(config.set_regex_rate("(unclosed", 0.5).is_err());
+}
+
+#[test]
+fn test_reply_carries_correlation_id() {
+    let mut request = VehicleMessage::new(
+        "route".to_string(),
+        "TEST_VIN_123".to_string(),
+        1234567890.0,
+    );
+    request.channel = "route".to_string();
+    request.correlation_id = Some("42".to_string());
+
+    let reply = request.reply(1234567891.0, serde_json::json!({"status": "ok"}));
+    assert_eq!(reply.correlation_id.as_deref(), Some("42"));
+    assert_eq!(reply.params["id"], "42");
+    assert_eq!(reply.vin, "TEST_VIN_123");
+    assert_eq!(reply.channel, "route");
+    assert_eq!(reply.timestamp, 1234567891.0);
+
+    // 未携带关联ID的消息序列化时不输出该字段
+    let json = serde_json::to_string(&VehicleMessage::new("route".to_string(), "V".to_string(), 1.0)).unwrap();
+    assert!(!json.contains("correlation_id"));
+}
+
+#[test]
+fn test_validate_reports_reason() {
+    let mut message = VehicleMessage::new("tracking".to_string(), "".to_string(), 1234567890.0);
+    message.params.insert("data".to_string(), serde_json::json!({}));
+    assert_eq!(message.validate(), Err("missing vin"));
+
+    message.vin = "TEST_VIN_123".to_string();
+    assert!(message.validate().is_ok());
+
+    message.params.clear();
+    assert_eq!(message.validate(), Err("missing data"));
+}
+
+#[test]
+fn test_default_sampling_rate() {
+    let mut config = SamplingConfig::default();
+    assert_eq!(config.get_rate("new_service"), 1.0);
+
+    config.set_default_rate(0.25);
+    assert_eq!(config.get_rate("new_service"), 0.25);
+    // 已配置的服务和模式不受影响
+    assert_eq!(config.get_rate("tracking"), 1.0);
+    config.set_glob_rate("new_*", 0.5).unwrap();
+    assert_eq!(config.get_rate("new_service"), 0.5);
+
+    config.set_default_rate(2.0);
+    assert_eq!(config.default_rate, 1.0);
+}
+
+#[test]
+fn test_service_kind() {
+    assert_eq!(Service::from("tracking"), Service::Tracking);
+    assert_eq!(Service::from("loc_stat"), Service::LocStat);
+    assert_eq!(Service::from("sensor_gps"), Service::Other("sensor_gps".to_string()));
+    assert_eq!(Service::ErrorInfo.to_string(), "error_info");
+    assert!(!Service::from("sensor_gps").is_known());
+
+    let message = VehicleMessage::new("traj".to_string(), "TEST_VIN_123".to_string(), 1.0);
+    assert_eq!(message.service_kind(), Service::Traj);
+}
+
+#[test]
+fn test_hash_keeps_sub_second_timestamp() {
+    let mut first = VehicleMessage::new("tracking".to_string(), "TEST_VIN_123".to_string(), 1234567890.1);
+    first.params.insert("data".to_string(), serde_json::json!({"x": 1.0}));
+    let mut second = first.clone();
+    second.timestamp = 1234567890.2;
+
+    assert_ne!(first.get_hash(), second.get_hash());
+    assert_eq!(first.get_hash(), first.clone().get_hash());
+}
+
+#[test]
+fn test_dedup_config_applies_to() {
+    let mut config = DedupConfig::default();
+    assert!(config.applies_to("tracking"));
+
+    config.disabled_services.insert("heartbeat".to_string());
+    assert!(!config.applies_to("heartbeat"));
+    assert!(config.applies_to("tracking"));
+
+    config.enabled = false;
+    assert!(!config.applies_to("tracking"));
+
+}
+
+#[test]
+fn test_materialize_lazy_data() {
+    let mut message = VehicleMessage::new("tracking".to_string(), "VIN".to_string(), 1.0);
+    message.lazy_params = Some(std::sync::Arc::new(serde_json::json!({"data": {"speed": 10}})));
+    assert!(message.is_valid());
+
+    message.materialize_data();
+    let json = serde_json::to_string(&message).unwrap();
+    assert!(json.contains("speed"));
+}
+
+#[test]
+fn test_data_optional_services() {
+    let message = VehicleMessage::new("error_info".to_string(), "VIN".to_string(), 1.0);
+    assert_eq!(message.validate(), Err("missing data"));
+
+    let mut config = ValidationConfig::default();
+    config.data_optional_services.insert("error_info".to_string());
+    assert_eq!(message.validate_with(&config), Ok(()));
+
+    let tracking = VehicleMessage::new("tracking".to_string(), "VIN".to_string(), 1.0);
+    assert_eq!(tracking.validate_with(&config), Err("missing data"));
+}
+
+#[test]
+fn test_scene_filter_missing_scene() {
+    let filter = SceneFilter::allow(["production"]);
+    let mut message = VehicleMessage::new("tracking".to_string(), "VIN".to_string(), 1.0);
+    assert!(!filter.allows(&message));
+    assert!(filter.clone().with_allow_missing(true).allows(&message));
+
+    message.run_scene = Some("production".to_string());
+    assert!(filter.allows(&message));
+}
+
+#[test]
+fn test_processing_rate_uses_window() {
+    let mut stats = ProcessingStats::new();
+    let start = stats.window_start.unwrap();
+    for _ in 0..100 {
+        stats.increment_processed();
     }
-}
\ No newline at end of file
+
+    // 频繁更新last_update不影响速率
+    let rate = stats.processing_rate_at(start + std::time::Duration::from_secs(2));
+    assert!((rate - 50.0).abs() < 1e-9);
+    assert_eq!(ProcessingStats::default().get_processing_rate(), 0.0);
+}
+
+#[test]
+fn test_field_paths_reject_invalid_pointers() {
+    let paths = FieldPaths::default().with_vin("/params/vehicle/vin").unwrap();
+    assert_eq!(paths.vin(), "/params/vehicle/vin");
+    assert_eq!(paths.service(), "/service");
+
+    assert!(FieldPaths::default().with_vin("params/vin").is_err());
+    assert!(FieldPaths::default().with_timestamp("/params/t~2").is_err());
+    assert!(FieldPaths::default().with_service("/meta/a~1b").is_ok());
+}
+
+#[test]
+fn test_msgpack_round_trip() {
+    let mut message = VehicleMessage::new("tracking".to_string(), "VIN_1".to_string(), 1234567890.5);
+    message.params.insert("data".to_string(), serde_json::json!({"x": 1.5, "y": [1, 2], "ok": true}));
+    message.run_scene = Some("production".to_string());
+    message.origin = TimestampOrigin::Backfilled;
+
+    let encoded = message.to_msgpack().unwrap();
+    assert!(encoded.len() < serde_json::to_vec(&message).unwrap().len());
+
+    let decoded = VehicleMessage::from_msgpack(&encoded).unwrap();
+    assert_eq!(decoded.vin, "VIN_1");
+    assert_eq!(decoded.timestamp, 1234567890.5);
+    assert_eq!(decoded.data(), message.data());
+    assert_eq!(decoded.run_scene.as_deref(), Some("production"));
+    assert_eq!(decoded.correlation_id, None);
+    assert_eq!(decoded.origin, TimestampOrigin::Backfilled);
+    assert!(VehicleMessage::from_msgpack(b"\xc1").is_err());
+}
+
+#[test]
+fn test_msgpack_includes_lazy_data() {
+    let mut message = VehicleMessage::new("tracking".to_string(), "VIN_1".to_string(), 1.0);
+    message.lazy_params = Some(std::sync::Arc::new(serde_json::json!({"vin": "VIN_1", "data": {"speed": 42.0}})));
+
+    let decoded = VehicleMessage::from_msgpack(&message.to_msgpack().unwrap()).unwrap();
+    assert_eq!(decoded.data(), Some(&serde_json::json!({"speed": 42.0})));
+}
+
+#[test]
+fn test_restore_stats_from_previous_run() {
+    let mut previous = ProcessingStats::new();
+    previous.messages_received = 100;
+    previous.messages_processed = 90;
+    previous.processed_critical = 90;
+    previous.queue_size = 12;
+    previous.messages_dispatched = 95;
+
+    let mut current = ProcessingStats::new();
+    current.messages_received = 5;
+    current.queue_size = 3;
+    current.restore_from(&previous);
+
+    assert_eq!(current.messages_received, 105);
+    assert_eq!(current.messages_processed, 90);
+    assert_eq!(current.processed_critical, 90);
+    // 队列大小和本次运行的计数不累加
+    assert_eq!(current.queue_size, 3);
+    assert_eq!(current.messages_dispatched, 0);
+}
+
+#[test]
+fn test_service_codes_bidirectional() {
+    let codes = ServiceCodes::new()
+        .with_code(1, "tracking")
+        .unwrap()
+        .with_code(2, "route")
+        .unwrap();
+    assert_eq!(codes.name(2), Some("route"));
+    assert_eq!(codes.code("tracking"), Some(1));
+    assert_eq!(codes.name(3), None);
+    assert_eq!(codes.len(), 2);
+
+    assert!(codes.clone().with_code(1, "vcc").is_err());
+    assert!(codes.with_code(3, "route").is_err());
+}
+
+#[test]
+fn test_in_flight_clamped_at_zero() {
+    let mut stats = ProcessingStats::new();
+    stats.messages_received = 10;
+    stats.messages_processed = 6;
+    stats.messages_dropped = 1;
+    assert_eq!(stats.in_flight(), 3);
+
+    // 接入前丢弃的消息不计入接收，差值不足零时按零计
+    stats.messages_dropped = 8;
+    assert_eq!(stats.in_flight(), 0);
+}
+
+#[test]
+fn test_sampling_strategies() {
+    let mut config = SamplingConfig::default();
+    config.set_strategy("traj", SamplingStrategy::EveryNth(4)).unwrap();
+    let kept = (0..100).filter(|_| config.should_process_vin("traj", "VIN_1")).count();
+    assert_eq!(kept, 25);
+    assert_eq!(config.get_rate("traj"), 0.25);
+
+    // 同一VIN的判定始终一致
+    config.set_strategy("device", SamplingStrategy::HashMod(10, 3)).unwrap();
+    let first = config.should_process_vin("device", "VIN_42");
+    assert!((0..10).all(|_| config.should_process_vin("device", "VIN_42") == first));
+    let kept = (0..1000)
+        .filter(|i| config.should_process_vin("device", &format!("VIN_{}", i)))
+        .count();
+    assert!((200..400).contains(&kept));
+
+    assert!(config.set_strategy("traj", SamplingStrategy::EveryNth(0)).is_err());
+    config.set_rate("traj", 1.0);
+    assert_eq!(config.get_strategy("traj"), SamplingStrategy::Probabilistic(1.0));
+}
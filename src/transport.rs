@@ -0,0 +1,160 @@
+use crate::error::{NanomsgErrorKind, Result, VehicleError};
+
+use crossbeam::channel::{Receiver, Sender, TrySendError};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use tracing::info;
+
+/// 进程内传输的URL前缀
+pub const INPROC_SCHEME: &str = "inproc://";
+
+/// 每个进程内端点缓存的最大帧数
+const INPROC_CHANNEL_CAPACITY: usize = 4096;
+
+/// 客户端接收消息使用的传输层
+pub trait Transport: Send + Sync {
+    /// 绑定到URL
+    fn bind(&mut self, url: &str) -> Result<()>;
+
+    /// 非阻塞接收一帧，暂无消息时返回`WouldBlock`
+    fn recv(&mut self, buffer: &mut [u8]) -> Result<usize>;
+
+    /// 确认最近一次`recv`返回的帧已由处理器接收或有意丢弃（过滤、采样、去重），无需重放
+    ///
+    /// 需要确认的传输（如Redis消费者组）在此提交确认，未确认的帧可在重新连接时重放；默认不做任何事
+    fn ack(&mut self) {}
+
+    /// 最近一次`recv`返回的帧无法处理（解析或校验失败、超大），重放也不会成功
+    ///
+    /// 默认与`ack`相同，避免无效帧在每次重连时反复重放
+    fn reject(&mut self) {
+        self.ack();
+    }
+
+    /// 关闭连接
+    fn close(&mut self);
+}
+
+/// 进程内端点：发送端与接收端通过同一URL在注册表中汇合
+struct InprocEndpoint {
+    tx: Sender<Vec<u8>>,
+    rx: Receiver<Vec<u8>>,
+}
+
+/// 进程内端点注册表（URL -> 端点）
+static INPROC_REGISTRY: LazyLock<Mutex<HashMap<String, InprocEndpoint>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 获取URL对应的端点通道，不存在时创建；先启动发送端或接收端均可
+fn inproc_endpoint(url: &str) -> (Sender<Vec<u8>>, Receiver<Vec<u8>>) {
+    let mut registry = INPROC_REGISTRY.lock();
+    let endpoint = registry.entry(url.to_string()).or_insert_with(|| {
+        let (tx, rx) = crossbeam::channel::bounded(INPROC_CHANNEL_CAPACITY);
+        InprocEndpoint { tx, rx }
+    });
+    (endpoint.tx.clone(), endpoint.rx.clone())
+}
+
+fn check_inproc_url(url: &str) -> Result<()> {
+    if is_inproc_url(url) {
+        Ok(())
+    } else {
+        Err(VehicleError::nanomsg(
+            NanomsgErrorKind::BindFailed,
+            format!("Not an inproc url: {}", url),
+        ))
+    }
+}
+
+/// 检查URL是否为进程内传输
+pub fn is_inproc_url(url: &str) -> bool {
+    url.starts_with(INPROC_SCHEME)
+}
+
+/// 进程内传输的接收端，不经过操作系统socket，用于测试和同进程的生产者
+#[derive(Default)]
+pub struct InprocTransport {
+    rx: Option<Receiver<Vec<u8>>>,
+}
+
+impl InprocTransport {
+    /// 创建未绑定的接收端
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Transport for InprocTransport {
+    fn bind(&mut self, url: &str) -> Result<()> {
+        check_inproc_url(url)?;
+        self.rx = Some(inproc_endpoint(url).1);
+        info!("Inproc transport bound to: {}", url);
+        Ok(())
+    }
+
+    fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        let Some(ref rx) = self.rx else {
+            return Err(VehicleError::nanomsg(NanomsgErrorKind::NotConnected, "Inproc transport not bound"));
+        };
+
+        match rx.try_recv() {
+            Ok(frame) => {
+                let copy_len = frame.len().min(buffer.len());
+                buffer[..copy_len].copy_from_slice(&frame[..copy_len]);
+                Ok(copy_len)
+            }
+            Err(_) => Err(VehicleError::WouldBlock),
+        }
+    }
+
+    fn close(&mut self) {
+        self.rx = None;
+    }
+}
+
+/// 进程内传输的发送端
+#[derive(Clone)]
+pub struct InprocSender {
+    tx: Sender<Vec<u8>>,
+}
+
+impl InprocSender {
+    /// 连接到进程内URL，接收端可以稍后绑定
+    pub fn connect(url: &str) -> Result<Self> {
+        check_inproc_url(url)?;
+        Ok(Self { tx: inproc_endpoint(url).0 })
+    }
+
+    /// 发送一帧，端点缓存已满时返回`QueueFull`
+    pub fn send(&self, frame: &[u8]) -> Result<()> {
+        self.tx.try_send(frame.to_vec()).map_err(|e| match e {
+            TrySendError::Full(_) => VehicleError::QueueFull,
+            TrySendError::Disconnected(_) => {
+                VehicleError::nanomsg(NanomsgErrorKind::ConnectionReset, "Inproc endpoint closed")
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inproc_rendezvous() {
+        let url = "inproc://transport-test";
+        let sender = InprocSender::connect(url).unwrap();
+        sender.send(b"first").unwrap();
+
+        let mut transport = InprocTransport::new();
+        let mut buffer = [0u8; 16];
+        assert!(transport.recv(&mut buffer).is_err());
+        transport.bind(url).unwrap();
+
+        let len = transport.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..len], b"first");
+        assert!(matches!(transport.recv(&mut buffer), Err(VehicleError::WouldBlock)));
+        assert!(InprocSender::connect("ipc:///tmp/not-inproc.ipc").is_err());
+    }
+}
@@ -1,5 +1,13 @@
+use crate::error::{Result, VehicleError};
+
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use indexmap::IndexMap;
+use regex::Regex;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 /// 车辆消息结构
@@ -11,12 +19,34 @@ pub struct VehicleMessage {
     pub vin: String,
     /// 消息时间戳
     pub timestamp: f64,
-    /// 消息参数
-    pub params: HashMap<String, serde_json::Value>,
+    /// 消息参数（保持插入顺序，保证序列化输出稳定）
+    pub params: IndexMap<String, serde_json::Value>,
     /// 消息通道
     pub channel: String,
     /// 运行场景
     pub run_scene: Option<String>,
+    /// 请求/应答关联ID，取自`params.id`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    /// 时间戳来源
+    #[serde(default)]
+    pub origin: TimestampOrigin,
+    /// 消息来源标识，由接收端在提交时填充（如配置的来源ID），用于多生产者场景的来源归属
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// 延迟取数模式下共享的原始params，字段通过`param`/`data`按需读取，不参与序列化
+    #[serde(skip)]
+    pub lazy_params: Option<Arc<serde_json::Value>>,
+}
+
+/// 消息时间戳来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TimestampOrigin {
+    /// 消息自带的事件时间
+    #[default]
+    Provided,
+    /// 消息缺少时间戳，使用到达时间补全
+    Backfilled,
 }
 
 impl VehicleMessage {
@@ -26,25 +56,114 @@ impl VehicleMessage {
             service,
             vin,
             timestamp,
-            params: HashMap::new(),
+            params: IndexMap::new(),
             channel: String::new(),
             run_scene: None,
+            correlation_id: None,
+            origin: TimestampOrigin::Provided,
+            source: None,
+            lazy_params: None,
         }
     }
     
+    /// 读取参数字段，优先使用已展开的`params`，其次读取共享的原始params
+    pub fn param(&self, key: &str) -> Option<&serde_json::Value> {
+        self.params
+            .get(key)
+            .or_else(|| self.lazy_params.as_ref().and_then(|raw| raw.get(key)))
+    }
+    
+    /// 读取`data`字段
+    pub fn data(&self) -> Option<&serde_json::Value> {
+        self.param("data")
+    }
+    
+    /// 将共享原始params中的`data`展开到`params`，序列化前调用以保留数据
+    pub fn materialize_data(&mut self) {
+        if self.params.contains_key("data") {
+            return;
+        }
+        if let Some(data) = self.lazy_params.as_ref().and_then(|raw| raw.get("data")) {
+            let data = data.clone();
+            self.params.insert("data".to_string(), data);
+        }
+    }
+    
+    /// 编码为MessagePack（字段以名称为键），比JSON更紧凑，用于转发和FFI
+    ///
+    /// 延迟取数模式下的`data`会一并编码
+    pub fn to_msgpack(&self) -> Result<Vec<u8>> {
+        let encode = |message: &VehicleMessage| {
+            rmp_serde::to_vec_named(message).map_err(|e| VehicleError::MsgPackError(e.to_string()))
+        };
+        if self.lazy_params.is_some() && !self.params.contains_key("data") {
+            let mut message = self.clone();
+            message.materialize_data();
+            return encode(&message);
+        }
+        encode(self)
+    }
+    
+    /// 从MessagePack解码
+    pub fn from_msgpack(bytes: &[u8]) -> Result<Self> {
+        rmp_serde::from_slice(bytes).map_err(|e| VehicleError::MsgPackError(e.to_string()))
+    }
+    
+    /// 构造对本消息的应答，携带相同的服务、VIN、通道和关联ID
+    pub fn reply(&self, timestamp: f64, data: serde_json::Value) -> VehicleMessage {
+        let mut reply = VehicleMessage::new(self.service.clone(), self.vin.clone(), timestamp);
+        reply.channel = self.channel.clone();
+        reply.run_scene = self.run_scene.clone();
+        if let Some(ref id) = self.correlation_id {
+            reply.params.insert("id".to_string(), serde_json::Value::String(id.clone()));
+            reply.correlation_id = Some(id.clone());
+        }
+        reply.params.insert("data".to_string(), data);
+        reply
+    }
+    
+    /// 获取服务类型
+    pub fn service_kind(&self) -> Service {
+        Service::from(self.service.as_str())
+    }
+    
     /// 获取消息的唯一标识符（用于去重）
     pub fn get_hash(&self) -> u64 {
+        self.hash_with(&DedupKeyConfig::default())
+    }
+    
+    /// 按去重键配置计算消息hash
+    pub fn hash_with(&self, config: &DedupKeyConfig) -> u64 {
         use std::collections::hash_map::DefaultHasher;
         use std::hash::{Hash, Hasher};
         
         let mut hasher = DefaultHasher::new();
-        self.service.hash(&mut hasher);
-        self.vin.hash(&mut hasher);
-        (self.timestamp as u64).hash(&mut hasher);
+        if config.include_service {
+            self.service.hash(&mut hasher);
+        }
+        if config.include_vin {
+            self.vin.hash(&mut hasher);
+        }
+        if config.include_timestamp {
+            // 使用完整的位表示，保留亚秒精度
+            self.timestamp.to_bits().hash(&mut hasher);
+        }
         
         // 对关键参数进行hash
-        if let Some(data) = self.params.get("data") {
-            format!("{:?}", data).hash(&mut hasher);
+        if let Some(data) = self.data() {
+            match config.data {
+                DedupDataKey::Ignore => {}
+                DedupDataKey::Full => hash_json(data, &mut hasher),
+                DedupDataKey::Fields(ref fields) => {
+                    for field in fields {
+                        field.hash(&mut hasher);
+                        match data.get(field) {
+                            Some(value) => hash_json(value, &mut hasher),
+                            None => u8::MAX.hash(&mut hasher),
+                        }
+                    }
+                }
+            }
         }
         
         hasher.finish()
@@ -52,30 +171,619 @@ impl VehicleMessage {
     
     /// 检查消息是否有效
     pub fn is_valid(&self) -> bool {
-        !self.service.is_empty() && 
-        !self.vin.is_empty() && 
-        self.timestamp > 0.0 &&
-        self.params.contains_key("data")
+        self.validate().is_ok()
+    }
+    
+    /// 校验消息，失败时返回具体原因（同时用作丢弃原因）
+    pub fn validate(&self) -> std::result::Result<(), &'static str> {
+        self.validate_with(&ValidationConfig::default())
+    }
+    
+    /// 按校验配置校验消息
+    pub fn validate_with(&self, config: &ValidationConfig) -> std::result::Result<(), &'static str> {
+        if self.service.is_empty() {
+            return Err("missing service");
+        }
+        if self.vin.trim().is_empty() {
+            return Err("missing vin");
+        }
+        if self.timestamp <= 0.0 {
+            return Err("invalid timestamp");
+        }
+        if self.data().is_none() && config.requires_data(&self.service) {
+            return Err("missing data");
+        }
+        Ok(())
+    }
+}
+
+/// 去重键中data字段的参与方式
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum DedupDataKey {
+    /// 不使用data
+    Ignore,
+    /// 使用整个data
+    #[default]
+    Full,
+    /// 只使用data中的指定字段
+    Fields(Vec<String>),
+}
+
+/// 未注册回调时出队消息的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoCallbackPolicy {
+    /// 只记录统计后丢弃（计为处理成功）
+    #[default]
+    Discard,
+    /// 最多缓存n条，注册回调时按出队顺序回放，缓存满后的消息计为丢弃
+    BufferUpTo(usize),
+    /// 计为处理失败并丢弃
+    Error,
+}
+
+/// 以规范形式hash JSON值：对象按键排序，结果与键的插入顺序无关
+fn hash_json<H: Hasher>(value: &serde_json::Value, hasher: &mut H) {
+    match value {
+        serde_json::Value::Null => 0u8.hash(hasher),
+        serde_json::Value::Bool(b) => {
+            1u8.hash(hasher);
+            b.hash(hasher);
+        }
+        serde_json::Value::Number(n) => {
+            2u8.hash(hasher);
+            if let Some(i) = n.as_i64() {
+                i.hash(hasher);
+            } else if let Some(u) = n.as_u64() {
+                u.hash(hasher);
+            } else {
+                n.as_f64().map(f64::to_bits).hash(hasher);
+            }
+        }
+        serde_json::Value::String(s) => {
+            3u8.hash(hasher);
+            s.hash(hasher);
+        }
+        serde_json::Value::Array(items) => {
+            4u8.hash(hasher);
+            items.len().hash(hasher);
+            for item in items {
+                hash_json(item, hasher);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            5u8.hash(hasher);
+            map.len().hash(hasher);
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_unstable_by_key(|(key, _)| *key);
+            for (key, value) in entries {
+                key.hash(hasher);
+                hash_json(value, hasher);
+            }
+        }
+    }
+}
+
+/// 去重键配置：指定哪些字段参与去重hash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DedupKeyConfig {
+    pub include_service: bool,
+    pub include_vin: bool,
+    pub include_timestamp: bool,
+    pub data: DedupDataKey,
+}
+
+impl Default for DedupKeyConfig {
+    fn default() -> Self {
+        Self {
+            include_service: true,
+            include_vin: true,
+            include_timestamp: true,
+            data: DedupDataKey::Full,
+        }
+    }
+}
+
+impl DedupKeyConfig {
+    /// 从默认配置（service+vin+timestamp+data）开始构建
+    pub fn builder() -> DedupKeyConfigBuilder {
+        DedupKeyConfigBuilder {
+            config: DedupKeyConfig::default(),
+        }
+    }
+}
+
+/// 去重配置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DedupConfig {
+    /// 是否启用去重，关闭时不检查重复也不运行缓存清理任务
+    pub enabled: bool,
+    /// 不做去重的服务（如有意发送相同内容的心跳）
+    pub disabled_services: HashSet<String>,
+    /// 重复时仍放行的服务（默认error_info），消息照常写入去重缓存，但不会因命中缓存被丢弃
+    ///
+    /// 用于重复本身有意义的关键事件（如连续上报的同一故障）
+    pub dedup_bypass: HashSet<String>,
+    /// 去重缓存清理间隔
+    pub cleanup_interval: Duration,
+    /// 去重缓存条目保留时长
+    pub retention: Duration,
+    /// 每次清理最多淘汰的条目数，超出部分留待下次清理，限制超大缓存的清理耗时；None时不限制
+    pub max_evictions_per_pass: Option<usize>,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            disabled_services: HashSet::new(),
+            dedup_bypass: HashSet::from([Service::ErrorInfo.to_string()]),
+            cleanup_interval: Duration::from_secs(60),
+            retention: Duration::from_secs(300),
+            max_evictions_per_pass: None,
+        }
+    }
+}
+
+impl DedupConfig {
+    /// 检查服务是否需要去重
+    pub fn applies_to(&self, service: &str) -> bool {
+        self.enabled && !self.disabled_services.contains(service)
+    }
+    
+    /// 检查服务的重复消息是否放行
+    pub fn bypasses(&self, service: &str) -> bool {
+        self.dedup_bypass.contains(service)
+    }
+}
+
+/// 消息校验配置
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationConfig {
+    /// 允许不携带`data`的服务（如仅上报错误码的error_info），其余服务缺少`data`视为无效
+    pub data_optional_services: HashSet<String>,
+}
+
+impl ValidationConfig {
+    /// 检查服务是否要求携带`data`
+    pub fn requires_data(&self, service: &str) -> bool {
+        !self.data_optional_services.contains(service)
+    }
+}
+
+/// 接入帧的字段位置（JSON Pointer），用于兼容字段嵌套不同的生产者
+///
+/// `data`、`run_scene`、`seq`等其余字段从`params`对象中读取
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldPaths {
+    service: String,
+    service_code: String,
+    params: String,
+    vin: String,
+    timestamp: String,
+}
+
+impl Default for FieldPaths {
+    fn default() -> Self {
+        Self {
+            service: "/service".to_string(),
+            service_code: "/service_code".to_string(),
+            params: "/params".to_string(),
+            vin: "/params/vin".to_string(),
+            timestamp: "/params/timestamp".to_string(),
+        }
+    }
+}
+
+impl FieldPaths {
+    /// 服务类型字段位置
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+    
+    /// 整数服务编码字段位置，服务类型字段缺失时使用
+    pub fn service_code(&self) -> &str {
+        &self.service_code
+    }
+    
+    /// params对象位置
+    pub fn params(&self) -> &str {
+        &self.params
+    }
+    
+    /// VIN字段位置
+    pub fn vin(&self) -> &str {
+        &self.vin
+    }
+    
+    /// 时间戳字段位置
+    pub fn timestamp(&self) -> &str {
+        &self.timestamp
+    }
+    
+    /// 设置服务类型字段位置
+    pub fn with_service(mut self, pointer: &str) -> Result<Self> {
+        self.service = Self::check_pointer(pointer)?;
+        Ok(self)
+    }
+    
+    /// 设置整数服务编码字段位置
+    pub fn with_service_code(mut self, pointer: &str) -> Result<Self> {
+        self.service_code = Self::check_pointer(pointer)?;
+        Ok(self)
+    }
+    
+    /// 设置params对象位置
+    pub fn with_params(mut self, pointer: &str) -> Result<Self> {
+        self.params = Self::check_pointer(pointer)?;
+        Ok(self)
+    }
+    
+    /// 设置VIN字段位置（如`/params/vehicle/vin`）
+    pub fn with_vin(mut self, pointer: &str) -> Result<Self> {
+        self.vin = Self::check_pointer(pointer)?;
+        Ok(self)
+    }
+    
+    /// 设置时间戳字段位置
+    pub fn with_timestamp(mut self, pointer: &str) -> Result<Self> {
+        self.timestamp = Self::check_pointer(pointer)?;
+        Ok(self)
+    }
+    
+    /// 校验JSON Pointer语法：以`/`开头，`~`只能用于`~0`/`~1`转义
+    fn check_pointer(pointer: &str) -> Result<String> {
+        let invalid = |reason: &str| {
+            Err(VehicleError::ConfigError(format!("Invalid field pointer '{}': {}", pointer, reason)))
+        };
+        if !pointer.starts_with('/') {
+            return invalid("must start with '/'");
+        }
+        let mut chars = pointer.chars();
+        while let Some(c) = chars.next() {
+            if c == '~' && !matches!(chars.next(), Some('0' | '1')) {
+                return invalid("'~' must be followed by '0' or '1'");
+            }
+        }
+        Ok(pointer.to_string())
+    }
+}
+
+/// 整数服务编码与服务名的双向映射，用于以数字编码代替服务名的带宽优化生产者
+///
+/// 接入时编码被还原为服务名，优先级、采样、去重均基于服务名
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServiceCodes {
+    by_code: HashMap<u64, String>,
+    by_name: HashMap<String, u64>,
+}
+
+impl ServiceCodes {
+    /// 创建空映射
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// 添加编码与服务名的对应关系，编码或服务名已被占用时返回错误
+    pub fn with_code(mut self, code: u64, service: &str) -> Result<Self> {
+        if let Some(existing) = self.by_code.get(&code) {
+            return Err(VehicleError::ConfigError(format!(
+                "Service code {} already mapped to '{}'",
+                code, existing
+            )));
+        }
+        if let Some(existing) = self.by_name.get(service) {
+            return Err(VehicleError::ConfigError(format!(
+                "Service '{}' already mapped to code {}",
+                service, existing
+            )));
+        }
+        self.by_code.insert(code, service.to_string());
+        self.by_name.insert(service.to_string(), code);
+        Ok(self)
+    }
+    
+    /// 编码对应的服务名
+    pub fn name(&self, code: u64) -> Option<&str> {
+        self.by_code.get(&code).map(String::as_str)
+    }
+    
+    /// 服务名对应的编码
+    pub fn code(&self, service: &str) -> Option<u64> {
+        self.by_name.get(service).copied()
+    }
+    
+    /// 映射数量
+    pub fn len(&self) -> usize {
+        self.by_code.len()
+    }
+    
+    /// 检查映射是否为空
+    pub fn is_empty(&self) -> bool {
+        self.by_code.is_empty()
+    }
+}
+
+/// 去重键配置构建器
+#[derive(Debug, Clone)]
+pub struct DedupKeyConfigBuilder {
+    config: DedupKeyConfig,
+}
+
+impl DedupKeyConfigBuilder {
+    /// 是否包含服务类型
+    pub fn service(mut self, include: bool) -> Self {
+        self.config.include_service = include;
+        self
+    }
+    
+    /// 是否包含VIN
+    pub fn vin(mut self, include: bool) -> Self {
+        self.config.include_vin = include;
+        self
+    }
+    
+    /// 是否包含时间戳
+    pub fn timestamp(mut self, include: bool) -> Self {
+        self.config.include_timestamp = include;
+        self
+    }
+    
+    /// 不使用data
+    pub fn ignore_data(mut self) -> Self {
+        self.config.data = DedupDataKey::Ignore;
+        self
+    }
+    
+    /// 使用整个data
+    pub fn full_data(mut self) -> Self {
+        self.config.data = DedupDataKey::Full;
+        self
+    }
+    
+    /// 只使用data中的指定字段（可多次调用追加）
+    pub fn data_field(mut self, field: &str) -> Self {
+        match self.config.data {
+            DedupDataKey::Fields(ref mut fields) => fields.push(field.to_string()),
+            _ => self.config.data = DedupDataKey::Fields(vec![field.to_string()]),
+        }
+        self
+    }
+    
+    /// 完成构建
+    pub fn build(self) -> DedupKeyConfig {
+        self.config
+    }
+}
+
+/// 运行场景过滤配置，只处理允许场景的消息（如生产与测试流量分节点处理）
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct SceneFilter {
+    /// 允许处理的场景
+    pub allowed_scenes: HashSet<String>,
+    /// 是否允许未携带`run_scene`的消息
+    pub allow_missing: bool,
+}
+
+impl SceneFilter {
+    /// 创建只允许指定场景的过滤器，未携带场景的消息被过滤
+    pub fn allow<I, S>(scenes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            allowed_scenes: scenes.into_iter().map(Into::into).collect(),
+            allow_missing: false,
+        }
+    }
+    
+    /// 设置是否允许未携带`run_scene`的消息
+    pub fn with_allow_missing(mut self, allow_missing: bool) -> Self {
+        self.allow_missing = allow_missing;
+        self
+    }
+    
+    /// 检查消息是否允许通过
+    pub fn allows(&self, message: &VehicleMessage) -> bool {
+        match message.run_scene {
+            Some(ref scene) => self.allowed_scenes.contains(scene),
+            None => self.allow_missing,
+        }
+    }
+}
+
+/// 地理围栏过滤配置（矩形区域）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct GeoFilter {
+    pub min_x: f64,
+    pub max_x: f64,
+    pub min_y: f64,
+    pub max_y: f64,
+}
+
+impl GeoFilter {
+    /// 创建新的地理围栏
+    pub fn new(min_x: f64, max_x: f64, min_y: f64, max_y: f64) -> Self {
+        Self { min_x, max_x, min_y, max_y }
+    }
+    
+    /// 检查坐标是否在区域内（含边界）
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.min_x && x <= self.max_x && y >= self.min_y && y <= self.max_y
+    }
+    
+    /// 检查服务是否携带位置信息
+    pub fn applies_to(service: &str) -> bool {
+        matches!(Service::from(service), Service::Tracking | Service::Traj)
+    }
+    
+    /// 检查消息是否允许通过：非位置服务或缺少坐标的消息不过滤
+    pub fn allows(&self, message: &VehicleMessage) -> bool {
+        if !Self::applies_to(&message.service) {
+            return true;
+        }
+        
+        let Some(data) = message.data() else {
+            return true;
+        };
+        
+        match (
+            data.get("x").and_then(|v| v.as_f64()),
+            data.get("y").and_then(|v| v.as_f64()),
+        ) {
+            (Some(x), Some(y)) => self.contains(x, y),
+            _ => true,
+        }
+    }
+}
+
+/// 已知的服务类型
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Service {
+    /// 车辆跟踪
+    Tracking,
+    /// 路线
+    Route,
+    /// 错误信息
+    ErrorInfo,
+    /// VCC
+    Vcc,
+    /// UOS配置
+    UosConfig,
+    /// 轨迹
+    Traj,
+    /// 移动对象
+    MovingObj,
+    /// 设备状态
+    Device,
+    /// 定位状态
+    LocStat,
+    /// 其他未知服务
+    Other(String),
+}
+
+impl Service {
+    /// 获取服务名
+    pub fn as_str(&self) -> &str {
+        match self {
+            Service::Tracking => "tracking",
+            Service::Route => "route",
+            Service::ErrorInfo => "error_info",
+            Service::Vcc => "vcc",
+            Service::UosConfig => "uos_config",
+            Service::Traj => "traj",
+            Service::MovingObj => "moving_obj",
+            Service::Device => "device",
+            Service::LocStat => "loc_stat",
+            Service::Other(name) => name,
+        }
+    }
+    
+    /// 检查是否为已知服务
+    pub fn is_known(&self) -> bool {
+        !matches!(self, Service::Other(_))
+    }
+}
+
+impl From<&str> for Service {
+    fn from(service: &str) -> Self {
+        match service {
+            "tracking" => Service::Tracking,
+            "route" => Service::Route,
+            "error_info" => Service::ErrorInfo,
+            "vcc" => Service::Vcc,
+            "uos_config" => Service::UosConfig,
+            "traj" => Service::Traj,
+            "moving_obj" => Service::MovingObj,
+            "device" => Service::Device,
+            "loc_stat" => Service::LocStat,
+            other => Service::Other(other.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for Service {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 加权公平调度中每轮从各优先级队列最多取出的消息数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LaneWeights {
+    pub critical: u32,
+    pub high: u32,
+    pub normal: u32,
+    pub low: u32,
+    pub background: u32,
+}
+
+impl Default for LaneWeights {
+    fn default() -> Self {
+        Self {
+            critical: 8,
+            high: 4,
+            normal: 2,
+            low: 1,
+            background: 1,
+        }
+    }
+}
+
+impl LaneWeights {
+    /// 获取优先级对应的权重，0按1处理以保证低优先级队列不会被无限期饿死
+    pub fn weight(&self, priority: MessagePriority) -> u32 {
+        let weight = match priority {
+            MessagePriority::Critical => self.critical,
+            MessagePriority::High => self.high,
+            MessagePriority::Normal => self.normal,
+            MessagePriority::Low => self.low,
+            MessagePriority::Background => self.background,
+        };
+        weight.max(1)
     }
 }
 
 /// 消息优先级
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+///
+/// 按服务类型的默认映射只使用Critical、Normal、Background三级；
+/// High、Low用于通过`MessageProcessor::set_service_priority`做更细的调度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
 pub enum MessagePriority {
     /// 关键消息：tracking, route, error_info
     Critical,
+    /// 高优先级消息，介于Critical与Normal之间
+    High,
     /// 普通消息：vcc, uos_config
     Normal,
+    /// 低优先级消息，介于Normal与Background之间
+    Low,
     /// 背景消息：traj, moving_obj, device
     Background,
 }
 
 impl MessagePriority {
+    /// 优先级数量
+    pub const COUNT: usize = 5;
+    
+    /// 所有优先级（按处理优先顺序）
+    pub fn all() -> [MessagePriority; Self::COUNT] {
+        [
+            MessagePriority::Critical,
+            MessagePriority::High,
+            MessagePriority::Normal,
+            MessagePriority::Low,
+            MessagePriority::Background,
+        ]
+    }
+    
     /// 根据服务类型确定优先级
     pub fn from_service(service: &str) -> Self {
-        match service {
-            "tracking" | "route" | "error_info" => MessagePriority::Critical,
-            "traj" | "moving_obj" | "device" | "loc_stat" => MessagePriority::Background,
+        match Service::from(service) {
+            Service::Tracking | Service::Route | Service::ErrorInfo => MessagePriority::Critical,
+            Service::Traj | Service::MovingObj | Service::Device | Service::LocStat => {
+                MessagePriority::Background
+            }
             _ => MessagePriority::Normal,
         }
     }
@@ -84,7 +792,9 @@ impl MessagePriority {
     pub fn queue_capacity(&self) -> usize {
         match self {
             MessagePriority::Critical => 200,   // 关键消息队列较大
+            MessagePriority::High => 200,
             MessagePriority::Normal => 500,     // 普通消息队列最大
+            MessagePriority::Low => 200,
             MessagePriority::Background => 100, // 背景消息队列较小
         }
     }
@@ -93,34 +803,395 @@ impl MessagePriority {
     pub fn processing_interval(&self) -> Duration {
         match self {
             MessagePriority::Critical => Duration::from_micros(100),  // 100μs
+            MessagePriority::High => Duration::from_micros(500),      // 500μs
             MessagePriority::Normal => Duration::from_millis(1),      // 1ms
+            MessagePriority::Low => Duration::from_millis(5),         // 5ms
             MessagePriority::Background => Duration::from_millis(10), // 10ms
         }
     }
 }
 
-/// 处理统计信息
+/// 队列水位
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Watermark {
+    /// 队列占用升至高水位
+    High,
+    /// 队列占用从高水位回落至低水位
+    Low,
+}
+
+/// 消息处理流水线阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PipelineStage {
+    /// JSON解析与字段提取
+    Parse,
+    /// 消息校验
+    Validate,
+    /// 去重检查
+    Dedup,
+    /// 采样判定
+    Sample,
+    /// 写入优先级队列
+    Enqueue,
+    /// 在队列中等待处理
+    QueueWait,
+    /// 回调执行（含告警规则求值）
+    Callback,
+}
+
+impl PipelineStage {
+    /// 阶段数量
+    pub const COUNT: usize = 7;
+    
+    /// 所有阶段（按流水线顺序）
+    pub fn all() -> [PipelineStage; Self::COUNT] {
+        [
+            PipelineStage::Parse,
+            PipelineStage::Validate,
+            PipelineStage::Dedup,
+            PipelineStage::Sample,
+            PipelineStage::Enqueue,
+            PipelineStage::QueueWait,
+            PipelineStage::Callback,
+        ]
+    }
+}
+
+/// 单个阶段的耗时统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StageStat {
+    /// 记录次数
+    pub count: u64,
+    /// 累计耗时
+    pub total: Duration,
+    /// 最大耗时
+    pub max: Duration,
+}
+
+impl StageStat {
+    /// 记录一次耗时
+    pub fn record(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+    
+    /// 平均耗时
+    pub fn avg(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_nanos((self.total.as_nanos() / self.count as u128) as u64)
+        }
+    }
+}
+
+/// 各流水线阶段的耗时汇总
 #[derive(Debug, Clone, Default)]
+pub struct StageTimings {
+    stages: HashMap<PipelineStage, StageStat>,
+}
+
+impl StageTimings {
+    /// 记录阶段耗时
+    pub fn record(&mut self, stage: PipelineStage, elapsed: Duration) {
+        self.stages.entry(stage).or_default().record(elapsed);
+    }
+    
+    /// 获取阶段统计，未记录过的阶段返回空统计
+    pub fn get(&self, stage: PipelineStage) -> StageStat {
+        self.stages.get(&stage).copied().unwrap_or_default()
+    }
+    
+    /// 生成文本报告，每个阶段一行
+    pub fn report(&self) -> String {
+        PipelineStage::all()
+            .into_iter()
+            .map(|stage| {
+                let stat = self.get(stage);
+                format!(
+                    "{:<10} count={:<8} avg={:>8.1}us max={:>8.1}us\n",
+                    format!("{:?}", stage),
+                    stat.count,
+                    stat.avg().as_secs_f64() * 1_000_000.0,
+                    stat.max.as_secs_f64() * 1_000_000.0
+                )
+            })
+            .collect()
+    }
+}
+
+impl FromIterator<(PipelineStage, StageStat)> for StageTimings {
+    fn from_iter<I: IntoIterator<Item = (PipelineStage, StageStat)>>(iter: I) -> Self {
+        Self {
+            stages: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// 消息处理完成事件
+#[derive(Debug, Clone)]
+pub struct ProcessedEvent {
+    /// 处理器分配的消息ID
+    pub message_id: u64,
+    /// 服务类型
+    pub service: String,
+    /// 车辆VIN码
+    pub vin: String,
+    /// 消息优先级
+    pub priority: MessagePriority,
+    /// 请求/应答关联ID
+    pub correlation_id: Option<String>,
+    /// 消息来源标识
+    pub source: Option<String>,
+    /// 回调处理耗时
+    pub processing_time: Duration,
+}
+
+/// 最近分发消息的摘要，用于故障诊断（见`ProcessorConfig::recent_messages`）
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentMessage {
+    /// 处理器分配的消息ID
+    pub message_id: u64,
+    /// 服务类型
+    pub service: String,
+    /// 车辆VIN码
+    pub vin: String,
+    /// 消息时间戳
+    pub timestamp: f64,
+    /// 消息优先级
+    pub priority: MessagePriority,
+}
+
+/// 处理器当前生效配置的快照，见`MessageProcessor::config_snapshot`
+///
+/// 汇总构建时的配置和运行中修改的采样、优先级、过滤设置，可直接序列化输出（如管理接口）
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigSnapshot {
+    /// 运行状态
+    pub state: ProcessorState,
+    /// 采样开关
+    pub sampling_enabled: bool,
+    /// 各服务的采样率
+    pub sampling_rates: BTreeMap<String, f32>,
+    /// 按服务设置的采样策略（优先于采样率）
+    pub sampling_strategies: BTreeMap<String, SamplingStrategy>,
+    /// 服务名模式规则及其采样率，按添加顺序
+    pub sampling_patterns: Vec<(String, f32)>,
+    /// 未配置服务的默认采样率
+    pub default_sampling_rate: f32,
+    /// 始终全量处理的VIN
+    pub priority_vins: BTreeSet<String>,
+    /// 按服务覆盖的优先级
+    pub priority_overrides: BTreeMap<String, MessagePriority>,
+    /// 各优先级队列容量，按优先级从高到低
+    pub queue_capacities: Vec<(MessagePriority, usize)>,
+    /// 加权公平调度权重，None时每条队列独立一个任务
+    pub lane_weights: Option<LaneWeights>,
+    /// 去重配置
+    pub dedup: DedupConfig,
+    /// 去重窗口
+    pub dedup_window: Duration,
+    /// 地理围栏过滤
+    pub geo_filter: Option<GeoFilter>,
+    /// 运行场景过滤
+    pub scene_filter: Option<SceneFilter>,
+    /// 严格模式
+    pub strict: bool,
+    /// 试运行
+    pub dry_run: bool,
+    /// 延迟取数模式
+    pub lazy_data: bool,
+    /// 单条消息最大字节数
+    pub max_message_bytes: Option<usize>,
+    /// 内联处理的服务
+    pub inline_services: BTreeSet<String>,
+    /// 同时执行回调的数量上限
+    pub max_concurrent_callbacks: Option<usize>,
+    /// 按优先级的回调超时
+    pub callback_timeouts: Vec<(MessagePriority, Duration)>,
+    /// 各服务的处理延迟预算
+    pub latency_budgets: BTreeMap<String, Duration>,
+    /// 有界运行的消息数
+    pub stop_after: Option<u64>,
+    /// 保留的最近消息摘要数量
+    pub recent_messages: Option<usize>,
+    /// 是否正在录制接入帧
+    pub recording: bool,
+}
+
+/// 运行时控制命令，通过`MessageProcessor::control_sender`发送给运行中的处理器
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlCommand {
+    /// 设置服务采样率
+    SetSampling { service: String, rate: f32 },
+    /// 暂停向回调投递消息
+    Pause,
+    /// 恢复向回调投递消息
+    Resume,
+    /// 重置性能统计
+    ResetStats,
+    /// 覆盖服务的优先级，None时恢复默认映射
+    SetPriority { service: String, priority: Option<MessagePriority> },
+}
+
+/// 消息提交结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubmitOutcome {
+    /// 已进入对应优先级队列
+    Enqueued {
+        /// 分配的优先级
+        priority: MessagePriority,
+    },
+    /// 内联服务，已在提交调用内完成回调处理
+    ProcessedInline {
+        /// 分配的优先级
+        priority: MessagePriority,
+    },
+    /// 去重窗口内的重复消息
+    Deduplicated,
+    /// 被采样丢弃
+    Sampled,
+    /// 被地理围栏过滤
+    Filtered,
+    /// 关键队列已满，已写入磁盘溢写文件，队列腾出空间后重新接入
+    Spilled,
+    /// 因其他原因丢弃（超大、队列满等）
+    Dropped {
+        /// 丢弃原因
+        reason: &'static str,
+    },
+}
+
+/// 服务的实际采样率（按近期采样判定统计）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EffectiveSamplingRate {
+    /// 当前配置的采样率
+    pub configured: f32,
+    /// 近期实际保留比例
+    pub effective: f32,
+    /// 统计窗口内参与采样判定的消息数
+    pub seen: u64,
+}
+
+/// 批量提交结果
+#[derive(Debug, Default)]
+pub struct BatchResult {
+    /// 每帧的提交结果，顺序与输入一致
+    pub outcomes: Vec<Result<SubmitOutcome>>,
+    /// 入队数量
+    pub enqueued: usize,
+    /// 内联处理数量
+    pub processed_inline: usize,
+    /// 去重丢弃数量
+    pub deduplicated: usize,
+    /// 采样丢弃数量
+    pub sampled: usize,
+    /// 地理围栏过滤数量
+    pub filtered: usize,
+    /// 溢写到磁盘的数量
+    pub spilled: usize,
+    /// 其他原因丢弃数量
+    pub dropped: usize,
+    /// 解析或校验失败数量
+    pub failed: usize,
+}
+
+impl BatchResult {
+    /// 追加一帧的提交结果并更新计数
+    pub fn push(&mut self, outcome: Result<SubmitOutcome>) {
+        match outcome {
+            Ok(SubmitOutcome::Enqueued { .. }) => self.enqueued += 1,
+            Ok(SubmitOutcome::ProcessedInline { .. }) => self.processed_inline += 1,
+            Ok(SubmitOutcome::Deduplicated) => self.deduplicated += 1,
+            Ok(SubmitOutcome::Sampled) => self.sampled += 1,
+            Ok(SubmitOutcome::Filtered) => self.filtered += 1,
+            Ok(SubmitOutcome::Spilled) => self.spilled += 1,
+            Ok(SubmitOutcome::Dropped { .. }) => self.dropped += 1,
+            Err(_) => self.failed += 1,
+        }
+        self.outcomes.push(outcome);
+    }
+    
+    /// 批次中的帧数
+    pub fn len(&self) -> usize {
+        self.outcomes.len()
+    }
+    
+    /// 检查批次是否为空
+    pub fn is_empty(&self) -> bool {
+        self.outcomes.is_empty()
+    }
+}
+
+/// 处理器运行状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ProcessorState {
+    /// 未运行
+    #[default]
+    Stopped,
+    /// 正常运行
+    Running,
+    /// 已暂停：消息继续入队缓冲，但不调用回调
+    Paused,
+}
+
+/// 处理统计信息
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ProcessingStats {
     /// 接收到的消息总数
     pub messages_received: u64,
     /// 已处理的消息数
     pub messages_processed: u64,
+    /// 已处理的Critical优先级消息数
+    pub processed_critical: u64,
+    /// 已处理的High优先级消息数
+    pub processed_high: u64,
+    /// 已处理的Normal优先级消息数
+    pub processed_normal: u64,
+    /// 已处理的Low优先级消息数
+    pub processed_low: u64,
+    /// 已处理的Background优先级消息数
+    pub processed_background: u64,
     /// 丢弃的消息数
     pub messages_dropped: u64,
-    /// 平均处理时间（微秒）
+    /// 使用到达时间补全时间戳的消息数
+    pub messages_backfilled: u64,
+    /// JSON解析失败的消息数，同时计入`messages_dropped`
+    pub parse_errors: u64,
+    /// 解析成功但字段缺失或校验失败的消息数，同时计入`messages_dropped`
+    pub validation_errors: u64,
+    /// 试运行模式下完成处理（未调用回调）的消息数，同时计入`messages_processed`
+    pub messages_dry_run: u64,
+    /// 平均处理时间（微秒），为`processing_time_ewma_us`四舍五入后的值
     pub avg_processing_time_us: u64,
+    /// 未取整的处理时间指数移动平均（微秒），避免整数运算的向下取整偏差累积
+    pub processing_time_ewma_us: f64,
     /// 当前队列大小
     pub queue_size: usize,
     /// 最后更新时间
+    #[serde(skip)]
     pub last_update: Option<Instant>,
+    /// 统计窗口起点（创建或重置统计的时间），用于计算处理速率
+    #[serde(skip)]
+    pub window_start: Option<Instant>,
+    /// 处理器运行状态
+    pub processor_state: ProcessorState,
+    /// 本次运行已处理的消息数（含处理失败），不随统计重置清零
+    pub messages_dispatched: u64,
+    /// 有界运行的消息数上限（`ProcessorConfig::stop_after`）
+    pub stop_after: Option<u64>,
+    /// 统计时正在执行的回调数
+    pub callbacks_in_flight: usize,
 }
 
 impl ProcessingStats {
     /// 创建新的统计实例
     pub fn new() -> Self {
+        let now = Instant::now();
         Self {
-            last_update: Some(Instant::now()),
+            last_update: Some(now),
+            window_start: Some(now),
             ..Default::default()
         }
     }
@@ -137,24 +1208,85 @@ impl ProcessingStats {
         self.last_update = Some(Instant::now());
     }
     
+    /// 增加指定优先级的处理计数
+    pub fn increment_priority_processed(&mut self, priority: MessagePriority) {
+        match priority {
+            MessagePriority::Critical => self.processed_critical += 1,
+            MessagePriority::High => self.processed_high += 1,
+            MessagePriority::Normal => self.processed_normal += 1,
+            MessagePriority::Low => self.processed_low += 1,
+            MessagePriority::Background => self.processed_background += 1,
+        }
+    }
+    
+    /// 获取指定优先级的处理计数
+    pub fn processed_for(&self, priority: MessagePriority) -> u64 {
+        match priority {
+            MessagePriority::Critical => self.processed_critical,
+            MessagePriority::High => self.processed_high,
+            MessagePriority::Normal => self.processed_normal,
+            MessagePriority::Low => self.processed_low,
+            MessagePriority::Background => self.processed_background,
+        }
+    }
+    
     /// 增加丢弃计数
     pub fn increment_dropped(&mut self) {
         self.messages_dropped += 1;
         self.last_update = Some(Instant::now());
     }
     
-    /// 更新处理时间
+    /// 已接收但尚未处理完成或丢弃的消息数，不足零时按零计
+    ///
+    /// 接入前即被丢弃（如解析失败）的消息也计入丢弃，因此是积压的下界估计
+    pub fn in_flight(&self) -> u64 {
+        self.messages_received
+            .saturating_sub(self.messages_processed.saturating_add(self.messages_dropped))
+    }
+    
+    /// 增加时间戳补全计数
+    pub fn increment_backfilled(&mut self) {
+        self.messages_backfilled += 1;
+        self.last_update = Some(Instant::now());
+    }
+    
+    /// 增加解析失败计数
+    pub fn increment_parse_errors(&mut self) {
+        self.parse_errors += 1;
+        self.last_update = Some(Instant::now());
+    }
+    
+    /// 增加校验失败计数
+    pub fn increment_validation_errors(&mut self) {
+        self.validation_errors += 1;
+        self.last_update = Some(Instant::now());
+    }
+    
+    /// 增加试运行处理计数
+    pub fn increment_dry_run(&mut self) {
+        self.messages_dry_run += 1;
+        self.last_update = Some(Instant::now());
+    }
+    
+    /// 处理时间移动平均的默认平滑系数
+    pub const DEFAULT_EWMA_ALPHA: f64 = 0.1;
+    
+    /// 更新处理时间（使用默认平滑系数）
     pub fn update_processing_time(&mut self, duration: Duration) {
-        let new_time_us = duration.as_micros() as u64;
+        self.update_processing_time_with_alpha(duration, Self::DEFAULT_EWMA_ALPHA);
+    }
+    
+    /// 按指定平滑系数更新处理时间，alpha越大平均值对新样本越敏感
+    pub fn update_processing_time_with_alpha(&mut self, duration: Duration, alpha: f64) {
+        let new_time_us = duration.as_secs_f64() * 1_000_000.0;
         
-        // 使用移动平均计算
-        if self.avg_processing_time_us == 0 {
-            self.avg_processing_time_us = new_time_us;
+        // 首个样本直接作为平均值
+        if self.processing_time_ewma_us == 0.0 {
+            self.processing_time_ewma_us = new_time_us;
         } else {
-            // 权重为0.1的移动平均
-            self.avg_processing_time_us = 
-                (self.avg_processing_time_us * 9 + new_time_us) / 10;
+            self.processing_time_ewma_us += alpha * (new_time_us - self.processing_time_ewma_us);
         }
+        self.avg_processing_time_us = self.processing_time_ewma_us.round() as u64;
         
         self.last_update = Some(Instant::now());
     }
@@ -165,15 +1297,78 @@ impl ProcessingStats {
         self.last_update = Some(Instant::now());
     }
     
-    /// 获取处理速率（消息/秒）
+    /// 合并另一份统计（多个处理器汇总），平均处理时间按已处理数加权
+    pub fn merge(&mut self, other: &ProcessingStats) {
+        let total_processed = self.messages_processed + other.messages_processed;
+        let weighted_time = self.avg_processing_time_us * self.messages_processed
+            + other.avg_processing_time_us * other.messages_processed;
+        if let Some(avg) = weighted_time.checked_div(total_processed) {
+            self.avg_processing_time_us = avg;
+            self.processing_time_ewma_us = (self.processing_time_ewma_us * self.messages_processed as f64
+                + other.processing_time_ewma_us * other.messages_processed as f64)
+                / total_processed as f64;
+        }
+        self.messages_received += other.messages_received;
+        self.messages_processed = total_processed;
+        self.processed_critical += other.processed_critical;
+        self.processed_high += other.processed_high;
+        self.processed_normal += other.processed_normal;
+        self.processed_low += other.processed_low;
+        self.processed_background += other.processed_background;
+        self.messages_dropped += other.messages_dropped;
+        self.messages_backfilled += other.messages_backfilled;
+        self.parse_errors += other.parse_errors;
+        self.validation_errors += other.validation_errors;
+        self.messages_dry_run += other.messages_dry_run;
+        self.queue_size += other.queue_size;
+        self.last_update = self.last_update.max(other.last_update);
+        self.window_start = match (self.window_start, other.window_start) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.messages_dispatched += other.messages_dispatched;
+        self.callbacks_in_flight += other.callbacks_in_flight;
+        self.stop_after = match (self.stop_after, other.stop_after) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+    }
+    
+    /// 累加上次运行保存的计数（用于重启后延续统计），队列大小和本次运行相关的字段不累加
+    pub fn restore_from(&mut self, previous: &ProcessingStats) {
+        let carried = ProcessingStats {
+            queue_size: 0,
+            last_update: None,
+            window_start: None,
+            messages_dispatched: 0,
+            stop_after: None,
+            callbacks_in_flight: 0,
+            ..previous.clone()
+        };
+        self.merge(&carried);
+    }
+    
+    /// 有界运行是否已处理完`stop_after`条消息
+    pub fn is_completed(&self) -> bool {
+        self.stop_after.is_some_and(|limit| self.messages_dispatched >= limit)
+    }
+    
+    /// 获取统计窗口内的平均处理速率（消息/秒）
     pub fn get_processing_rate(&self) -> f64 {
-        if let Some(last_update) = self.last_update {
-            let elapsed = last_update.elapsed().as_secs_f64();
-            if elapsed > 0.0 {
-                return self.messages_processed as f64 / elapsed;
-            }
+        self.processing_rate_at(Instant::now())
+    }
+    
+    /// 计算截至`now`的平均处理速率（消息/秒）
+    pub fn processing_rate_at(&self, now: Instant) -> f64 {
+        let Some(window_start) = self.window_start else {
+            return 0.0;
+        };
+        let elapsed = now.saturating_duration_since(window_start).as_secs_f64();
+        if elapsed > 0.0 {
+            self.messages_processed as f64 / elapsed
+        } else {
+            0.0
         }
-        0.0
     }
     
     /// 获取丢弃率
@@ -184,6 +1379,49 @@ impl ProcessingStats {
             0.0
         }
     }
+    
+    /// 计算相对上一次快照的区间增量
+    pub fn delta(&self, prev: &ProcessingStats, elapsed: Duration) -> StatsDelta {
+        // 计数在两次快照之间被重置时，增量按0处理
+        let received = self.messages_received.saturating_sub(prev.messages_received);
+        let processed = self.messages_processed.saturating_sub(prev.messages_processed);
+        let dropped = self.messages_dropped.saturating_sub(prev.messages_dropped);
+        
+        let secs = elapsed.as_secs_f64();
+        let per_sec = |count: u64| if secs > 0.0 { count as f64 / secs } else { 0.0 };
+        
+        StatsDelta {
+            elapsed,
+            received,
+            processed,
+            dropped,
+            received_per_sec: per_sec(received),
+            processed_per_sec: per_sec(processed),
+            dropped_per_sec: per_sec(dropped),
+            drop_rate: if received > 0 { dropped as f64 / received as f64 } else { 0.0 },
+        }
+    }
+}
+
+/// 两次统计快照之间的区间增量
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StatsDelta {
+    /// 区间时长
+    pub elapsed: Duration,
+    /// 区间内接收的消息数
+    pub received: u64,
+    /// 区间内处理的消息数
+    pub processed: u64,
+    /// 区间内丢弃的消息数
+    pub dropped: u64,
+    /// 接收速率（消息/秒）
+    pub received_per_sec: f64,
+    /// 处理速率（消息/秒）
+    pub processed_per_sec: f64,
+    /// 丢弃速率（消息/秒）
+    pub dropped_per_sec: f64,
+    /// 区间丢弃率
+    pub drop_rate: f64,
 }
 
 /// 采样配置
@@ -191,62 +1429,283 @@ impl ProcessingStats {
 pub struct SamplingConfig {
     /// 各服务类型的采样率 (0.0-1.0)
     pub rates: HashMap<String, f32>,
+    /// 始终全量处理的车辆VIN（绕过采样）
+    pub priority_vins: HashSet<String>,
+    /// 服务名模式规则，精确匹配失败时使用最具体的匹配规则
+    pub patterns: Vec<SamplingPattern>,
+    /// 未配置服务的默认采样率
+    pub default_rate: f32,
+    /// 按服务设置的采样策略及其计数器（`EveryNth`使用），优先于采样率
+    strategies: HashMap<String, (SamplingStrategy, Arc<AtomicU64>)>,
 }
 
-impl Default for SamplingConfig {
-    fn default() -> Self {
-        let mut rates = HashMap::new();
+/// 服务采样策略
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum SamplingStrategy {
+    /// 按概率保留 (0.0-1.0)
+    Probabilistic(f32),
+    /// 每n条保留1条，确定性且均匀
+    EveryNth(u64),
+    /// 按VIN的hash保留`hash % n < k`的消息，同一车辆始终被一致地保留或丢弃
+    HashMod(u64, u64),
+}
+
+impl SamplingStrategy {
+    /// 名义采样率
+    pub fn rate(&self) -> f32 {
+        match *self {
+            SamplingStrategy::Probabilistic(rate) => rate,
+            SamplingStrategy::EveryNth(n) => 1.0 / n as f32,
+            SamplingStrategy::HashMod(n, k) => k.min(n) as f32 / n as f32,
+        }
+    }
+    
+    fn validate(&self) -> Result<()> {
+        match *self {
+            SamplingStrategy::Probabilistic(rate) if !rate.is_finite() => Err(VehicleError::ConfigError(
+                format!("Sampling rate must be finite: {}", rate),
+            )),
+            SamplingStrategy::EveryNth(0) | SamplingStrategy::HashMod(0, _) => Err(VehicleError::ConfigError(
+                format!("Sampling modulus must be positive: {:?}", self),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 采样率模式规则（glob或正则，创建时编译）
+#[derive(Debug, Clone)]
+pub struct SamplingPattern {
+    /// 原始模式字符串
+    pub pattern: String,
+    /// 采样率 (0.0-1.0)
+    pub rate: f32,
+    regex: Regex,
+    /// 具体程度：模式中的字面字符数，越大越优先
+    specificity: usize,
+}
+
+impl SamplingPattern {
+    /// 从glob模式创建（支持`*`和`?`）
+    pub fn glob(pattern: &str, rate: f32) -> Result<Self> {
+        let mut regex = String::from("^");
+        let mut specificity = 0;
+        for ch in pattern.chars() {
+            match ch {
+                '*' => regex.push_str(".*"),
+                '?' => regex.push('.'),
+                _ => {
+                    regex.push_str(&regex::escape(&ch.to_string()));
+                    specificity += 1;
+                }
+            }
+        }
+        regex.push('$');
         
-        // 关键消息100%处理
-        rates.insert("tracking".to_string(), 1.0);
-        rates.insert("route".to_string(), 1.0);
-        rates.insert("error_info".to_string(), 1.0);
-        rates.insert("vcc".to_string(), 1.0);
-        rates.insert("uos_config".to_string(), 1.0);
+        Self::compile(pattern, &regex, specificity, rate)
+    }
+    
+    /// 从正则表达式创建
+    pub fn regex(pattern: &str, rate: f32) -> Result<Self> {
+        let specificity = pattern
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+            .count();
+        Self::compile(pattern, pattern, specificity, rate)
+    }
+    
+    fn compile(pattern: &str, regex: &str, specificity: usize, rate: f32) -> Result<Self> {
+        let regex = Regex::new(regex).map_err(|e| {
+            VehicleError::ConfigError(format!("Invalid sampling pattern '{}': {}", pattern, e))
+        })?;
         
-        // 背景消息采样处理
-        rates.insert("traj".to_string(), 0.1);        // 10%
-        rates.insert("moving_obj".to_string(), 0.05); // 5%
-        rates.insert("device".to_string(), 0.2);      // 20%
-        rates.insert("loc_stat".to_string(), 0.3);    // 30%
+        Ok(Self {
+            pattern: pattern.to_string(),
+            rate: rate.clamp(0.0, 1.0),
+            regex,
+            specificity,
+        })
+    }
+    
+    /// 检查服务名是否匹配
+    pub fn matches(&self, service: &str) -> bool {
+        self.regex.is_match(service)
+    }
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        let rates = [
+            // 关键消息100%处理
+            (Service::Tracking, 1.0),
+            (Service::Route, 1.0),
+            (Service::ErrorInfo, 1.0),
+            (Service::Vcc, 1.0),
+            (Service::UosConfig, 1.0),
+            // 背景消息采样处理
+            (Service::Traj, 0.1),       // 10%
+            (Service::MovingObj, 0.05), // 5%
+            (Service::Device, 0.2),     // 20%
+            (Service::LocStat, 0.3),    // 30%
+        ]
+        .into_iter()
+        .map(|(service, rate)| (service.to_string(), rate))
+        .collect();
         
-        Self { rates }
+        Self {
+            rates,
+            priority_vins: HashSet::new(),
+            patterns: Vec::new(),
+            default_rate: 1.0,
+            strategies: HashMap::new(),
+        }
     }
 }
 
 impl SamplingConfig {
     /// 获取服务的采样率
     pub fn get_rate(&self, service: &str) -> f32 {
-        self.rates.get(service).copied().unwrap_or(1.0)
+        if let Some((strategy, _)) = self.strategies.get(service) {
+            return strategy.rate();
+        }
+        if let Some(rate) = self.rates.get(service) {
+            return *rate;
+        }
+        
+        self.match_pattern(service)
+            .map(|pattern| pattern.rate)
+            .unwrap_or(self.default_rate)
+    }
+    
+    /// 遍历按服务设置的采样策略
+    pub fn strategies(&self) -> impl Iterator<Item = (&str, SamplingStrategy)> {
+        self.strategies.iter().map(|(service, (strategy, _))| (service.as_str(), *strategy))
+    }
+    
+    /// 设置未配置服务的默认采样率
+    pub fn set_default_rate(&mut self, rate: f32) {
+        self.default_rate = rate.clamp(0.0, 1.0);
+    }
+    
+    /// 查找匹配服务名的最具体模式规则，具体程度相同时先添加的优先
+    fn match_pattern(&self, service: &str) -> Option<&SamplingPattern> {
+        let mut best: Option<&SamplingPattern> = None;
+        for pattern in self.patterns.iter().filter(|p| p.matches(service)) {
+            if best.is_none_or(|b| pattern.specificity > b.specificity) {
+                best = Some(pattern);
+            }
+        }
+        best
     }
     
-    /// 设置服务的采样率
+    /// 添加或替换模式规则
+    pub fn set_pattern(&mut self, pattern: SamplingPattern) {
+        if let Some(existing) = self.patterns.iter_mut().find(|p| p.pattern == pattern.pattern) {
+            *existing = pattern;
+        } else {
+            self.patterns.push(pattern);
+        }
+    }
+    
+    /// 按glob模式设置采样率，如`sensor_*`
+    pub fn set_glob_rate(&mut self, glob: &str, rate: f32) -> Result<()> {
+        self.set_pattern(SamplingPattern::glob(glob, rate)?);
+        Ok(())
+    }
+    
+    /// 按正则表达式设置采样率
+    pub fn set_regex_rate(&mut self, regex: &str, rate: f32) -> Result<()> {
+        self.set_pattern(SamplingPattern::regex(regex, rate)?);
+        Ok(())
+    }
+    
+    /// 设置服务的采样率，替换该服务已设置的采样策略
     pub fn set_rate(&mut self, service: &str, rate: f32) {
         let rate = rate.clamp(0.0, 1.0); // 确保在有效范围内
+        self.strategies.remove(service);
         self.rates.insert(service.to_string(), rate);
     }
     
+    /// 设置服务的采样策略，替换该服务的采样率
+    pub fn set_strategy(&mut self, service: &str, strategy: SamplingStrategy) -> Result<()> {
+        strategy.validate()?;
+        let strategy = match strategy {
+            SamplingStrategy::Probabilistic(rate) => SamplingStrategy::Probabilistic(rate.clamp(0.0, 1.0)),
+            other => other,
+        };
+        self.strategies
+            .insert(service.to_string(), (strategy, Arc::new(AtomicU64::new(0))));
+        Ok(())
+    }
+    
+    /// 获取服务的采样策略，未设置策略时为按采样率的概率采样
+    pub fn get_strategy(&self, service: &str) -> SamplingStrategy {
+        self.strategies
+            .get(service)
+            .map(|(strategy, _)| *strategy)
+            .unwrap_or_else(|| SamplingStrategy::Probabilistic(self.get_rate(service)))
+    }
+    
+    /// 添加始终全量处理的VIN
+    pub fn add_priority_vin(&mut self, vin: &str) {
+        self.priority_vins.insert(vin.to_string());
+    }
+    
+    /// 移除始终全量处理的VIN，返回该VIN此前是否存在
+    pub fn remove_priority_vin(&mut self, vin: &str) -> bool {
+        self.priority_vins.remove(vin)
+    }
+    
+    /// 检查VIN是否在全量处理名单中
+    pub fn is_priority_vin(&self, vin: &str) -> bool {
+        self.priority_vins.contains(vin)
+    }
+    
+    /// 检查是否应该处理该车辆的消息，名单内的VIN绕过采样
+    pub fn should_process_vin(&self, service: &str, vin: &str) -> bool {
+        self.is_priority_vin(vin) || self.sample(service, vin)
+    }
+    
     /// 检查是否应该处理该消息
+    ///
+    /// 不携带VIN，`HashMod`策略按服务名hash，即整个服务全部保留或全部丢弃
     pub fn should_process(&self, service: &str) -> bool {
-        let rate = self.get_rate(service);
-        
-        if rate >= 1.0 {
-            return true;
+        self.sample(service, service)
+    }
+    
+    /// 按服务的采样策略决定是否保留，`key`用于`HashMod`
+    fn sample(&self, service: &str, key: &str) -> bool {
+        match self.strategies.get(service) {
+            Some((SamplingStrategy::EveryNth(n), counter)) => counter.fetch_add(1, Ordering::Relaxed) % n == 0,
+            Some((SamplingStrategy::HashMod(n, k), _)) => stable_hash(key) % n < *k,
+            Some((SamplingStrategy::Probabilistic(rate), _)) => sample_random(service, *rate),
+            None => sample_random(service, self.get_rate(service)),
         }
-        
-        // 使用快速随机数生成
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-        
-        let mut hasher = DefaultHasher::new();
-        service.hash(&mut hasher);
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_nanos()
-            .hash(&mut hasher);
-            
-        let random_val = (hasher.finish() % 1000) as f32 / 1000.0;
-        random_val < rate
     }
+}
+
+/// 进程内稳定的字符串hash（固定key的SipHash）
+fn stable_hash(key: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 按概率保留
+fn sample_random(service: &str, rate: f32) -> bool {
+    if rate >= 1.0 {
+        return true;
+    }
+    
+    // 使用快速随机数生成
+    let mut hasher = DefaultHasher::new();
+    service.hash(&mut hasher);
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos()
+        .hash(&mut hasher);
+        
+    let random_val = (hasher.finish() % 1000) as f32 / 1000.0;
+    random_val < rate
 }
\ No newline at end of file
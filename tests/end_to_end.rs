@@ -0,0 +1,88 @@
+//! 端到端测试：模拟socket -> NanomsgClient -> MessageProcessor -> 回调
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use vehicle_nn_core::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_client_to_callback_pipeline() {
+    let seen_services = Arc::new(Mutex::new(HashSet::new()));
+    let seen = seen_services.clone();
+
+    let processor = MessageProcessor::new();
+    processor.set_callback(Arc::new(move |message: VehicleMessage| {
+        seen.lock().insert(message.service);
+        Ok(())
+    }));
+    // 轨迹消息默认10%采样，测试中全量处理
+    processor.update_sampling_config("traj", 1.0);
+    let processor = Arc::new(processor);
+
+    let runner = processor.clone();
+    let processor_handle = tokio::spawn(async move { runner.start().await });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let client = Arc::new(NanomsgClient::new(NanomsgConfig::default(), processor.clone()));
+    let client_runner = client.clone();
+    let client_handle = tokio::spawn(async move { client_runner.start().await });
+
+    // 运行有限时间，直到两种服务都被回调处理
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while tokio::time::Instant::now() < deadline {
+        let done = {
+            let seen = seen_services.lock();
+            seen.contains("tracking") && seen.contains("traj")
+        };
+        if done {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    client.stop();
+    processor.stop();
+
+    let stats = processor.get_stats();
+    assert!(stats.messages_processed > 0);
+    assert!(client.get_stats().messages_received > 0);
+
+    let result = tokio::time::timeout(Duration::from_secs(1), processor_handle).await;
+    assert!(result.is_ok());
+    client_handle.abort();
+
+    let seen = seen_services.lock();
+    assert!(seen.contains("tracking"), "tracking not seen: {:?}", seen);
+    assert!(seen.contains("traj"), "traj not seen: {:?}", seen);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_shutdown_stops_client_before_processor() {
+    let processor = MessageProcessor::new();
+    processor.set_callback(Arc::new(|_| Ok(())));
+    let processor = Arc::new(processor);
+
+    let runner = processor.clone();
+    let processor_handle = tokio::spawn(async move { runner.start().await });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let client = Arc::new(NanomsgClient::new(NanomsgConfig::default(), processor.clone()));
+    let client_runner = client.clone();
+    let client_handle = tokio::spawn(async move { client_runner.start().await });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    shutdown(&client, &processor, Duration::from_secs(5)).await.unwrap();
+
+    // 客户端先停止接收，处理器清空队列后才停止，入队的消息都已处理
+    assert!(!client.is_receiving());
+    let stats = processor.get_stats();
+    assert!(stats.messages_received > 0);
+    assert_eq!(stats.messages_processed, stats.messages_received);
+
+    let result = tokio::time::timeout(Duration::from_secs(1), processor_handle).await;
+    assert!(result.is_ok());
+    let result = tokio::time::timeout(Duration::from_secs(1), client_handle).await;
+    assert!(result.is_ok());
+}